@@ -0,0 +1,82 @@
+use lodtree::coords::QuadVec;
+use lodtree::*;
+
+// finds the depth of a node by going down from the root, which is what had to be done before nodes cached their depth
+fn depth_from_root(root: QuadVec, position: QuadVec) -> usize {
+    let mut current = root;
+    let mut depth = root.depth as usize;
+
+    while current != position {
+        current = (0..QuadVec::num_children())
+            .map(|i| current.get_child(i))
+            .find(|child| child.contains_child_node(position))
+            .unwrap();
+
+        depth += 1;
+    }
+
+    depth
+}
+
+// the histogram, with the depth of every chunk found by going down from the root
+fn depth_histogram_from_root(tree: &mut Tree<(), QuadVec>) -> Vec<usize> {
+    let root = tree.get_root();
+    let mut histogram = Vec::new();
+
+    for position in tree.iter_chunk_positions() {
+        let depth = depth_from_root(root, position);
+
+        if histogram.len() <= depth {
+            histogram.resize(depth + 1, 0);
+        }
+
+        histogram[depth] += 1;
+    }
+
+    histogram
+}
+
+fn main() {
+    // create a quadtree
+    let mut tree = Tree::<(), QuadVec>::new(0);
+
+    // and move the target around a lot, so the tree is deep and the nodes are all over the place
+    for i in 0..64 {
+        let target = QuadVec::new((i * 37) % 4096, (i * 91) % 4096, 12);
+
+        while tree.prepare_update(&[target], 4, |_| ()) {
+            tree.do_update();
+            tree.complete_update();
+        }
+    }
+
+    println!("Num chunks in the tree: {}", tree.get_num_chunks());
+
+    // before, by going down from the root for every chunk
+    let start_time = std::time::Instant::now();
+    let mut before = Vec::new();
+
+    for _ in 0..100 {
+        before = depth_histogram_from_root(&mut tree);
+    }
+
+    let before_time = start_time.elapsed().as_micros();
+
+    // and after, with the cached depth
+    let start_time = std::time::Instant::now();
+    let mut after = Vec::new();
+
+    for _ in 0..100 {
+        after = tree.depth_histogram();
+    }
+
+    let after_time = start_time.elapsed().as_micros();
+
+    // both should give the same result
+    assert_eq!(before, after);
+
+    println!(
+        "Going down from the root: {} microseconds, cached depth: {} microseconds, for 100 histograms",
+        before_time, after_time
+    );
+}
@@ -0,0 +1,92 @@
+use lodtree::coords::QuadVec;
+use lodtree::*;
+
+struct Chunk {
+    // some data, so the chunks take up some space
+    data: [f32; 16],
+}
+
+impl Chunk {
+    fn new(position: QuadVec) -> Self {
+        Self {
+            data: [position.depth as f32; 16],
+        }
+    }
+}
+
+// iterates over all chunks, and looks all of them up by position, and returns how long both took in microseconds
+fn measure(tree: &mut Tree<Chunk, QuadVec>, positions: &[QuadVec]) -> (u128, u128) {
+    // go over all chunks a few times
+    let start_time = std::time::Instant::now();
+    let mut sum = 0.0;
+
+    for _ in 0..100 {
+        for chunk in tree.iter_chunks() {
+            sum += chunk.data[0];
+        }
+    }
+
+    let iteration = start_time.elapsed().as_micros();
+
+    // and find all chunks by their position
+    let start_time = std::time::Instant::now();
+
+    for _ in 0..10 {
+        for position in positions {
+            if let Some(chunk) = tree.get_chunk_from_position(*position) {
+                sum += chunk.data[0];
+            }
+        }
+    }
+
+    let lookup = start_time.elapsed().as_micros();
+
+    // make sure the work isn't optimized away
+    println!("Sum of all chunks: {}", sum);
+
+    (iteration, lookup)
+}
+
+fn main() {
+    // create a quadtree
+    let mut tree = Tree::<Chunk, QuadVec>::new(0);
+
+    // and move the target around a lot, so the nodes and chunks end up all over the place
+    for i in 0..64 {
+        let target = QuadVec::new((i * 37) % 256, (i * 91) % 256, 8);
+
+        while tree.prepare_update(&[target], 4, Chunk::new) {
+            tree.do_update();
+            tree.complete_update();
+        }
+    }
+
+    println!("Num chunks in the tree: {}", tree.get_num_chunks());
+
+    // all positions to look up
+    let positions = tree.iter_chunk_positions().collect::<Vec<_>>();
+
+    let (iteration, lookup) = measure(&mut tree, &positions);
+
+    println!(
+        "Before optimizing: {} microseconds to iterate, {} microseconds to look up",
+        iteration, lookup
+    );
+
+    // optimize the tree
+    let start_time = std::time::Instant::now();
+
+    tree.optimize();
+
+    println!(
+        "Took {} microseconds to optimize the tree",
+        start_time.elapsed().as_micros()
+    );
+
+    let (iteration, lookup) = measure(&mut tree, &positions);
+
+    println!(
+        "After optimizing: {} microseconds to iterate, {} microseconds to look up",
+        iteration, lookup
+    );
+}
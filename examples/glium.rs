@@ -54,7 +54,7 @@ fn main() {
     let index_buffer = glium::IndexBuffer::new(
         &display,
         PrimitiveType::TrianglesList,
-        &[0 as u16, 1, 2, 1, 2, 3],
+        &[0_u16, 1, 2, 1, 2, 3],
     )
     .unwrap();
 
@@ -173,6 +173,7 @@ fn main() {
                     scale: position.get_size() as f32,
                     state: chunk.cache_state,
                     selected: chunk.selected as i32,
+                    in_bounds: chunk.in_bounds as i32,
                 };
 
                 // draw it with glium
@@ -1,10 +1,24 @@
-//! Contains coordinate structs, QuadVec for quadtrees, and OctVec for octrees, as well as their LodVec implementation
+//! Contains coordinate structs, LineVec for binary trees, QuadVec for quadtrees, OctVec for octrees, and NVec for any number of axes, as well as their LodVec implementation
 
 use crate::traits::LodVec;
 
+use std::cmp::Ordering;
+
+/// An axis aligned bounding box, with DIM axes.
+/// Used by QuadVec and OctVec to give the area a node takes up in the tree, from 0 to 1 on all axes.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Aabb<const DIM: usize> {
+    /// lowest corner of the box.
+    pub min: [f64; DIM],
+
+    /// highest corner of the box.
+    pub max: [f64; DIM],
+}
+
 /// A Lod Vector for use in a quadtree.
 /// It subdivides into 4 children of equal size.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct QuadVec {
     /// x position in the quadtree.
     pub x: u64,
@@ -31,18 +45,21 @@ impl QuadVec {
     /// creates a new vector from floating point coords
     /// mapped so that (0, 0) is the bottom left corner and (1, 1) is the top right
     /// # Args
-    /// * `x` x coord of the float vector, from 0 to 1
-    /// * `y` y coord of the float vector, from 0 to 1
+    /// * `x` x coord of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
+    /// * `y` y coord of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
     /// * `depth` The lod depth of the coord
     #[inline]
     pub fn from_float_coords(x: f64, y: f64, depth: u8) -> Self {
         // scaling factor due to the lod depth
         let scale_factor = (1 << depth) as f64;
 
+        // highest coord at this depth, negative floats already become 0 when converting
+        let max = (1 << depth) - 1;
+
         // and get the actual coord
         Self {
-            x: (x * scale_factor) as u64,
-            y: (y * scale_factor) as u64,
+            x: ((x * scale_factor) as u64).min(max),
+            y: ((y * scale_factor) as u64).min(max),
             depth,
         }
     }
@@ -58,11 +75,119 @@ impl QuadVec {
         (self.x as f64 * scale_factor, self.y as f64 * scale_factor)
     }
 
-    /// gets the size the chunk of this lod vector takes up, with the root taking up.
+    /// gets the size the chunk of this lod vector takes up on each axis, with the root taking up 1.
+    /// Together with get_float_coords, this gives the box of the chunk.
+    /// ```rust
+    /// # use lodtree::coords::QuadVec;
+    /// assert_eq!(QuadVec::new(0, 0, 0).get_size(), 1.0);
+    /// assert_eq!(QuadVec::new(5, 2, 3).get_size(), 0.125);
+    /// ```
     #[inline]
     pub fn get_size(self) -> f64 {
         1.0 / (1 << self.depth) as f64
     }
+
+    /// converts the coord into float coords, at the center of the node.
+    /// Returns a tuple of (x: f64, y: f64) to represent the coordinates.
+    #[inline]
+    pub fn get_center_float_coords(self) -> (f64, f64) {
+        let (x, y) = self.get_float_coords();
+        let half_size = self.get_size() * 0.5;
+
+        (x + half_size, y + half_size)
+    }
+
+    /// linearly interpolates between the centers of this node and another node, in float coords.
+    /// # Args
+    /// * `other` the node to interpolate to
+    /// * `t` how far to interpolate, 0 gives the center of this node, and 1 the center of other
+    #[inline]
+    pub fn lerp_float(self, other: Self, t: f64) -> (f64, f64) {
+        let (self_x, self_y) = self.get_center_float_coords();
+        let (other_x, other_y) = other.get_center_float_coords();
+
+        (
+            self_x + (other_x - self_x) * t,
+            self_y + (other_y - self_y) * t,
+        )
+    }
+
+    /// iterates over the cells at neighbor_depth inside this node that touch one of it's faces.
+    /// These are the cells that line up with a finer neighbor on that side of the node.
+    /// # Args
+    /// * `neighbor_depth` the depth of the cells, this can't be lower than the depth of this node
+    /// * `axis` the axis of the face, 0 for x and 1 for y
+    /// * `positive` wether to use the face at the highest coordinate on the axis, instead of the lowest
+    #[inline]
+    pub fn shared_face_cells(
+        self,
+        neighbor_depth: u8,
+        axis: usize,
+        positive: bool,
+    ) -> impl Iterator<Item = Self> {
+        assert!(
+            neighbor_depth >= self.depth,
+            "neighbor depth can't be lower than the depth of the node"
+        );
+        assert!(axis < 2, "axis out of range");
+
+        // number of cells along each side of the node
+        let scale = neighbor_depth - self.depth;
+        let cells = 1 << scale;
+
+        // the lowest cell in the node
+        let base = [self.x << scale, self.y << scale];
+
+        // and where the face is on the axis
+        let face = base[axis] + if positive { cells - 1 } else { 0 };
+
+        (0..cells).map(move |i| {
+            let mut coords = [base[0] + i, base[1] + i];
+            coords[axis] = face;
+
+            Self::new(coords[0], coords[1], neighbor_depth)
+        })
+    }
+
+    /// gets the bounding box this node takes up in the tree, where the root spans from 0 to 1 on both axes.
+    #[inline]
+    pub fn aabb(self) -> Aabb<2> {
+        let (x, y) = self.get_float_coords();
+        let size = self.get_size();
+
+        Aabb {
+            min: [x, y],
+            max: [x + size, y + size],
+        }
+    }
+
+    /// creates the bounds that fully enclose a floating point box, for use with the bound iterators.
+    /// Coords are mapped the same way as from_float_coords, and clamped to the range of the tree.
+    /// Returns a tuple of (min, max), where max is exclusive.
+    /// # Args
+    /// * `min` lowest corner of the box, from 0 to 1
+    /// * `max` highest corner of the box, from 0 to 1
+    /// * `depth` The lod depth of the bounds
+    #[inline]
+    pub fn bounds_from_aabb(min: (f64, f64), max: (f64, f64), depth: u8) -> (Self, Self) {
+        // scaling factor due to the lod depth
+        let scale_factor = (1u64 << depth) as f64;
+
+        // min is rounded down, max is rounded up, that way the bounds enclose the box
+        let lower = |x: f64| (x * scale_factor).floor().clamp(0.0, scale_factor - 1.0) as u64;
+        let upper = |x: f64| (x * scale_factor).ceil().clamp(1.0, scale_factor) as u64;
+
+        let min = Self::new(lower(min.0), lower(min.1), depth);
+
+        // max is exclusive, so make sure there's at least one chunk in the bounds
+        let max = Self::new(
+            upper(max.0).max(min.x + 1),
+            upper(max.1).max(min.y + 1),
+            depth,
+        );
+
+        (min, max)
+    }
 }
 
 impl LodVec for QuadVec {
@@ -98,6 +223,26 @@ impl LodVec for QuadVec {
         }
     }
 
+    #[inline]
+    fn parent(self) -> Option<Self> {
+        // the root has no parent
+        if self.depth == 0 {
+            return None;
+        }
+
+        Some(Self {
+            x: self.x >> 1,
+            y: self.y >> 1,
+            depth: self.depth - 1,
+        })
+    }
+
+    #[inline]
+    fn get_child_index(self) -> usize {
+        // the lowest bit of each coordinate, same as in get_child
+        (self.x & 1 | (self.y & 1) << 1) as usize
+    }
+
     #[inline]
     fn can_subdivide(self, node: Self, detail: u64) -> bool {
         // return early if the level of this chunk is too high
@@ -108,27 +253,9 @@ impl LodVec for QuadVec {
         // difference in lod level between the target and the node
         let level_difference = self.depth - node.depth;
 
-        // minimum corner of the bounding box
-        let min = (
-            (node.x << (level_difference + 1))
-                .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-            (node.y << (level_difference + 1))
-                .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-        );
-
-        // max as well
-        let max = (
-            (node.x << (level_difference + 1))
-                .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-            (node.y << (level_difference + 1))
-                .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-        );
-
-        // local position of the target, which is one lod level higher to allow more detail
-        let local = (self.x << 1, self.y << 1);
-
-        // check if the target is inside of the bounding box
-        local.0 >= min.0 && local.0 < max.0 && local.1 >= min.1 && local.1 < max.1
+        // check if the target is inside of the bounding box around the node on both axes
+        in_subdivide_range(self.x, node.x, level_difference, detail)
+            && in_subdivide_range(self.y, node.y, level_difference, detail)
     }
 
     fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool {
@@ -160,6 +287,11 @@ impl LodVec for QuadVec {
 
     #[inline]
     fn contains_child_node(self, child: Self) -> bool {
+        // a node can't contain a node bigger than itself
+        if child.depth < self.depth {
+            return false;
+        }
+
         // basically, move the child node up to this level and check if they're equal
         let level_difference = child.depth - self.depth;
 
@@ -170,11 +302,84 @@ impl LodVec for QuadVec {
         // and check
         self.x == x && self.y == y
     }
+
+    type FloatCoords = [f64; 2];
+
+    #[inline]
+    fn get_float_coords(self) -> [f64; 2] {
+        // this calls the tuple version on QuadVec itself
+        let (x, y) = QuadVec::get_float_coords(self);
+        [x, y]
+    }
+
+    #[inline]
+    fn get_size(self) -> f64 {
+        QuadVec::get_size(self)
+    }
+
+    #[inline]
+    fn on_boundary(self, axis: usize, positive: bool) -> bool {
+        // coord on the axis
+        let coord = [self.x, self.y][axis];
+
+        // and check if it's on the edge
+        if positive {
+            coord + 1 == 1 << self.depth
+        } else {
+            coord == 0
+        }
+    }
+
+    #[inline]
+    fn containment_cmp(self, other: Self) -> Ordering {
+        // bring both to the depth of the shallowest one
+        let depth = self.depth.min(other.depth);
+        let a = [
+            self.x >> (self.depth - depth),
+            self.y >> (self.depth - depth),
+        ];
+        let b = [
+            other.x >> (other.depth - depth),
+            other.y >> (other.depth - depth),
+        ];
+
+        // if they are the same there, one contains the other, so the shallowest goes first
+        if a == b {
+            self.depth.cmp(&other.depth)
+        } else {
+            morton_cmp(&a, &b)
+        }
+    }
+
+    #[inline]
+    fn get_neighbor(self, direction: usize) -> Option<Self> {
+        // there's no axis for this direction
+        if direction >= 2 * 2 {
+            return None;
+        }
+
+        // can't go past the edge
+        if self.on_boundary(direction / 2, direction % 2 == 1) {
+            return None;
+        }
+
+        // otherwise move one step on the axis
+        let mut coords = [self.x, self.y];
+
+        if direction % 2 == 1 {
+            coords[direction / 2] += 1;
+        } else {
+            coords[direction / 2] -= 1;
+        }
+
+        Some(Self::new(coords[0], coords[1], self.depth))
+    }
 }
 
 /// A Lod Vector for use in an octree.
 /// It subdivides into 8 children of equal size.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OctVec {
     /// x position in the octree.
     pub x: u64,
@@ -205,20 +410,23 @@ impl OctVec {
     /// creates a new vector from floating point coords.
     /// mapped so that (0, 0, 0) is the front bottom left corner and (1, 1, 1) is the back top right.
     /// # Args
-    /// * `x` x coord of the float vector, from 0 to 1
-    /// * `y` y coord of the float vector, from 0 to 1
-    /// * `z` z coord of the float vector, from 0 to 1
+    /// * `x` x coord of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
+    /// * `y` y coord of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
+    /// * `z` z coord of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
     /// * `depth` The lod depth of the coord
     #[inline]
     pub fn from_float_coords(x: f64, y: f64, z: f64, depth: u8) -> Self {
         // scaling factor due to the lod depth
         let scale_factor = (1 << depth) as f64;
 
+        // highest coord at this depth, negative floats already become 0 when converting
+        let max = (1 << depth) - 1;
+
         // and get the actual coord
         Self {
-            x: (x * scale_factor) as u64,
-            y: (y * scale_factor) as u64,
-            z: (z * scale_factor) as u64,
+            x: ((x * scale_factor) as u64).min(max),
+            y: ((y * scale_factor) as u64).min(max),
+            z: ((z * scale_factor) as u64).min(max),
             depth,
         }
     }
@@ -238,11 +446,126 @@ impl OctVec {
         )
     }
 
-    /// gets the size the chunk of this lod vector takes up, with the root taking up.
+    /// gets the size the chunk of this lod vector takes up on each axis, with the root taking up 1.
+    /// Together with get_float_coords, this gives the box of the chunk.
+    /// ```rust
+    /// # use lodtree::coords::OctVec;
+    /// assert_eq!(OctVec::new(0, 0, 0, 0).get_size(), 1.0);
+    /// assert_eq!(OctVec::new(5, 2, 7, 3).get_size(), 0.125);
+    /// ```
     #[inline]
     pub fn get_size(self) -> f64 {
         1.0 / (1 << self.depth) as f64
     }
+
+    /// converts the coord into float coords, at the center of the node.
+    /// Returns a tuple of (x: f64, y: f64, z: f64) to represent the coordinates.
+    #[inline]
+    pub fn get_center_float_coords(self) -> (f64, f64, f64) {
+        let (x, y, z) = self.get_float_coords();
+        let half_size = self.get_size() * 0.5;
+
+        (x + half_size, y + half_size, z + half_size)
+    }
+
+    /// linearly interpolates between the centers of this node and another node, in float coords.
+    /// # Args
+    /// * `other` the node to interpolate to
+    /// * `t` how far to interpolate, 0 gives the center of this node, and 1 the center of other
+    #[inline]
+    pub fn lerp_float(self, other: Self, t: f64) -> (f64, f64, f64) {
+        let (self_x, self_y, self_z) = self.get_center_float_coords();
+        let (other_x, other_y, other_z) = other.get_center_float_coords();
+
+        (
+            self_x + (other_x - self_x) * t,
+            self_y + (other_y - self_y) * t,
+            self_z + (other_z - self_z) * t,
+        )
+    }
+
+    /// iterates over the cells at neighbor_depth inside this node that touch one of it's faces.
+    /// These are the cells that line up with a finer neighbor on that side of the node.
+    /// # Args
+    /// * `neighbor_depth` the depth of the cells, this can't be lower than the depth of this node
+    /// * `axis` the axis of the face, 0 for x, 1 for y and 2 for z
+    /// * `positive` wether to use the face at the highest coordinate on the axis, instead of the lowest
+    #[inline]
+    pub fn shared_face_cells(
+        self,
+        neighbor_depth: u8,
+        axis: usize,
+        positive: bool,
+    ) -> impl Iterator<Item = Self> {
+        assert!(
+            neighbor_depth >= self.depth,
+            "neighbor depth can't be lower than the depth of the node"
+        );
+        assert!(axis < 3, "axis out of range");
+
+        // number of cells along each side of the node
+        let scale = neighbor_depth - self.depth;
+        let cells = 1 << scale;
+
+        // the lowest cell in the node
+        let base = [self.x << scale, self.y << scale, self.z << scale];
+
+        // and where the face is on the axis
+        let face = base[axis] + if positive { cells - 1 } else { 0 };
+
+        // the other two axes span the face
+        let (first, second) = ((axis + 1) % 3, (axis + 2) % 3);
+
+        (0..cells * cells).map(move |i| {
+            let mut coords = base;
+            coords[axis] = face;
+            coords[first] += i % cells;
+            coords[second] += i / cells;
+
+            Self::new(coords[0], coords[1], coords[2], neighbor_depth)
+        })
+    }
+
+    /// gets the bounding box this node takes up in the tree, where the root spans from 0 to 1 on all axes.
+    #[inline]
+    pub fn aabb(self) -> Aabb<3> {
+        let (x, y, z) = self.get_float_coords();
+        let size = self.get_size();
+
+        Aabb {
+            min: [x, y, z],
+            max: [x + size, y + size, z + size],
+        }
+    }
+
+    /// creates the bounds that fully enclose a floating point box, for use with the bound iterators.
+    /// Coords are mapped the same way as from_float_coords, and clamped to the range of the tree.
+    /// Returns a tuple of (min, max), where max is exclusive.
+    /// # Args
+    /// * `min` lowest corner of the box, from 0 to 1
+    /// * `max` highest corner of the box, from 0 to 1
+    /// * `depth` The lod depth of the bounds
+    #[inline]
+    pub fn bounds_from_aabb(min: (f64, f64, f64), max: (f64, f64, f64), depth: u8) -> (Self, Self) {
+        // scaling factor due to the lod depth
+        let scale_factor = (1u64 << depth) as f64;
+
+        // min is rounded down, max is rounded up, that way the bounds enclose the box
+        let lower = |x: f64| (x * scale_factor).floor().clamp(0.0, scale_factor - 1.0) as u64;
+        let upper = |x: f64| (x * scale_factor).ceil().clamp(1.0, scale_factor) as u64;
+
+        let min = Self::new(lower(min.0), lower(min.1), lower(min.2), depth);
+
+        // max is exclusive, so make sure there's at least one chunk in the bounds
+        let max = Self::new(
+            upper(max.0).max(min.x + 1),
+            upper(max.1).max(min.y + 1),
+            upper(max.2).max(min.z + 1),
+            depth,
+        );
+
+        (min, max)
+    }
 }
 
 impl LodVec for OctVec {
@@ -282,6 +605,27 @@ impl LodVec for OctVec {
         }
     }
 
+    #[inline]
+    fn parent(self) -> Option<Self> {
+        // the root has no parent
+        if self.depth == 0 {
+            return None;
+        }
+
+        Some(Self {
+            x: self.x >> 1,
+            y: self.y >> 1,
+            z: self.z >> 1,
+            depth: self.depth - 1,
+        })
+    }
+
+    #[inline]
+    fn get_child_index(self) -> usize {
+        // the lowest bit of each coordinate, same as in get_child
+        (self.x & 1 | (self.y & 1) << 1 | (self.z & 1) << 2) as usize
+    }
+
     #[inline]
     fn can_subdivide(self, node: Self, detail: u64) -> bool {
         // return early if the level of this chunk is too high
@@ -292,36 +636,10 @@ impl LodVec for OctVec {
         // difference in lod level between the target and the node
         let level_difference = self.depth - node.depth;
 
-        // minimum corner of the bounding box
-        let min = (
-            (node.x << (level_difference + 1))
-                .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-            (node.y << (level_difference + 1))
-                .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-            (node.z << (level_difference + 1))
-                .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-        );
-
-        // max as well
-        let max = (
-            (node.x << (level_difference + 1))
-                .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-            (node.y << (level_difference + 1))
-                .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-            (node.z << (level_difference + 1))
-                .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-        );
-
-        // local position of the target
-        let local = (self.x << 1, self.y << 1, self.z << 1);
-
-        // check if the target is inside of the bounding box
-        local.0 >= min.0
-            && local.0 < max.0
-            && local.1 >= min.1
-            && local.1 < max.1
-            && local.2 >= min.2
-            && local.2 < max.2
+        // check if the target is inside of the bounding box around the node on all axes
+        in_subdivide_range(self.x, node.x, level_difference, detail)
+            && in_subdivide_range(self.y, node.y, level_difference, detail)
+            && in_subdivide_range(self.z, node.z, level_difference, detail)
     }
 
     fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool {
@@ -358,6 +676,11 @@ impl LodVec for OctVec {
 
     #[inline]
     fn contains_child_node(self, child: Self) -> bool {
+        // a node can't contain a node bigger than itself
+        if child.depth < self.depth {
+            return false;
+        }
+
         // basically, move the child node up to this level and check if they're equal
         let level_difference = child.depth - self.depth;
 
@@ -369,4 +692,1283 @@ impl LodVec for OctVec {
         // and check
         self.x == x && self.y == y && self.z == z
     }
+
+    type FloatCoords = [f64; 3];
+
+    #[inline]
+    fn get_float_coords(self) -> [f64; 3] {
+        // this calls the tuple version on OctVec itself
+        let (x, y, z) = OctVec::get_float_coords(self);
+        [x, y, z]
+    }
+
+    #[inline]
+    fn get_size(self) -> f64 {
+        OctVec::get_size(self)
+    }
+
+    #[inline]
+    fn on_boundary(self, axis: usize, positive: bool) -> bool {
+        // coord on the axis
+        let coord = [self.x, self.y, self.z][axis];
+
+        // and check if it's on the edge
+        if positive {
+            coord + 1 == 1 << self.depth
+        } else {
+            coord == 0
+        }
+    }
+
+    #[inline]
+    fn containment_cmp(self, other: Self) -> Ordering {
+        // bring both to the depth of the shallowest one
+        let depth = self.depth.min(other.depth);
+        let a = [
+            self.x >> (self.depth - depth),
+            self.y >> (self.depth - depth),
+            self.z >> (self.depth - depth),
+        ];
+        let b = [
+            other.x >> (other.depth - depth),
+            other.y >> (other.depth - depth),
+            other.z >> (other.depth - depth),
+        ];
+
+        // if they are the same there, one contains the other, so the shallowest goes first
+        if a == b {
+            self.depth.cmp(&other.depth)
+        } else {
+            morton_cmp(&a, &b)
+        }
+    }
+
+    #[inline]
+    fn get_neighbor(self, direction: usize) -> Option<Self> {
+        // there's no axis for this direction
+        if direction >= 2 * 3 {
+            return None;
+        }
+
+        // can't go past the edge
+        if self.on_boundary(direction / 2, direction % 2 == 1) {
+            return None;
+        }
+
+        // otherwise move one step on the axis
+        let mut coords = [self.x, self.y, self.z];
+
+        if direction % 2 == 1 {
+            coords[direction / 2] += 1;
+        } else {
+            coords[direction / 2] -= 1;
+        }
+
+        Some(Self::new(coords[0], coords[1], coords[2], self.depth))
+    }
+}
+
+/// A Lod Vector for use in a binary tree, for things that only have one axis.
+/// It subdivides into 2 children of equal size.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineVec {
+    /// position in the binary tree.
+    pub pos: u64,
+
+    /// lod depth in the binary tree.
+    /// this is limited, hence we use u8.
+    pub depth: u8,
+}
+
+impl LineVec {
+    /// creates a new vector from the raw position.
+    /// # Args
+    /// * `pos` The position in the tree. Allowed range scales with the depth (doubles as the depth increases by one)
+    /// * `depth` the lod depth the coord is at. This is soft limited at roughly 60, and the tree might behave weird if it gets higher
+    #[inline]
+    pub fn new(pos: u64, depth: u8) -> Self {
+        Self { pos, depth }
+    }
+
+    /// creates a new vector from a floating point position
+    /// mapped so that 0 is the start of the line and 1 is the end
+    /// # Args
+    /// * `pos` position of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
+    /// * `depth` The lod depth of the coord
+    #[inline]
+    pub fn from_float_coords(pos: f64, depth: u8) -> Self {
+        // scaling factor due to the lod depth
+        let scale_factor = (1u64 << depth) as f64;
+
+        // highest coord at this depth, negative floats already become 0 when converting
+        let max = (1 << depth) - 1;
+
+        // and get the actual coord
+        Self {
+            pos: ((pos * scale_factor) as u64).min(max),
+            depth,
+        }
+    }
+
+    /// converts the coord into a float position, at the start of the node.
+    #[inline]
+    pub fn get_float_coords(self) -> f64 {
+        // scaling factor to scale the coords down with
+        let scale_factor = 1.0 / (1u64 << self.depth) as f64;
+
+        self.pos as f64 * scale_factor
+    }
+
+    /// gets the size the chunk of this lod vector takes up, with the root taking up 1.
+    /// Together with get_float_coords, this gives the range of the chunk.
+    /// ```rust
+    /// # use lodtree::coords::LineVec;
+    /// assert_eq!(LineVec::new(0, 0).get_size(), 1.0);
+    /// assert_eq!(LineVec::new(5, 3).get_size(), 0.125);
+    /// ```
+    #[inline]
+    pub fn get_size(self) -> f64 {
+        1.0 / (1u64 << self.depth) as f64
+    }
+
+    /// converts the coord into a float position, at the center of the node.
+    #[inline]
+    pub fn get_center_float_coords(self) -> f64 {
+        self.get_float_coords() + self.get_size() * 0.5
+    }
+
+    /// gets the bounding box this node takes up in the tree, where the root spans from 0 to 1.
+    #[inline]
+    pub fn aabb(self) -> Aabb<1> {
+        let pos = self.get_float_coords();
+
+        Aabb {
+            min: [pos],
+            max: [pos + self.get_size()],
+        }
+    }
+}
+
+impl LodVec for LineVec {
+    #[inline]
+    fn num_children() -> usize {
+        2
+    }
+
+    #[inline]
+    fn root() -> Self {
+        Self { pos: 0, depth: 0 }
+    }
+
+    #[inline]
+    fn get_child(self, index: usize) -> Self {
+        // the position, doubled in scale, and incremented for the second child
+        Self {
+            pos: (self.pos << 1) + (index as u64 & 1),
+            depth: self.depth + 1,
+        }
+    }
+
+    #[inline]
+    fn parent(self) -> Option<Self> {
+        // the root has no parent
+        if self.depth == 0 {
+            return None;
+        }
+
+        Some(Self {
+            pos: self.pos >> 1,
+            depth: self.depth - 1,
+        })
+    }
+
+    #[inline]
+    fn get_child_index(self) -> usize {
+        // the lowest bit of the position, same as in get_child
+        (self.pos & 1) as usize
+    }
+
+    #[inline]
+    fn can_subdivide(self, node: Self, detail: u64) -> bool {
+        // return early if the level of this chunk is too high
+        if node.depth >= self.depth {
+            return false;
+        }
+
+        // difference in lod level between the target and the node
+        let level_difference = self.depth - node.depth;
+
+        // check if the target is inside of the bounding range around the node
+        in_subdivide_range(self.pos, node.pos, level_difference, detail)
+    }
+
+    fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool {
+        // get the lowest lod level
+        let level = self.depth.min(min.depth.min(max.depth));
+
+        // bring all coords to the lowest level
+        let self_difference = self.depth - level;
+        let min_difference = min.depth - level;
+        let max_difference = max.depth - level;
+
+        // get the coords to that level
+        let self_pos = self.pos >> self_difference;
+        let min_pos = min.pos >> min_difference;
+
+        let max_pos = max.pos >> max_difference;
+
+        // then check if we are inside the range
+        self.depth as u64 <= max_depth && self_pos >= min_pos && self_pos < max_pos
+    }
+
+    #[inline]
+    fn contains_child_node(self, child: Self) -> bool {
+        // a node can't contain a node bigger than itself
+        if child.depth < self.depth {
+            return false;
+        }
+
+        // move the child node up to this level and check if they're equal
+        self.pos == child.pos >> (child.depth - self.depth)
+    }
+
+    type FloatCoords = [f64; 1];
+
+    #[inline]
+    fn get_float_coords(self) -> [f64; 1] {
+        [LineVec::get_float_coords(self)]
+    }
+
+    #[inline]
+    fn get_size(self) -> f64 {
+        LineVec::get_size(self)
+    }
+
+    #[inline]
+    fn on_boundary(self, axis: usize, positive: bool) -> bool {
+        assert!(axis < 1, "axis out of range");
+
+        // check if it's on the edge
+        if positive {
+            self.pos + 1 == 1 << self.depth
+        } else {
+            self.pos == 0
+        }
+    }
+
+    #[inline]
+    fn containment_cmp(self, other: Self) -> Ordering {
+        // bring both to the depth of the shallowest one
+        let depth = self.depth.min(other.depth);
+        let a = self.pos >> (self.depth - depth);
+        let b = other.pos >> (other.depth - depth);
+
+        // if they are the same there, one contains the other, so the shallowest goes first
+        if a == b {
+            self.depth.cmp(&other.depth)
+        } else {
+            a.cmp(&b)
+        }
+    }
+
+    #[inline]
+    fn get_neighbor(self, direction: usize) -> Option<Self> {
+        // there's no axis for this direction
+        if direction >= 2 {
+            return None;
+        }
+
+        // can't go past the edge
+        if self.on_boundary(direction / 2, direction % 2 == 1) {
+            return None;
+        }
+
+        // otherwise move one step
+        if direction % 2 == 1 {
+            Some(Self::new(self.pos + 1, self.depth))
+        } else {
+            Some(Self::new(self.pos - 1, self.depth))
+        }
+    }
+}
+
+/// A Lod Vector for use in a tree with D axes, such as a 4d tree.
+/// It subdivides into 2^D children of equal size, and behaves the same as QuadVec for 2 axes, and OctVec for 3 axes.
+/// Because there are that many children, D is limited to a few axes in practice.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NVec<const D: usize> {
+    /// position on each axis in the tree.
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
+    pub pos: [u64; D],
+
+    /// lod depth in the tree.
+    /// this is limited, hence we use u8.
+    pub depth: u8,
+}
+
+impl<const D: usize> Default for NVec<D> {
+    fn default() -> Self {
+        Self {
+            pos: [0; D],
+            depth: 0,
+        }
+    }
+}
+
+impl<const D: usize> NVec<D> {
+    /// creates a new vector from the raw coords.
+    /// # Args
+    /// * `pos` The position in the tree on each axis. Allowed range scales with the depth (doubles as the depth increases by one)
+    /// * `depth` the lod depth the coord is at. This is soft limited at roughly 60, and the tree might behave weird if it gets higher
+    #[inline]
+    pub fn new(pos: [u64; D], depth: u8) -> Self {
+        Self { pos, depth }
+    }
+
+    /// creates a new vector from floating point coords
+    /// mapped so that all zeros is the lowest corner and all ones the highest
+    /// # Args
+    /// * `pos` coords of the float vector, from 0 to 1, anything outside of that is clamped to the edge of the tree
+    /// * `depth` The lod depth of the coord
+    #[inline]
+    pub fn from_float_coords(pos: [f64; D], depth: u8) -> Self {
+        // scaling factor due to the lod depth
+        let scale_factor = (1u64 << depth) as f64;
+
+        // highest coord at this depth, negative floats already become 0 when converting
+        let max = (1 << depth) - 1;
+
+        // and get the actual coords
+        Self {
+            pos: pos.map(|x| ((x * scale_factor) as u64).min(max)),
+            depth,
+        }
+    }
+
+    /// converts the coord into float coords, at the lowest corner of the node.
+    #[inline]
+    pub fn get_float_coords(self) -> [f64; D] {
+        // scaling factor to scale the coords down with
+        let scale_factor = 1.0 / (1u64 << self.depth) as f64;
+
+        self.pos.map(|x| x as f64 * scale_factor)
+    }
+
+    /// gets the size the chunk of this lod vector takes up on each axis, with the root taking up 1.
+    #[inline]
+    pub fn get_size(self) -> f64 {
+        1.0 / (1u64 << self.depth) as f64
+    }
+
+    /// gets the bounding box this node takes up in the tree, where the root spans from 0 to 1 on all axes.
+    #[inline]
+    pub fn aabb(self) -> Aabb<D> {
+        let min = self.get_float_coords();
+        let size = self.get_size();
+
+        Aabb {
+            min,
+            max: min.map(|x| x + size),
+        }
+    }
+}
+
+impl<const D: usize> LodVec for NVec<D> {
+    #[inline]
+    fn num_children() -> usize {
+        1 << D
+    }
+
+    #[inline]
+    fn root() -> Self {
+        Self::default()
+    }
+
+    #[inline]
+    fn get_child(self, index: usize) -> Self {
+        let mut pos = self.pos;
+
+        // the positions, doubled in scale, and incremented by the bit of the index for that axis
+        for (axis, x) in pos.iter_mut().enumerate() {
+            *x = (*x << 1) + ((index >> axis) as u64 & 1);
+        }
+
+        Self {
+            pos,
+            depth: self.depth + 1,
+        }
+    }
+
+    #[inline]
+    fn parent(self) -> Option<Self> {
+        // the root has no parent
+        if self.depth == 0 {
+            return None;
+        }
+
+        Some(Self {
+            pos: self.pos.map(|x| x >> 1),
+            depth: self.depth - 1,
+        })
+    }
+
+    #[inline]
+    fn get_child_index(self) -> usize {
+        // the lowest bit of each coordinate, same as in get_child
+        self.pos
+            .iter()
+            .enumerate()
+            .fold(0, |index, (axis, x)| index | ((x & 1) as usize) << axis)
+    }
+
+    #[inline]
+    fn can_subdivide(self, node: Self, detail: u64) -> bool {
+        // return early if the level of this chunk is too high
+        if node.depth >= self.depth {
+            return false;
+        }
+
+        // difference in lod level between the target and the node
+        let level_difference = self.depth - node.depth;
+
+        // check if the target is inside of the bounding box around the node on every axis
+        self.pos
+            .iter()
+            .zip(node.pos.iter())
+            .all(|(&target, &node)| in_subdivide_range(target, node, level_difference, detail))
+    }
+
+    fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool {
+        // get the lowest lod level
+        let level = self.depth.min(min.depth.min(max.depth));
+
+        // bring all coords to the lowest level
+        let self_difference = self.depth - level;
+        let min_difference = min.depth - level;
+        let max_difference = max.depth - level;
+
+        // then check if we are inside the AABB
+        self.depth as u64 <= max_depth
+            && (0..D).all(|axis| {
+                let self_x = self.pos[axis] >> self_difference;
+                let min_x = min.pos[axis] >> min_difference;
+                let max_x = max.pos[axis] >> max_difference;
+
+                self_x >= min_x && self_x < max_x
+            })
+    }
+
+    #[inline]
+    fn contains_child_node(self, child: Self) -> bool {
+        // a node can't contain a node bigger than itself
+        if child.depth < self.depth {
+            return false;
+        }
+
+        // move the child node up to this level and check if they're equal
+        let level_difference = child.depth - self.depth;
+
+        self.pos == child.pos.map(|x| x >> level_difference)
+    }
+
+    type FloatCoords = [f64; D];
+
+    #[inline]
+    fn get_float_coords(self) -> [f64; D] {
+        NVec::get_float_coords(self)
+    }
+
+    #[inline]
+    fn get_size(self) -> f64 {
+        NVec::get_size(self)
+    }
+
+    #[inline]
+    fn on_boundary(self, axis: usize, positive: bool) -> bool {
+        // coord on the axis
+        let coord = self.pos[axis];
+
+        // and check if it's on the edge
+        if positive {
+            coord + 1 == 1 << self.depth
+        } else {
+            coord == 0
+        }
+    }
+
+    #[inline]
+    fn containment_cmp(self, other: Self) -> Ordering {
+        // bring both to the depth of the shallowest one
+        let depth = self.depth.min(other.depth);
+        let a = self.pos.map(|x| x >> (self.depth - depth));
+        let b = other.pos.map(|x| x >> (other.depth - depth));
+
+        // if they are the same there, one contains the other, so the shallowest goes first
+        if a == b {
+            self.depth.cmp(&other.depth)
+        } else {
+            morton_cmp(&a, &b)
+        }
+    }
+
+    #[inline]
+    fn get_neighbor(self, direction: usize) -> Option<Self> {
+        // there's no axis for this direction
+        if direction >= 2 * D {
+            return None;
+        }
+
+        // can't go past the edge
+        if self.on_boundary(direction / 2, direction % 2 == 1) {
+            return None;
+        }
+
+        // otherwise move one step on the axis
+        let mut pos = self.pos;
+
+        if direction % 2 == 1 {
+            pos[direction / 2] += 1;
+        } else {
+            pos[direction / 2] -= 1;
+        }
+
+        Some(Self::new(pos, self.depth))
+    }
+}
+
+// wether a target is close enough to a node on one axis for the node to subdivide, where level_difference is how much deeper the target is
+// this is computed with u128, so a big detail or level difference doesn't overflow
+#[inline]
+fn in_subdivide_range(target: u64, node: u64, level_difference: u8, detail: u64) -> bool {
+    // position of the node, one lod level deeper than the target
+    let node = (node as u128) << (level_difference + 1);
+
+    // how far the range goes around the node
+    let detail = (detail as u128 + 1) << level_difference;
+    let half_size = 1u128 << level_difference;
+
+    // minimum of the bounding range
+    let min = node.saturating_sub(detail - half_size);
+
+    // max as well
+    let max = node.saturating_add(detail + half_size);
+
+    // local position of the target, which is one lod level higher to allow more detail
+    let local = (target as u128) << 1;
+
+    local >= min && local < max
+}
+
+// serde only implements arrays up to a fixed size, so NVec uses this to (de)serialize it's position as a tuple of D numbers
+#[cfg(feature = "serde")]
+mod serde_array {
+    use serde::de::{Error, SeqAccess, Visitor};
+    use serde::ser::SerializeTuple;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, const D: usize>(
+        array: &[u64; D],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(D)?;
+
+        for x in array {
+            tuple.serialize_element(x)?;
+        }
+
+        tuple.end()
+    }
+
+    pub fn deserialize<'de, De: Deserializer<'de>, const D: usize>(
+        deserializer: De,
+    ) -> Result<[u64; D], De::Error> {
+        deserializer.deserialize_tuple(D, ArrayVisitor)
+    }
+
+    struct ArrayVisitor<const D: usize>;
+
+    impl<'de, const D: usize> Visitor<'de> for ArrayVisitor<D> {
+        type Value = [u64; D];
+
+        fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(formatter, "an array of {} integers", D)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<[u64; D], A::Error> {
+            let mut array = [0; D];
+
+            for (i, x) in array.iter_mut().enumerate() {
+                *x = seq
+                    .next_element()?
+                    .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+            }
+
+            Ok(array)
+        }
+    }
+}
+
+// compares coords at the same depth in Morton order, where later axes are more significant
+// this is decided by the highest bit that differs, and the last axis if multiple axes differ there
+fn morton_cmp(a: &[u64], b: &[u64]) -> Ordering {
+    let mut highest = None;
+
+    for (axis, (a, b)) in a.iter().zip(b).enumerate() {
+        let difference = a ^ b;
+
+        if difference != 0 {
+            let bit = 63 - difference.leading_zeros();
+
+            if highest.map_or(true, |(highest_bit, _)| bit >= highest_bit) {
+                highest = Some((bit, axis));
+            }
+        }
+    }
+
+    match highest {
+        Some((_, axis)) => a[axis].cmp(&b[axis]),
+        None => Ordering::Equal,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn bounds_from_aabb() {
+        // box to enclose
+        let (box_min, box_max) = ((0.3, 0.1), (0.6, 0.45));
+
+        let (min, max) = QuadVec::bounds_from_aabb(box_min, box_max, 3);
+
+        assert_eq!(min, QuadVec::new(2, 0, 3));
+        assert_eq!(max, QuadVec::new(5, 4, 3));
+
+        // and check the box is inside the bounds
+        let (min_x, min_y) = min.get_float_coords();
+        let (max_x, max_y) = max.get_float_coords();
+
+        assert!(min_x <= box_min.0 && min_y <= box_min.1);
+        assert!(max_x >= box_max.0 && max_y >= box_max.1);
+
+        // same for the octree, with a box partially outside of the tree
+        let (min, max) = OctVec::bounds_from_aabb((-0.5, 0.25, 0.7), (0.2, 0.5, 1.5), 2);
+
+        assert_eq!(min, OctVec::new(0, 1, 2, 2));
+        assert_eq!(max, OctVec::new(1, 2, 4, 2));
+    }
+
+    // gets the bounding box of any node, only using the LodVec trait
+    fn get_aabb<L: LodVec>(position: L) -> (L::FloatCoords, L::FloatCoords) {
+        let min = position.get_float_coords();
+        let mut max = min;
+
+        for axis in max.as_mut() {
+            *axis += position.get_size();
+        }
+
+        (min, max)
+    }
+
+    #[test]
+    fn generic_aabb() {
+        // the root spans the entire tree
+        assert_eq!(get_aabb(QuadVec::root()), ([0.0, 0.0], [1.0, 1.0]));
+        assert_eq!(get_aabb(OctVec::root()), ([0.0, 0.0, 0.0], [1.0, 1.0, 1.0]));
+
+        // and children only a part of it
+        assert_eq!(get_aabb(QuadVec::new(1, 2, 2)), ([0.25, 0.5], [0.5, 0.75]));
+        assert_eq!(
+            get_aabb(OctVec::new(1, 0, 3, 2)),
+            ([0.25, 0.0, 0.75], [0.5, 0.25, 1.0])
+        );
+    }
+
+    #[test]
+    fn on_boundary() {
+        // the root touches every face
+        for axis in 0..2 {
+            assert!(QuadVec::root().on_boundary(axis, false));
+            assert!(QuadVec::root().on_boundary(axis, true));
+        }
+
+        // a corner
+        let corner = QuadVec::new(7, 0, 3);
+        assert!(corner.on_boundary(0, true));
+        assert!(!corner.on_boundary(0, false));
+        assert!(corner.on_boundary(1, false));
+        assert!(!corner.on_boundary(1, true));
+
+        // an edge
+        let edge = OctVec::new(3, 0, 5, 3);
+        assert!(edge.on_boundary(1, false));
+        assert!(!edge.on_boundary(1, true));
+        assert!(!edge.on_boundary(0, false));
+        assert!(!edge.on_boundary(0, true));
+        assert!(!edge.on_boundary(2, false));
+        assert!(!edge.on_boundary(2, true));
+
+        // and something in the middle
+        let interior = OctVec::new(3, 4, 5, 3);
+        for axis in 0..3 {
+            assert!(!interior.on_boundary(axis, false));
+            assert!(!interior.on_boundary(axis, true));
+        }
+    }
+
+    #[test]
+    fn aabb() {
+        // the root is the unit box
+        assert_eq!(
+            QuadVec::root().aabb(),
+            Aabb {
+                min: [0.0, 0.0],
+                max: [1.0, 1.0]
+            }
+        );
+        assert_eq!(
+            OctVec::root().aabb(),
+            Aabb {
+                min: [0.0, 0.0, 0.0],
+                max: [1.0, 1.0, 1.0]
+            }
+        );
+
+        // and a child is a part of it
+        assert_eq!(
+            QuadVec::root().get_child(1).aabb(),
+            Aabb {
+                min: [0.5, 0.0],
+                max: [1.0, 0.5]
+            }
+        );
+        assert_eq!(
+            OctVec::new(1, 3, 2, 2).aabb(),
+            Aabb {
+                min: [0.25, 0.75, 0.5],
+                max: [0.5, 1.0, 0.75]
+            }
+        );
+    }
+
+    #[test]
+    fn lerp_float() {
+        let a = QuadVec::new(1, 2, 2);
+        let b = QuadVec::new(7, 0, 3);
+
+        // the ends are the centers
+        assert_eq!(a.lerp_float(b, 0.0), a.get_center_float_coords());
+        assert_eq!(a.lerp_float(b, 1.0), b.get_center_float_coords());
+        assert_eq!(a.get_center_float_coords(), (0.375, 0.625));
+
+        // and halfway is in between
+        assert_eq!(a.lerp_float(b, 0.5), (0.65625, 0.34375));
+
+        let a = OctVec::new(1, 2, 3, 2);
+        let b = OctVec::root();
+
+        assert_eq!(a.lerp_float(b, 0.0), a.get_center_float_coords());
+        assert_eq!(a.lerp_float(b, 1.0), (0.5, 0.5, 0.5));
+    }
+
+    #[test]
+    fn shared_face_cells() {
+        let node = QuadVec::new(1, 2, 2);
+
+        // the positive x face
+        assert_eq!(
+            node.shared_face_cells(4, 0, true).collect::<Vec<_>>(),
+            vec![
+                QuadVec::new(7, 8, 4),
+                QuadVec::new(7, 9, 4),
+                QuadVec::new(7, 10, 4),
+                QuadVec::new(7, 11, 4),
+            ]
+        );
+
+        // the negative y face
+        assert_eq!(
+            node.shared_face_cells(4, 1, false).collect::<Vec<_>>(),
+            vec![
+                QuadVec::new(4, 8, 4),
+                QuadVec::new(5, 8, 4),
+                QuadVec::new(6, 8, 4),
+                QuadVec::new(7, 8, 4),
+            ]
+        );
+
+        // at the same depth, it's only the node itself
+        assert_eq!(
+            node.shared_face_cells(2, 0, false).collect::<Vec<_>>(),
+            vec![node]
+        );
+
+        // and for an octree, the face is a square
+        let node = OctVec::new(1, 2, 3, 2);
+        let cells = node.shared_face_cells(4, 2, true).collect::<Vec<_>>();
+
+        assert_eq!(cells.len(), 16);
+
+        for cell in cells.iter() {
+            assert!(node.contains_child_node(*cell));
+            assert_eq!(cell.z, 15);
+            assert!(cell.on_boundary(2, true));
+        }
+
+        // all distinct
+        for (i, cell) in cells.iter().enumerate() {
+            assert!(!cells[..i].contains(cell));
+        }
+    }
+
+    #[test]
+    fn parent() {
+        // the root has no parent
+        assert_eq!(QuadVec::root().parent(), None);
+        assert_eq!(OctVec::root().parent(), None);
+
+        // some positions at different depths
+        let mut seed = 0x2545f4914f6cdd1du64;
+        let mut random = || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for depth in 1..20u8 {
+            let mask = (1 << depth) - 1;
+            let quad = QuadVec::new(random() & mask, random() & mask, depth);
+            let oct = OctVec::new(random() & mask, random() & mask, random() & mask, depth);
+
+            // going to the parent and back gives the same node
+            let quad_parent = quad.parent().unwrap();
+            let oct_parent = oct.parent().unwrap();
+
+            assert_eq!(quad_parent.depth, depth - 1);
+            assert_eq!(oct_parent.depth, depth - 1);
+            assert!((0..4).any(|i| quad_parent.get_child(i) == quad));
+            assert!((0..8).any(|i| oct_parent.get_child(i) == oct));
+        }
+
+        assert_eq!(QuadVec::new(3, 6, 3).parent(), Some(QuadVec::new(1, 3, 2)));
+        assert_eq!(
+            OctVec::new(5, 0, 7, 3).parent(),
+            Some(OctVec::new(2, 0, 3, 2))
+        );
+    }
+
+    #[test]
+    fn get_child_index() {
+        let quad = QuadVec::new(5, 2, 3);
+        let oct = OctVec::new(1, 6, 3, 3);
+
+        // every child knows where it is in the parent
+        for i in 0..QuadVec::num_children() {
+            assert_eq!(quad.get_child(i).get_child_index(), i);
+            assert_eq!(quad.get_child(i).parent(), Some(quad));
+        }
+
+        for i in 0..OctVec::num_children() {
+            assert_eq!(oct.get_child(i).get_child_index(), i);
+            assert_eq!(oct.get_child(i).parent(), Some(oct));
+        }
+
+        // and the other way around
+        assert_eq!(
+            quad.parent().unwrap().get_child(quad.get_child_index()),
+            quad
+        );
+        assert_eq!(oct.parent().unwrap().get_child(oct.get_child_index()), oct);
+    }
+
+    #[test]
+    fn get_neighbor() {
+        // the root has no neighbors
+        for direction in 0..4 {
+            assert_eq!(QuadVec::root().get_neighbor(direction), None);
+        }
+
+        for direction in 0..6 {
+            assert_eq!(OctVec::root().get_neighbor(direction), None);
+        }
+
+        // at 0 there's nothing lower
+        let quad = QuadVec::new(0, 0, 3);
+        assert_eq!(quad.get_neighbor(0), None);
+        assert_eq!(quad.get_neighbor(1), Some(QuadVec::new(1, 0, 3)));
+        assert_eq!(quad.get_neighbor(2), None);
+        assert_eq!(quad.get_neighbor(3), Some(QuadVec::new(0, 1, 3)));
+
+        // and at the max there's nothing higher
+        let quad = QuadVec::new(7, 7, 3);
+        assert_eq!(quad.get_neighbor(0), Some(QuadVec::new(6, 7, 3)));
+        assert_eq!(quad.get_neighbor(1), None);
+        assert_eq!(quad.get_neighbor(2), Some(QuadVec::new(7, 6, 3)));
+        assert_eq!(quad.get_neighbor(3), None);
+
+        // same for octrees
+        let oct = OctVec::new(0, 3, 0, 2);
+        assert_eq!(oct.get_neighbor(0), None);
+        assert_eq!(oct.get_neighbor(1), Some(OctVec::new(1, 3, 0, 2)));
+        assert_eq!(oct.get_neighbor(2), Some(OctVec::new(0, 2, 0, 2)));
+        assert_eq!(oct.get_neighbor(3), None);
+        assert_eq!(oct.get_neighbor(4), None);
+        assert_eq!(oct.get_neighbor(5), Some(OctVec::new(0, 3, 1, 2)));
+
+        let oct = OctVec::new(3, 0, 3, 2);
+        assert_eq!(oct.get_neighbor(1), None);
+        assert_eq!(oct.get_neighbor(2), None);
+        assert_eq!(oct.get_neighbor(4), Some(OctVec::new(3, 0, 2, 2)));
+        assert_eq!(oct.get_neighbor(5), None);
+
+        // directions past the last axis have no neighbor
+        assert_eq!(QuadVec::new(3, 5, 3).get_neighbor(4), None);
+        assert_eq!(OctVec::new(1, 1, 1, 2).get_neighbor(6), None);
+        assert_eq!(LineVec::new(1, 2).get_neighbor(2), None);
+        assert_eq!(NVec::new([1, 1, 1, 1], 2).get_neighbor(8), None);
+        assert_eq!(NVec::new([1, 1, 1, 1], 2).get_neighbor(usize::MAX), None);
+
+        // and going back gives the same node
+        let quad = QuadVec::new(3, 5, 3);
+        for direction in 0..4 {
+            assert_eq!(
+                quad.get_neighbor(direction)
+                    .and_then(|x| x.get_neighbor(direction ^ 1)),
+                Some(quad)
+            );
+        }
+    }
+
+    #[test]
+    fn hash() {
+        use std::collections::HashSet;
+
+        // duplicates are only stored once
+        let quads = [
+            QuadVec::new(1, 2, 3),
+            QuadVec::new(1, 2, 4),
+            QuadVec::new(2, 1, 3),
+            QuadVec::new(1, 2, 3),
+        ]
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
+
+        assert_eq!(quads.len(), 3);
+        assert!(quads.contains(&QuadVec::new(1, 2, 4)));
+        assert!(!quads.contains(&QuadVec::new(2, 1, 4)));
+
+        let octs = [
+            OctVec::new(1, 2, 3, 3),
+            OctVec::new(3, 2, 1, 3),
+            OctVec::new(1, 2, 3, 3),
+        ]
+        .iter()
+        .copied()
+        .collect::<HashSet<_>>();
+
+        assert_eq!(octs.len(), 2);
+        assert!(octs.contains(&OctVec::new(3, 2, 1, 3)));
+        assert!(!octs.contains(&OctVec::new(1, 2, 3, 4)));
+    }
+
+    #[test]
+    fn containment_cmp() {
+        // a mix of nodes at different depths
+        let mut quads = vec![
+            QuadVec::new(3, 3, 2),
+            QuadVec::new(1, 0, 1),
+            QuadVec::new(0, 0, 2),
+            QuadVec::new(5, 1, 3),
+            QuadVec::root(),
+            QuadVec::new(0, 1, 1),
+            QuadVec::new(1, 1, 1),
+            QuadVec::new(2, 0, 2),
+            QuadVec::new(0, 0, 1),
+            QuadVec::new(7, 7, 3),
+        ];
+
+        quads.sort_by(|a, b| a.containment_cmp(*b));
+
+        // every node comes before all nodes it contains
+        for (i, quad) in quads.iter().enumerate() {
+            assert!(quads[..i]
+                .iter()
+                .all(|other| !quad.contains_child_node(*other) || other == quad));
+        }
+
+        assert_eq!(
+            quads,
+            vec![
+                QuadVec::root(),
+                QuadVec::new(0, 0, 1),
+                QuadVec::new(0, 0, 2),
+                QuadVec::new(1, 0, 1),
+                QuadVec::new(2, 0, 2),
+                QuadVec::new(5, 1, 3),
+                QuadVec::new(0, 1, 1),
+                QuadVec::new(1, 1, 1),
+                QuadVec::new(3, 3, 2),
+                QuadVec::new(7, 7, 3),
+            ]
+        );
+
+        // and children are in the same order as their index
+        let oct = OctVec::new(1, 2, 3, 2);
+
+        for i in 1..8 {
+            assert_eq!(
+                oct.get_child(i - 1).containment_cmp(oct.get_child(i)),
+                Ordering::Less
+            );
+        }
+
+        assert_eq!(oct.containment_cmp(oct), Ordering::Equal);
+        assert_eq!(oct.get_child(7).containment_cmp(oct), Ordering::Greater);
+        assert_eq!(
+            OctVec::new(0, 0, 1, 1).containment_cmp(OctVec::new(1, 1, 0, 1)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn from_float_coords_clamped() {
+        // outside of the tree on all sides
+        assert_eq!(
+            QuadVec::from_float_coords(-0.5, 1.0, 3),
+            QuadVec::new(0, 7, 3)
+        );
+        assert_eq!(
+            QuadVec::from_float_coords(1.5, -0.5, 3),
+            QuadVec::new(7, 0, 3)
+        );
+        assert_eq!(
+            OctVec::from_float_coords(-0.5, 1.0, 1.5, 2),
+            OctVec::new(0, 3, 3, 2)
+        );
+
+        // and the root is the only node at depth 0
+        assert_eq!(QuadVec::from_float_coords(1.5, 1.0, 0), QuadVec::root());
+        assert_eq!(OctVec::from_float_coords(-0.5, 1.0, 1.5, 0), OctVec::root());
+
+        // while the positions are still inside of the tree
+        for depth in [0, 1, 5, 20] {
+            for value in [-0.5, 1.0, 1.5] {
+                let quad = QuadVec::from_float_coords(value, value, depth);
+                let oct = OctVec::from_float_coords(value, value, value, depth);
+
+                assert_eq!(quad.depth, depth);
+                assert_eq!(oct.depth, depth);
+                assert!(QuadVec::root().contains_child_node(quad));
+                assert!(OctVec::root().contains_child_node(oct));
+            }
+        }
+    }
+
+    #[test]
+    fn line_vec() {
+        let node = LineVec::new(5, 3);
+
+        // children
+        assert_eq!(node.get_child(0), LineVec::new(10, 4));
+        assert_eq!(node.get_child(1), LineVec::new(11, 4));
+        assert_eq!(node.get_child(1).parent(), Some(node));
+        assert_eq!(node.get_child(1).get_child_index(), 1);
+        assert!(node.contains_child_node(LineVec::new(23, 5)));
+        assert!(!node.contains_child_node(LineVec::new(24, 5)));
+
+        // float coords
+        assert_eq!(node.get_float_coords(), 0.625);
+        assert_eq!(node.get_center_float_coords(), 0.6875);
+        assert_eq!(LineVec::from_float_coords(0.7, 3), node);
+        assert_eq!(LineVec::from_float_coords(1.5, 3), LineVec::new(7, 3));
+
+        // subdividing
+        assert!(LineVec::new(20, 5).can_subdivide(node, 0));
+        assert!(!LineVec::new(24, 5).can_subdivide(node, 0));
+        assert!(LineVec::new(24, 5).can_subdivide(node, 1));
+
+        // and bounds
+        assert!(node.is_inside_bounds(LineVec::new(4, 3), LineVec::new(6, 3), 3));
+        assert!(!node.is_inside_bounds(LineVec::new(6, 3), LineVec::new(8, 3), 3));
+
+        // neighbors
+        assert_eq!(node.get_neighbor(0), Some(LineVec::new(4, 3)));
+        assert_eq!(node.get_neighbor(1), Some(LineVec::new(6, 3)));
+        assert_eq!(LineVec::new(7, 3).get_neighbor(1), None);
+        assert_eq!(LineVec::new(0, 3).get_neighbor(0), None);
+    }
+
+    #[test]
+    fn nvec_matches_quadvec() {
+        let positions = [
+            (0, 0, 0),
+            (1, 0, 1),
+            (3, 2, 2),
+            (5, 9, 4),
+            (12, 3, 4),
+            (100, 37, 7),
+        ];
+
+        for &(x, y, depth) in positions.iter() {
+            let quad = QuadVec::new(x, y, depth);
+            let n = NVec::new([x, y], depth);
+
+            // same children
+            for i in 0..4 {
+                let quad_child = quad.get_child(i);
+
+                assert_eq!(
+                    n.get_child(i),
+                    NVec::new([quad_child.x, quad_child.y], quad_child.depth)
+                );
+                assert_eq!(n.get_child(i).get_child_index(), i);
+            }
+
+            // and they subdivide the same for targets around it
+            for &(target_x, target_y, target_depth) in positions.iter() {
+                for detail in 0..4 {
+                    assert_eq!(
+                        QuadVec::new(target_x, target_y, target_depth).can_subdivide(quad, detail),
+                        NVec::new([target_x, target_y], target_depth).can_subdivide(n, detail),
+                    );
+                }
+            }
+
+            assert_eq!(n.get_float_coords(), LodVec::get_float_coords(quad));
+        }
+    }
+
+    #[test]
+    fn can_subdivide_large_detail() {
+        // a huge detail subdivides everything above the target
+        assert!(QuadVec::new(3, 5, 4).can_subdivide(QuadVec::new(1, 0, 1), u64::MAX));
+        assert!(OctVec::new(60, 2, 9, 6).can_subdivide(OctVec::root(), u64::MAX));
+        assert!(LineVec::new(1 << 40, 60).can_subdivide(LineVec::new(0, 1), u64::MAX));
+        assert!(NVec::new([0, 7, 3, 1], 3).can_subdivide(NVec::new([1, 0, 1, 1], 1), u64::MAX));
+
+        // but still nothing at or below it
+        assert!(!QuadVec::new(3, 5, 4).can_subdivide(QuadVec::new(3, 5, 4), u64::MAX));
+        assert!(!QuadVec::new(3, 5, 4).can_subdivide(QuadVec::new(0, 0, 5), u64::MAX));
+
+        // and it works in a deep tree as well
+        let target = QuadVec::new((1 << 60) - 1, 0, 60);
+        assert!(target.can_subdivide(QuadVec::new(0, 1, 1), u64::MAX));
+        assert!(target.can_subdivide(QuadVec::new(0, 0, 59), u64::MAX / 2));
+
+        // while a small detail still only subdivides close by
+        assert!(target.can_subdivide(QuadVec::new((1 << 59) - 1, 0, 59), 0));
+        assert!(!target.can_subdivide(QuadVec::new(0, 0, 59), 0));
+    }
+
+    #[test]
+    fn is_inside_bounds_edges() {
+        let (min, max) = (QuadVec::new(2, 2, 3), QuadVec::new(5, 4, 3));
+
+        // min is inclusive
+        assert!(QuadVec::new(2, 2, 3).is_inside_bounds(min, max, 3));
+        assert!(!QuadVec::new(1, 2, 3).is_inside_bounds(min, max, 3));
+        assert!(!QuadVec::new(2, 1, 3).is_inside_bounds(min, max, 3));
+
+        // and max exclusive
+        assert!(QuadVec::new(4, 3, 3).is_inside_bounds(min, max, 3));
+        assert!(!QuadVec::new(5, 3, 3).is_inside_bounds(min, max, 3));
+        assert!(!QuadVec::new(4, 4, 3).is_inside_bounds(min, max, 3));
+
+        // bigger nodes are compared with max rounded down
+        assert!(QuadVec::new(1, 1, 2).is_inside_bounds(min, max, 3));
+        assert!(QuadVec::new(0, 0, 1).is_inside_bounds(min, max, 3));
+        assert!(!QuadVec::new(1, 1, 1).is_inside_bounds(min, max, 3));
+
+        // so they aren't inside if they only partly overlap the max side of the box
+        assert!(!QuadVec::new(2, 1, 2).is_inside_bounds(min, max, 3));
+        assert!(!QuadVec::new(0, 0, 0).is_inside_bounds(min, max, 3));
+
+        // smaller ones inside the cells of the box as well
+        assert!(QuadVec::new(9, 7, 4).is_inside_bounds(min, max, 4));
+        assert!(!QuadVec::new(10, 7, 4).is_inside_bounds(min, max, 4));
+
+        // but not when they're deeper than max depth
+        assert!(!QuadVec::new(9, 7, 4).is_inside_bounds(min, max, 3));
+
+        // same for octrees
+        let (min, max) = (OctVec::new(1, 1, 1, 2), OctVec::new(3, 3, 3, 2));
+
+        assert!(OctVec::new(1, 1, 1, 2).is_inside_bounds(min, max, 2));
+        assert!(OctVec::new(2, 2, 2, 2).is_inside_bounds(min, max, 2));
+        assert!(!OctVec::new(3, 2, 2, 2).is_inside_bounds(min, max, 2));
+        assert!(!OctVec::new(2, 2, 0, 2).is_inside_bounds(min, max, 2));
+    }
+
+    #[test]
+    fn contains_child_node_edges() {
+        let node = QuadVec::new(1, 1, 1);
+
+        // a node contains itself
+        assert!(node.contains_child_node(node));
+
+        // and it's children at any depth, up to the edges
+        assert!(node.contains_child_node(QuadVec::new(2, 2, 2)));
+        assert!(node.contains_child_node(QuadVec::new(3, 3, 2)));
+        assert!(node.contains_child_node(QuadVec::new(7, 4, 3)));
+
+        // but not nodes next to it, or bigger ones
+        assert!(!node.contains_child_node(QuadVec::new(1, 2, 2)));
+        assert!(!node.contains_child_node(QuadVec::new(3, 8, 3)));
+        assert!(!node.contains_child_node(QuadVec::root()));
+
+        let node = OctVec::new(0, 1, 0, 1);
+
+        assert!(node.contains_child_node(OctVec::new(1, 3, 1, 2)));
+        assert!(!node.contains_child_node(OctVec::new(2, 3, 1, 2)));
+        assert!(!node.contains_child_node(OctVec::new(1, 1, 1, 2)));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde_round_trip() {
+        let quads = vec![
+            QuadVec::new(0, 0, 0),
+            QuadVec::new(5, 9, 4),
+            QuadVec::new(u64::MAX, 3, 63),
+        ];
+
+        // json
+        let json = serde_json::to_string(&quads).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<QuadVec>>(&json).unwrap(), quads);
+
+        // and bincode
+        let bytes = bincode::serialize(&quads).unwrap();
+        assert_eq!(bincode::deserialize::<Vec<QuadVec>>(&bytes).unwrap(), quads);
+
+        // same for the other coords
+        let oct = OctVec::new(1, 2, 3, 4);
+        let line = LineVec::new(7, 3);
+        let n = NVec::new([1, 2, 3, 4], 5);
+
+        assert_eq!(
+            serde_json::from_str::<OctVec>(&serde_json::to_string(&oct).unwrap()).unwrap(),
+            oct
+        );
+        assert_eq!(
+            serde_json::from_str::<LineVec>(&serde_json::to_string(&line).unwrap()).unwrap(),
+            line
+        );
+        assert_eq!(
+            serde_json::from_str::<NVec<4>>(&serde_json::to_string(&n).unwrap()).unwrap(),
+            n
+        );
+        assert_eq!(
+            bincode::deserialize::<NVec<4>>(&bincode::serialize(&n).unwrap()).unwrap(),
+            n
+        );
+
+        // and the wrong number of axes doesn't work
+        assert!(serde_json::from_str::<NVec<5>>(&serde_json::to_string(&n).unwrap()).is_err());
+    }
 }
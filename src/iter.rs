@@ -3,6 +3,8 @@
 use crate::traits::*;
 use crate::tree::*;
 
+use std::collections::VecDeque;
+
 // implements all iterators for the given functions
 // this allows quickly and easily set them up for all chunks
 macro_rules! impl_all_iterators {
@@ -32,30 +34,35 @@ macro_rules! impl_all_iterators {
 		pub struct $name<'a, C: Sized, L: LodVec> {
             tree: &'a Tree<C, L>,
             index: usize,
+            modifications: u64,
         }
 
 		#[doc=concat!("Iterator for mutable chunks, see ", stringify!($func_name_mut), "() under Tree for documentation")]
         pub struct $name_mut<'a, C: Sized, L: LodVec> {
             tree: &'a mut Tree<C, L>,
             index: usize,
+            modifications: u64,
         }
 
         #[doc=concat!("Iterator for chunk positions, see ", stringify!($func_name_pos), "() under Tree for documentation")]
         pub struct $name_pos<'a, C: Sized, L: LodVec> {
             tree: &'a Tree<C, L>,
             index: usize,
+            modifications: u64,
         }
 
         #[doc=concat!("Iterator for chunks and positions, see ", stringify!($func_name_chunk_and_pos), "() under Tree for documentation")]
         pub struct $name_chunk_and_pos<'a, C: Sized, L: LodVec> {
             tree: &'a Tree<C, L>,
             index: usize,
+            modifications: u64,
         }
 
         #[doc=concat!("Iterator for mutable chunks and positions, see ", stringify!($func_name_chunk_and_pos_mut), "() under Tree for documentation")]
 		pub struct $name_chunk_and_pos_mut<'a, C: Sized, L: LodVec> {
             tree: &'a mut Tree<C, L>,
             index: usize,
+            modifications: u64,
         }
 
         // and implement iterator for it
@@ -64,6 +71,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
             fn next(&mut self) -> Option<Self::Item> {
+                // make sure the tree wasn't changed while iterating
+                debug_assert!(self.modifications == self.tree.modifications, "tree mutated during iteration");
+
                 // if the item is too big, stop
                 if self.index >= self.tree.$len() {
                     None
@@ -84,6 +94,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
             fn next(&mut self) -> Option<Self::Item> {
+                // make sure the tree wasn't changed while iterating
+                debug_assert!(self.modifications == self.tree.modifications, "tree mutated during iteration");
+
                 // if the item is too big, stop
                 if self.index >= self.tree.$len() {
                     None
@@ -104,6 +117,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
             fn next(&mut self) -> Option<Self::Item> {
+                // make sure the tree wasn't changed while iterating
+                debug_assert!(self.modifications == self.tree.modifications, "tree mutated during iteration");
+
                 // if the item is too big, stop
                 if self.index >= self.tree.$len() {
                     None
@@ -124,6 +140,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
             fn next(&mut self) -> Option<Self::Item> {
+                // make sure the tree wasn't changed while iterating
+                debug_assert!(self.modifications == self.tree.modifications, "tree mutated during iteration");
+
                 // if the item is too big, stop
                 if self.index >= self.tree.$len() {
                     None
@@ -144,6 +163,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
             fn next(&mut self) -> Option<Self::Item> {
+                // make sure the tree wasn't changed while iterating
+                debug_assert!(self.modifications == self.tree.modifications, "tree mutated during iteration");
+
                 // if the item is too big, stop
                 if self.index >= self.tree.$len() {
                     None
@@ -217,8 +239,9 @@ macro_rules! impl_all_iterators {
         {
 			#[inline]
 			$(#[$doc])*
-			pub fn $func_name(&mut self) -> $name<C, L> {
+			pub fn $func_name(&mut self) -> $name<'_, C, L> {
 				$name {
+					modifications: self.modifications,
 					tree: self,
 					index: 0,
 				}
@@ -226,8 +249,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
 			$(#[$doc_mut])*
-			pub fn $func_name_mut(&mut self) -> $name_mut<C, L> {
+			pub fn $func_name_mut(&mut self) -> $name_mut<'_, C, L> {
 				$name_mut {
+					modifications: self.modifications,
 					tree: self,
 					index: 0,
 				}
@@ -235,8 +259,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
 			$(#[$doc_pos])*
-			pub fn $func_name_pos(&mut self) -> $name_pos<C, L> {
+			pub fn $func_name_pos(&mut self) -> $name_pos<'_, C, L> {
 				$name_pos {
+					modifications: self.modifications,
 					tree: self,
 					index: 0,
 				}
@@ -244,8 +269,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
 			$(#[$doc_chunk_and_pos])*
-			pub fn $func_name_chunk_and_pos(&mut self) -> $name_chunk_and_pos<C, L> {
+			pub fn $func_name_chunk_and_pos(&mut self) -> $name_chunk_and_pos<'_, C, L> {
 				$name_chunk_and_pos {
+					modifications: self.modifications,
 					tree: self,
 					index: 0,
 				}
@@ -253,8 +279,9 @@ macro_rules! impl_all_iterators {
 
 			#[inline]
 			$(#[$doc_chunk_and_pos_mut])*
-			pub fn $func_name_chunk_and_pos_mut(&mut self) -> $name_chunk_and_pos_mut<C, L> {
+			pub fn $func_name_chunk_and_pos_mut(&mut self) -> $name_chunk_and_pos_mut<'_, C, L> {
 				$name_chunk_and_pos_mut {
+					modifications: self.modifications,
 					tree: self,
 					index: 0,
 				}
@@ -274,7 +301,8 @@ impl_all_iterators!(
     get_chunk,
     get_chunk_pointer_mut,
     get_chunk_position,
-    /// returns an iterator over all chunks
+    /// returns an iterator over all chunks.
+    /// The order is unspecified, and changes as chunks are added and removed, see iter_chunks_ordered for a stable order
     iter_chunks,
     /// returns an iterator over all chunks, mutable
     iter_chunks_mut,
@@ -452,6 +480,9 @@ pub struct ChunksInBoundAndMaybeTreeIter<'a, C: Sized, L: LodVec> {
 
     // and max of the bound
     bound_max: L,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
 }
 
 impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndMaybeTreeIter<'a, C, L> {
@@ -459,6 +490,12 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndMaybeTreeIter<'a, C,
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
         let (current_position, current_node) = self.stack.pop()?;
 
         // go over all child nodes
@@ -487,7 +524,7 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndMaybeTreeIter<'a, C,
         // and return this item from the stack
         if let Some(node) = current_node {
             // there is a node, so get the chunk it has
-            let chunk = &self.tree.chunks[node.chunk].chunk;
+            let chunk = self.tree.get_chunk(node.chunk);
 
             // and return it
             Some((current_position, Some(chunk)))
@@ -513,6 +550,9 @@ pub struct ChunksInBoundAndTreeIter<'a, C: Sized, L: LodVec> {
 
     // and max of the bound
     bound_max: L,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
 }
 
 impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndTreeIter<'a, C, L> {
@@ -520,6 +560,12 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndTreeIter<'a, C, L> {
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
         let (current_position, current_node) = self.stack.pop()?;
 
         // go over all child nodes
@@ -538,10 +584,7 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndTreeIter<'a, C, L> {
         }
 
         // and return the position and node
-        Some((
-            current_position,
-            &self.tree.chunks[current_node.chunk].chunk,
-        ))
+        Some((current_position, self.tree.get_chunk(current_node.chunk)))
     }
 }
 
@@ -560,6 +603,9 @@ pub struct ChunksInBoundAndMaybeTreeIterMut<'a, C: Sized, L: LodVec> {
 
     // and max of the bound
     bound_max: L,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
 }
 
 impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndMaybeTreeIterMut<'a, C, L> {
@@ -567,6 +613,12 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndMaybeTreeIterMut<'a,
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
         let (current_position, current_node) = self.stack.pop()?;
 
         // go over all child nodes
@@ -595,7 +647,7 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndMaybeTreeIterMut<'a,
         // and return this item from the stack
         if let Some(node) = current_node {
             // there is a node, so get the chunk it has
-            let chunk = &mut self.tree.chunks[node.chunk].chunk as *mut C;
+            let chunk = self.tree.get_chunk_pointer_mut(node.chunk);
 
             // and return it
             // Safety: The iterator lives at least as long as the tree, and no changes can be made to the tree while it's borrowed by the iterator
@@ -622,6 +674,9 @@ pub struct ChunksInBoundAndTreeIterMut<'a, C: Sized, L: LodVec> {
 
     // and max of the bound
     bound_max: L,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
 }
 
 impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndTreeIterMut<'a, C, L> {
@@ -629,6 +684,12 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndTreeIterMut<'a, C, L>
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
         let (current_position, current_node) = self.stack.pop()?;
 
         // go over all child nodes
@@ -649,11 +710,562 @@ impl<'a, C: Sized, L: LodVec> Iterator for ChunksInBoundAndTreeIterMut<'a, C, L>
         // and return the position and node
         // Safety: The iterator lives at least as long as the tree, and no changes can be made to the tree while it's borrowed by the iterator
         Some((current_position, unsafe {
-            (&mut self.tree.chunks[current_node.chunk].chunk as *mut C).as_mut()?
+            self.tree
+                .get_chunk_pointer_mut(current_node.chunk)
+                .as_mut()?
         }))
     }
 }
 
+pub struct ChunksOrderedIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal stack for which chunks are next
+    stack: Vec<(L, TreeNode)>,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for ChunksOrderedIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        let (current_position, current_node) = self.stack.pop()?;
+
+        // add the children in reverse, so the first child comes out of the stack first
+        if let Some(children) = current_node.children {
+            for i in (0..L::num_children()).rev() {
+                self.stack.push((
+                    current_position.get_child(i),
+                    self.tree.nodes[children.get() + i],
+                ));
+            }
+        }
+
+        // and return the position and node
+        Some((current_position, self.tree.get_chunk(current_node.chunk)))
+    }
+}
+
+pub struct ChunksInDepthRangeIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal stack for which chunks are next, and their depth
+    stack: Vec<(L, TreeNode, u8)>,
+
+    // lowest depth to return chunks at
+    min_depth: u8,
+
+    // and the highest
+    max_depth: u8,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for ChunksInDepthRangeIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        while let Some((current_position, current_node, current_depth)) = self.stack.pop() {
+            // add the children, as long as they aren't too deep
+            if current_depth < self.max_depth {
+                if let Some(children) = current_node.children {
+                    for i in (0..L::num_children()).rev() {
+                        self.stack.push((
+                            current_position.get_child(i),
+                            self.tree.nodes[children.get() + i],
+                            current_depth + 1,
+                        ));
+                    }
+                }
+            }
+
+            // and return the chunk if it's deep enough
+            if current_depth >= self.min_depth {
+                return Some((current_position, self.tree.get_chunk(current_node.chunk)));
+            }
+        }
+
+        None
+    }
+}
+
+pub struct ChunksOnSegmentIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal stack for which chunks are next
+    stack: Vec<(L, TreeNode)>,
+
+    // start of the segment
+    start: L::FloatCoords,
+
+    // and the end
+    end: L::FloatCoords,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for ChunksOnSegmentIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        while let Some((current_position, current_node)) = self.stack.pop() {
+            // if this is a leaf, return it
+            let children = match current_node.children {
+                Some(children) => children,
+                None => return Some((current_position, self.tree.get_chunk(current_node.chunk))),
+            };
+
+            // otherwise, find where the segment enters all children
+            let mut hits = (0..L::num_children())
+                .filter_map(|i| {
+                    let position = current_position.get_child(i);
+
+                    segment_entry(position, self.start, self.end)
+                        .map(|t| (t, position, self.tree.nodes[children.get() + i]))
+                })
+                .collect::<Vec<_>>();
+
+            // and push them furthest first, so the closest one comes out of the stack first
+            hits.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+
+            self.stack
+                .extend(hits.into_iter().map(|(_, position, node)| (position, node)));
+        }
+
+        None
+    }
+}
+
+pub struct ChunksBfsLimitedIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal queue for which chunks are next
+    queue: VecDeque<(L, TreeNode)>,
+
+    // how many chunks can still be returned
+    remaining: usize,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for ChunksBfsLimitedIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        // stop once we're out of budget
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let (current_position, current_node) = self.queue.pop_front()?;
+        self.remaining -= 1;
+
+        // add the children to the back, so all shallower chunks come first
+        if let Some(children) = current_node.children {
+            for i in 0..L::num_children() {
+                self.queue.push_back((
+                    current_position.get_child(i),
+                    self.tree.nodes[children.get() + i],
+                ));
+            }
+        }
+
+        // and return the position and chunk
+        Some((current_position, self.tree.get_chunk(current_node.chunk)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+pub struct VisibleLeavesIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal stack for which chunks are next
+    stack: Vec<(L, TreeNode)>,
+
+    // lowest corner of the box
+    min: L::FloatCoords,
+
+    // and the highest corner
+    max: L::FloatCoords,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for VisibleLeavesIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        while let Some((current_position, current_node)) = self.stack.pop() {
+            // if this is a leaf, return it
+            let children = match current_node.children {
+                Some(children) => children,
+                None => return Some((current_position, self.tree.get_chunk(current_node.chunk))),
+            };
+
+            // otherwise add the children that overlap the box
+            for i in (0..L::num_children()).rev() {
+                let position = current_position.get_child(i);
+
+                if aabb_overlaps(position, self.min, self.max) {
+                    self.stack
+                        .push((position, self.tree.nodes[children.get() + i]));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+pub struct ChunksContainingPointIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // next chunk to return, if any
+    next: Option<(L, TreeNode)>,
+
+    // the point the chunks need to contain
+    point: L::FloatCoords,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for ChunksContainingPointIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        let (current_position, current_node) = self.next.take()?;
+
+        // the next one is the child that has the point, if any
+        if let Some(children) = current_node.children {
+            self.next = (0..L::num_children())
+                .find(|&i| contains_point(current_position.get_child(i), self.point))
+                .map(|i| {
+                    (
+                        current_position.get_child(i),
+                        self.tree.nodes[children.get() + i],
+                    )
+                });
+        }
+
+        Some((current_position, self.tree.get_chunk(current_node.chunk)))
+    }
+}
+
+pub struct MissingInBoundsIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal stack for which chunks are next, and their depth
+    stack: Vec<(L, Option<TreeNode>, u64)>,
+
+    // and the depth to find missing chunks at
+    max_depth: u64,
+
+    // and the min of the bound
+    bound_min: L,
+
+    // and max of the bound
+    bound_max: L,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for MissingInBoundsIter<'a, C, L> {
+    type Item = L;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        while let Some((current_position, current_node, current_depth)) = self.stack.pop() {
+            // if we reached the depth, we're done with this chunk
+            if current_depth == self.max_depth {
+                // and return it if it's not in the tree
+                if current_node.is_none() {
+                    return Some(current_position);
+                }
+
+                continue;
+            }
+
+            // a leaf covers everything below it, so nothing there is missing
+            if matches!(current_node, Some(node) if node.children.is_none()) {
+                continue;
+            }
+
+            // go over all child nodes
+            for i in 0..L::num_children() {
+                let position = current_position.get_child(i);
+
+                // if they are in bounds, and the correct depth, add them to the stack
+                if position.is_inside_bounds(self.bound_min, self.bound_max, self.max_depth) {
+                    // get the node of the child, if there is any
+                    let node = current_node
+                        .and_then(|node| node.children)
+                        .map(|children| self.tree.nodes[children.get() + i]);
+
+                    self.stack.push((position, node, current_depth + 1));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+pub struct LeavesInBoundsMortonIter<'a, C: Sized, L: LodVec> {
+    // the tree
+    tree: &'a Tree<C, L>,
+
+    // internal stack for which nodes are next
+    stack: Vec<(L, TreeNode)>,
+
+    // and the max depth of the leaves
+    max_depth: u64,
+
+    // and the min of the bound
+    bound_min: L,
+
+    // and max of the bound
+    bound_max: L,
+
+    // modification count of the tree when this iterator was made
+    modifications: u64,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for LeavesInBoundsMortonIter<'a, C, L> {
+    type Item = (L, &'a C);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        // make sure the tree wasn't changed while iterating
+        debug_assert!(
+            self.modifications == self.tree.modifications,
+            "tree mutated during iteration"
+        );
+
+        while let Some((current_position, current_node)) = self.stack.pop() {
+            // leaves are returned, and so are nodes at the max depth, as their children aren't visited
+            let children = match current_node.children {
+                Some(children) if (current_node.depth as u64) < self.max_depth => children,
+                _ => return Some((current_position, self.tree.get_chunk(current_node.chunk))),
+            };
+
+            // otherwise go over the children in the bounds, in reverse so the first child is visited first
+            for i in (0..L::num_children()).rev() {
+                let position = current_position.get_child(i);
+
+                if position.is_inside_bounds(self.bound_min, self.bound_max, self.max_depth) {
+                    self.stack
+                        .push((position, self.tree.nodes[children.get() + i]));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Iterator for leaf chunks, see iter_leaf_chunks() under Tree for documentation
+pub struct LeafChunkIter<'a, C: Sized, L: LodVec> {
+    // the nodes, to see which chunks are leaves
+    nodes: &'a [TreeNode],
+
+    // the clock to mark the chunks as accessed with, if accesses are tracked
+    clock: Option<&'a AccessStamp>,
+
+    // and the chunks that are left
+    chunks: std::slice::Iter<'a, ChunkContainer<C, L>>,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for LeafChunkIter<'a, C, L> {
+    type Item = &'a C;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let nodes = self.nodes;
+        let clock = self.clock;
+
+        self.chunks
+            .find(|container| nodes[container.index].children.is_none())
+            .map(|container| {
+                container.last_access.touch(clock);
+                &container.chunk
+            })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.chunks.size_hint().1)
+    }
+}
+
+/// Iterator for mutable leaf chunks, see iter_leaf_chunks_mut() under Tree for documentation
+pub struct LeafChunkIterMut<'a, C: Sized, L: LodVec> {
+    // the nodes, to see which chunks are leaves
+    nodes: &'a [TreeNode],
+
+    // the clock to mark the chunks as accessed with, if accesses are tracked
+    clock: Option<&'a AccessStamp>,
+
+    // and the chunks that are left
+    chunks: std::slice::IterMut<'a, ChunkContainer<C, L>>,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for LeafChunkIterMut<'a, C, L> {
+    type Item = &'a mut C;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let nodes = self.nodes;
+        let clock = self.clock;
+
+        self.chunks
+            .find(|container| nodes[container.index].children.is_none())
+            .map(|container| {
+                container.last_access.touch(clock);
+                &mut container.chunk
+            })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.chunks.size_hint().1)
+    }
+}
+
+/// Iterator for leaf chunks and positions, see iter_leaf_chunks_and_positions() under Tree for documentation
+pub struct LeafChunkAndPositionIter<'a, C: Sized, L: LodVec> {
+    // the nodes, to see which chunks are leaves
+    nodes: &'a [TreeNode],
+
+    // the clock to mark the chunks as accessed with, if accesses are tracked
+    clock: Option<&'a AccessStamp>,
+
+    // and the chunks that are left
+    chunks: std::slice::Iter<'a, ChunkContainer<C, L>>,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for LeafChunkAndPositionIter<'a, C, L> {
+    type Item = (&'a C, L);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let nodes = self.nodes;
+        let clock = self.clock;
+
+        self.chunks
+            .find(|container| nodes[container.index].children.is_none())
+            .map(|container| {
+                container.last_access.touch(clock);
+                (&container.chunk, container.position)
+            })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.chunks.size_hint().1)
+    }
+}
+
+/// Iterator for mutable leaf chunks and positions, see iter_leaf_chunks_and_positions_mut() under Tree for documentation
+pub struct LeafChunkAndPositionIterMut<'a, C: Sized, L: LodVec> {
+    // the nodes, to see which chunks are leaves
+    nodes: &'a [TreeNode],
+
+    // the clock to mark the chunks as accessed with, if accesses are tracked
+    clock: Option<&'a AccessStamp>,
+
+    // and the chunks that are left
+    chunks: std::slice::IterMut<'a, ChunkContainer<C, L>>,
+}
+
+impl<'a, C: Sized, L: LodVec> Iterator for LeafChunkAndPositionIterMut<'a, C, L> {
+    type Item = (&'a mut C, L);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let nodes = self.nodes;
+        let clock = self.clock;
+
+        self.chunks
+            .find(|container| nodes[container.index].children.is_none())
+            .map(|container| {
+                container.last_access.touch(clock);
+                (&mut container.chunk, container.position)
+            })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.chunks.size_hint().1)
+    }
+}
+
 // TODO: iterator that also goes over chunks in the tree
 // as in: chunks in tree and bounds, immutable and mutable
 // all chunks in the bounds, and ones in the tree, if any
@@ -686,9 +1298,10 @@ where
         bound_min: L,
         bound_max: L,
         max_depth: u64,
-    ) -> ChunksInBoundAndMaybeTreeIter<C, L> {
+    ) -> ChunksInBoundAndMaybeTreeIter<'a, C, L> {
         ChunksInBoundAndMaybeTreeIter {
-            stack: vec![(L::root(), self.nodes.first().copied())],
+            stack: vec![(self.root, self.nodes.first().copied())],
+            modifications: self.modifications,
             tree: self,
             max_depth,
             bound_min,
@@ -704,16 +1317,17 @@ where
         bound_min: L,
         bound_max: L,
         max_depth: u64,
-    ) -> ChunksInBoundAndTreeIter<C, L> {
+    ) -> ChunksInBoundAndTreeIter<'a, C, L> {
         // get the stack, empty if we can't get the first node
         let stack = if let Some(node) = self.nodes.first() {
-            vec![(L::root(), *node)]
+            vec![(self.root, *node)]
         } else {
             vec![]
         };
 
         ChunksInBoundAndTreeIter {
             stack,
+            modifications: self.modifications,
             tree: self,
             max_depth,
             bound_min,
@@ -728,9 +1342,10 @@ where
         bound_min: L,
         bound_max: L,
         max_depth: u64,
-    ) -> ChunksInBoundAndMaybeTreeIterMut<C, L> {
+    ) -> ChunksInBoundAndMaybeTreeIterMut<'a, C, L> {
         ChunksInBoundAndMaybeTreeIterMut {
-            stack: vec![(L::root(), self.nodes.first().copied())],
+            stack: vec![(self.root, self.nodes.first().copied())],
+            modifications: self.modifications,
             tree: self,
             max_depth,
             bound_min,
@@ -746,22 +1361,508 @@ where
         bound_min: L,
         bound_max: L,
         max_depth: u64,
-    ) -> ChunksInBoundAndTreeIterMut<C, L> {
+    ) -> ChunksInBoundAndTreeIterMut<'a, C, L> {
         // get the stack, empty if we can't get the first node
         let stack = if let Some(node) = self.nodes.first() {
-            vec![(L::root(), *node)]
+            vec![(self.root, *node)]
         } else {
             vec![]
         };
 
         ChunksInBoundAndTreeIterMut {
             stack,
+            modifications: self.modifications,
             tree: self,
             max_depth,
             bound_min,
             bound_max,
         }
     }
+
+    /// iterate over all chunks and their positions in a stable order.
+    /// Parents come before their children, and children are visited in order, so this only depends on which chunks are in the tree,
+    /// and not on the order in which they were added or removed
+    #[inline]
+    pub fn iter_chunks_ordered(&'a self) -> ChunksOrderedIter<'a, C, L> {
+        // get the stack, empty if we can't get the first node
+        let stack = if let Some(node) = self.nodes.first() {
+            vec![(self.root, *node)]
+        } else {
+            vec![]
+        };
+
+        ChunksOrderedIter {
+            stack,
+            tree: self,
+            modifications: self.modifications,
+        }
+    }
+
+    /// iterate over all chunks and their positions with a depth from min_depth up to and including max_depth.
+    /// Parts of the tree deeper than max_depth aren't visited
+    #[inline]
+    pub fn iter_chunks_in_depth_range(
+        &'a self,
+        min_depth: u8,
+        max_depth: u8,
+    ) -> ChunksInDepthRangeIter<'a, C, L> {
+        // get the stack, empty if we can't get the first node
+        let stack = if let Some(node) = self.nodes.first() {
+            vec![(self.root, *node, self.root_depth)]
+        } else {
+            vec![]
+        };
+
+        ChunksInDepthRangeIter {
+            stack,
+            tree: self,
+            min_depth,
+            max_depth,
+            modifications: self.modifications,
+        }
+    }
+
+    /// iterate over all chunks and their positions at exactly the given depth, for example to build an index for one lod level.
+    /// This is iter_chunks_in_depth_range with the same min and max depth, so nothing deeper is visited
+    #[inline]
+    pub fn iter_positions_at_depth(&'a self, depth: u8) -> ChunksInDepthRangeIter<'a, C, L> {
+        self.iter_chunks_in_depth_range(depth, depth)
+    }
+
+    /// iterate over all leaf chunks and their positions that are crossed by a line segment, in the order the segment enters them.
+    /// The segment is in float coords, where the root spans from 0 to 1 on all axes.
+    /// Chunks that are only touched at an edge or corner aren't included
+    #[inline]
+    pub fn iter_chunks_on_segment(
+        &'a self,
+        start: L::FloatCoords,
+        end: L::FloatCoords,
+    ) -> ChunksOnSegmentIter<'a, C, L> {
+        // get the stack, empty if we can't get the first node, or the segment misses the root
+        let stack = match self.nodes.first() {
+            Some(node) if segment_entry(self.root, start, end).is_some() => {
+                vec![(self.root, *node)]
+            }
+            _ => vec![],
+        };
+
+        ChunksOnSegmentIter {
+            stack,
+            tree: self,
+            start,
+            end,
+            modifications: self.modifications,
+        }
+    }
+
+    /// iterate over at most max_chunks chunks and their positions, shallowest first.
+    /// This starts at the root, and goes down one depth at a time, so a small budget only gives the coarse chunks
+    #[inline]
+    pub fn iter_chunks_bfs_limited(&'a self, max_chunks: usize) -> ChunksBfsLimitedIter<'a, C, L> {
+        // get the queue, empty if we can't get the first node
+        let queue = self
+            .nodes
+            .first()
+            .map(|node| (self.root, *node))
+            .into_iter()
+            .collect();
+
+        ChunksBfsLimitedIter {
+            queue,
+            tree: self,
+            remaining: max_chunks,
+            modifications: self.modifications,
+        }
+    }
+
+    /// iterate over all leaf chunks and their positions that overlap a box.
+    /// The box is in float coords, where the root spans from 0 to 1 on all axes.
+    /// Chunks that only touch the box at an edge aren't included
+    #[inline]
+    pub fn iter_visible_leaves(
+        &'a self,
+        min: L::FloatCoords,
+        max: L::FloatCoords,
+    ) -> VisibleLeavesIter<'a, C, L> {
+        // get the stack, empty if we can't get the first node, or the box misses the root
+        let stack = match self.nodes.first() {
+            Some(node) if aabb_overlaps(self.root, min, max) => vec![(self.root, *node)],
+            _ => vec![],
+        };
+
+        VisibleLeavesIter {
+            stack,
+            tree: self,
+            min,
+            max,
+            modifications: self.modifications,
+        }
+    }
+
+    /// iterate over all chunks and their positions that contain a point, from the root down to the leaf with the point.
+    /// The point is in float coords, where the root spans from 0 to 1 on all axes.
+    /// Nodes contain their lowest corner, but not their highest, so a point on the edge between nodes is in the higher one
+    #[inline]
+    pub fn iter_chunks_containing_point(
+        &'a self,
+        point: L::FloatCoords,
+    ) -> ChunksContainingPointIter<'a, C, L> {
+        ChunksContainingPointIter {
+            next: self
+                .nodes
+                .first()
+                .filter(|_| contains_point(self.root, point))
+                .map(|node| (self.root, *node)),
+            tree: self,
+            point,
+            modifications: self.modifications,
+        }
+    }
+
+    /// iterate over all chunks and their positions, together with the id of the chunk.
+    /// The id stays valid until the chunk is removed from the tree, and can be used to get the chunk with get_chunk_by_id
+    #[inline]
+    pub fn iter_chunks_with_id(&'a self) -> impl Iterator<Item = (ChunkId, L, &'a C)> {
+        self.chunks.iter().map(move |container| {
+            container.last_access.touch(self.access_clock());
+
+            (
+                ChunkId {
+                    node: container.index,
+                    generation: self.nodes[container.index].generation,
+                },
+                container.position,
+                &container.chunk,
+            )
+        })
+    }
+
+    /// iterate over all active chunks and their positions.
+    /// After an update these are the leaves, but which ones are active can be changed with set_active_where
+    #[inline]
+    pub fn iter_active_chunks(&'a self) -> impl Iterator<Item = (L, &'a C)> {
+        self.chunks
+            .iter()
+            .filter(|container| container.active)
+            .map(move |container| {
+                container.last_access.touch(self.access_clock());
+                (container.position, &container.chunk)
+            })
+    }
+
+    /// iterate over all leaf chunks, which are the chunks of nodes without children.
+    /// These are the chunks to draw, as together they cover the tree without overlapping. The order is the same as for iter_chunks, without the other chunks
+    #[inline]
+    pub fn iter_leaf_chunks(&'a self) -> LeafChunkIter<'a, C, L> {
+        LeafChunkIter {
+            nodes: &self.nodes,
+            clock: self.access_clock(),
+            chunks: self.chunks.iter(),
+        }
+    }
+
+    /// iterate over all leaf chunks, mutable
+    #[inline]
+    pub fn iter_leaf_chunks_mut(&'a mut self) -> LeafChunkIterMut<'a, C, L> {
+        LeafChunkIterMut {
+            nodes: &self.nodes,
+            clock: self.chunk_limit.and(Some(&self.access_clock)),
+            chunks: self.chunks.iter_mut(),
+        }
+    }
+
+    /// iterate over all leaf chunks and their positions
+    #[inline]
+    pub fn iter_leaf_chunks_and_positions(&'a self) -> LeafChunkAndPositionIter<'a, C, L> {
+        LeafChunkAndPositionIter {
+            nodes: &self.nodes,
+            clock: self.access_clock(),
+            chunks: self.chunks.iter(),
+        }
+    }
+
+    /// iterate over all leaf chunks as mutable and their positions
+    #[inline]
+    pub fn iter_leaf_chunks_and_positions_mut(
+        &'a mut self,
+    ) -> LeafChunkAndPositionIterMut<'a, C, L> {
+        LeafChunkAndPositionIterMut {
+            nodes: &self.nodes,
+            clock: self.chunk_limit.and(Some(&self.access_clock)),
+            chunks: self.chunks.iter_mut(),
+        }
+    }
+
+    /// iterate over all leaf chunks and their positions at the lowest depth any leaf is at.
+    /// These are the coarsest parts of the tree, and the first places to add more detail
+    #[inline]
+    pub fn coarsest_leaves(&'a self) -> impl Iterator<Item = (L, &'a C)> {
+        // all leaves, as those have no children
+        let leaves = self
+            .chunks
+            .iter()
+            .filter(move |container| self.nodes[container.index].children.is_none());
+
+        // the shallowest leaves are the biggest ones
+        let size = leaves
+            .clone()
+            .map(|container| container.position.get_size())
+            .fold(0.0, f64::max);
+
+        leaves
+            .filter(move |container| container.position.get_size() == size)
+            .map(move |container| {
+                container.last_access.touch(self.access_clock());
+                (container.position, &container.chunk)
+            })
+    }
+
+    /// iterate over every parent and child pair of chunks in the tree, as the position and chunk of the parent, followed by those of the child.
+    /// Each node with children shows up once for every child
+    #[inline]
+    pub fn iter_parent_child_pairs(&'a self) -> impl Iterator<Item = (L, &'a C, L, &'a C)> {
+        self.chunks.iter().flat_map(move |parent| {
+            // nodes without children give no pairs
+            self.nodes[parent.index]
+                .children
+                .into_iter()
+                .flat_map(|children| (0..L::num_children()).map(move |i| children.get() + i))
+                .map(move |index| {
+                    let child = &self.chunks[self.nodes[index].chunk];
+
+                    parent.last_access.touch(self.access_clock());
+                    child.last_access.touch(self.access_clock());
+
+                    (parent.position, &parent.chunk, child.position, &child.chunk)
+                })
+        })
+    }
+
+    /// iterate over groups of sibling leaves, as the position of the parent, and the positions and chunks of it's children that are leaves, in order.
+    /// Each node with at least one leaf child gives one group, so groups have up to num_children chunks
+    #[inline]
+    pub fn iter_sibling_groups(&'a self) -> impl Iterator<Item = (L, Vec<(L, &'a C)>)> {
+        self.chunks.iter().filter_map(move |parent| {
+            let children = self.nodes[parent.index].children?.get();
+
+            // only the children without children of their own
+            let leaves = (children..children + L::num_children())
+                .map(|index| self.nodes[index])
+                .filter(|node| node.children.is_none())
+                .map(|node| {
+                    let child = &self.chunks[node.chunk];
+
+                    child.last_access.touch(self.access_clock());
+                    (child.position, &child.chunk)
+                })
+                .collect::<Vec<_>>();
+
+            if leaves.is_empty() {
+                None
+            } else {
+                Some((parent.position, leaves))
+            }
+        })
+    }
+
+    /// iterate over all positions at max_depth inside the bounds that aren't covered by the tree,
+    /// so they don't have a chunk of their own, and there's no leaf chunk above them either.
+    /// As a tree that's only changed with updates covers the entire root, this is everything in the bounds for an empty tree, and nothing otherwise
+    #[inline]
+    pub fn iter_missing_in_bounds(
+        &'a self,
+        bound_min: L,
+        bound_max: L,
+        max_depth: u64,
+    ) -> MissingInBoundsIter<'a, C, L> {
+        MissingInBoundsIter {
+            stack: vec![(
+                self.root,
+                self.nodes.first().copied(),
+                self.root_depth as u64,
+            )],
+            modifications: self.modifications,
+            tree: self,
+            max_depth,
+            bound_min,
+            bound_max,
+        }
+    }
+
+    /// iterate over all leaf chunks and their positions inside the bounds, sorted in Morton order, so chunks close to each other are also close in the order.
+    /// This is the same order as containment_cmp. Leaves deeper than max_depth aren't visited,
+    /// instead their ancestor at max_depth is returned in their place, so the returned chunks still cover the whole region in the bounds
+    #[inline]
+    pub fn iter_leaves_in_bounds_morton(
+        &'a self,
+        bound_min: L,
+        bound_max: L,
+        max_depth: u64,
+    ) -> LeavesInBoundsMortonIter<'a, C, L> {
+        LeavesInBoundsMortonIter {
+            stack: self
+                .nodes
+                .first()
+                .map(|node| (self.root, *node))
+                .into_iter()
+                .collect(),
+            modifications: self.modifications,
+            tree: self,
+            max_depth,
+            bound_min,
+            bound_max,
+        }
+    }
+
+    /// iterate over all chunks and their positions, sorted by the distance from their center to the center of target, closest first
+    #[inline]
+    pub fn iter_chunks_by_distance(&'a self, target: L) -> impl Iterator<Item = (L, &'a C)> {
+        // get all chunks with their distance
+        let mut chunks = self
+            .chunks
+            .iter()
+            .map(|container| {
+                (
+                    center_distance_squared(container.position, target),
+                    container,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        // and sort them
+        chunks.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        chunks.into_iter().map(move |(_, container)| {
+            container.last_access.touch(self.access_clock());
+            (container.position, &container.chunk)
+        })
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<C, L> Tree<C, L>
+where
+    C: Sized,
+    L: LodVec,
+{
+    /// returns a parallel iterator over all chunks, in the same order as iter_chunks
+    #[inline]
+    pub fn par_iter_chunks(&self) -> impl rayon::iter::IndexedParallelIterator<Item = &C>
+    where
+        C: Sync,
+    {
+        use rayon::prelude::*;
+
+        let clock = self.access_clock();
+
+        self.chunks.par_iter().map(move |container| {
+            container.last_access.touch(clock);
+            &container.chunk
+        })
+    }
+
+    /// returns a parallel iterator over all chunks, mutable
+    #[inline]
+    pub fn par_iter_chunks_mut(
+        &mut self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = &mut C>
+    where
+        C: Send,
+    {
+        use rayon::prelude::*;
+
+        let clock = self.chunk_limit.and(Some(&self.access_clock));
+
+        self.chunks.par_iter_mut().map(move |container| {
+            container.last_access.touch(clock);
+            &mut container.chunk
+        })
+    }
+}
+
+// where a line segment enters a node, as a fraction of the length of the segment from the start
+// returns None if the segment misses the node, or only touches it at an edge or corner
+fn segment_entry<L: LodVec>(
+    position: L,
+    start: L::FloatCoords,
+    end: L::FloatCoords,
+) -> Option<f64> {
+    let size = position.get_size();
+
+    // range of the segment that's inside the node
+    let mut entry = 0.0_f64;
+    let mut exit = 1.0_f64;
+
+    for ((min, start), end) in position
+        .get_float_coords()
+        .as_ref()
+        .iter()
+        .zip(start.as_ref())
+        .zip(end.as_ref())
+    {
+        let direction = end - start;
+
+        if direction == 0.0 {
+            // parallel to this axis, so it has to be inside the slab
+            if *start < *min || *start > min + size {
+                return None;
+            }
+        } else {
+            // clip the segment to the slab
+            let near = (min - start) / direction;
+            let far = (min + size - start) / direction;
+
+            entry = entry.max(near.min(far));
+            exit = exit.min(near.max(far));
+        }
+    }
+
+    if entry < exit || (entry == exit && start == end) {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+// wether a node overlaps a box, excluding the edges
+fn aabb_overlaps<L: LodVec>(position: L, min: L::FloatCoords, max: L::FloatCoords) -> bool {
+    let size = position.get_size();
+
+    position
+        .get_float_coords()
+        .as_ref()
+        .iter()
+        .zip(min.as_ref())
+        .zip(max.as_ref())
+        .all(|((coord, min), max)| *coord < *max && coord + size > *min)
+}
+
+// wether a node contains a point, including it's lowest corner but not it's highest
+fn contains_point<L: LodVec>(position: L, point: L::FloatCoords) -> bool {
+    let size = position.get_size();
+
+    position
+        .get_float_coords()
+        .as_ref()
+        .iter()
+        .zip(point.as_ref())
+        .all(|(coord, point)| *point >= *coord && *point < coord + size)
+}
+
+// squared distance between the centers of two nodes
+pub(crate) fn center_distance_squared<L: LodVec>(a: L, b: L) -> f64 {
+    let a_size = a.get_size();
+    let b_size = b.get_size();
+
+    a.get_float_coords()
+        .as_ref()
+        .iter()
+        .zip(b.get_float_coords().as_ref())
+        .map(|(a_coord, b_coord)| {
+            let difference = (a_coord + a_size * 0.5) - (b_coord + b_size * 0.5);
+            difference * difference
+        })
+        .sum()
 }
 
 #[cfg(test)]
@@ -784,4 +1885,656 @@ mod tests {
             println!("{:?}", pos);
         }
     }
+
+    #[test]
+    fn test_missing_in_bounds() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+        let (min, max) = (QuadVec::new(0, 0, 4), QuadVec::new(8, 8, 4));
+
+        // all positions at depth 4 in the bounds
+        let all = Tree::<C, QuadVec>::iter_all_chunks_in_bounds(min, max, 4)
+            .filter(|position| position.depth == 4)
+            .collect::<Vec<_>>();
+
+        // nothing is covered by an empty tree
+        let mut missing = tree.iter_missing_in_bounds(min, max, 4).collect::<Vec<_>>();
+        missing.sort_by(|a, b| a.containment_cmp(*b));
+
+        let mut expected = all.clone();
+        expected.sort_by(|a, b| a.containment_cmp(*b));
+
+        assert_eq!(missing, expected);
+
+        // load the tree, so that only part of the bounds has chunks at depth 4
+        while tree.prepare_update(&[QuadVec::new(2, 2, 4)], 0, |_| C {}) {
+            tree.do_update();
+        }
+
+        let present = all
+            .iter()
+            .filter(|position| tree.get_chunk_from_position(**position).is_some())
+            .count();
+
+        assert!(present > 0);
+        assert!(present < all.len());
+
+        // but the rest is covered by bigger leaves, so nothing is missing
+        assert_eq!(tree.iter_missing_in_bounds(min, max, 4).count(), 0);
+    }
+
+    #[test]
+    fn test_ordered() {
+        struct C;
+
+        // build the tree directly
+        let mut tree_a = Tree::<C, QuadVec>::new(0);
+
+        while tree_a.prepare_update(&[QuadVec::new(9, 3, 4)], 1, |_| C {}) {
+            tree_a.do_update();
+        }
+
+        // and build it somewhere else first, then move it to the same target
+        let mut tree_b = Tree::<C, QuadVec>::new(16);
+
+        while tree_b.prepare_update(&[QuadVec::new(1, 14, 4)], 1, |_| C {}) {
+            tree_b.do_update();
+        }
+
+        while tree_b.prepare_update(&[QuadVec::new(9, 3, 4)], 1, |_| C {}) {
+            tree_b.do_update();
+        }
+
+        let order_a = tree_a
+            .iter_chunks_ordered()
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        let order_b = tree_b
+            .iter_chunks_ordered()
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        // both should have all chunks, in the same order
+        assert_eq!(order_a.len(), tree_a.get_num_chunks());
+        assert_eq!(order_a, order_b);
+
+        // and parents come first
+        assert_eq!(order_a[0], QuadVec::root());
+        assert_eq!(order_a[1], QuadVec::new(0, 0, 1));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "tree mutated during iteration")]
+    fn test_mutation_during_iteration() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(1, 1, 2)], 1, |_| C {}) {
+            tree.do_update();
+        }
+
+        // the borrow checker stops safe code from doing this, so make an iterator by hand as it would be before the tree changes
+        // this state can only be reached with unsafe code or from inside the crate, which is what the check is for
+        let modifications = tree.modifications;
+
+        // and change the tree
+        tree.prepare_update(&[QuadVec::new(0, 0, 1)], 1, |_| C {});
+        tree.do_update();
+
+        // this iterator is now out of date, so it should panic
+        let mut iter = ChunkIter {
+            tree: &tree,
+            index: 0,
+            modifications,
+        };
+
+        iter.next();
+    }
+
+    #[test]
+    fn test_by_distance() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(9, 3, 4)], 1, |_| C {}) {
+            tree.do_update();
+        }
+
+        let target = QuadVec::new(5, 12, 4);
+
+        // all chunks are visited
+        let distances = tree
+            .iter_chunks_by_distance(target)
+            .map(|(position, _)| center_distance_squared(position, target))
+            .collect::<Vec<_>>();
+
+        assert_eq!(distances.len(), tree.get_num_chunks());
+
+        // and closest first
+        assert!(distances.windows(2).all(|x| x[0] <= x[1]));
+    }
+
+    #[test]
+    fn test_depth_range() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(21, 40, 6)], 1, |_| C {}) {
+            tree.do_update();
+        }
+
+        let positions = tree
+            .iter_chunks_in_depth_range(2, 4)
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        // only chunks in the range
+        assert!(positions
+            .iter()
+            .all(|position| position.depth >= 2 && position.depth <= 4));
+
+        // and all of them
+        assert_eq!(
+            positions.len(),
+            tree.iter_chunk_positions()
+                .filter(|position| position.depth >= 2 && position.depth <= 4)
+                .count()
+        );
+    }
+
+    #[test]
+    fn test_segment() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |_| C {}) {
+            tree.do_update();
+        }
+
+        // diagonal segment, slightly above the corner to corner one
+        let crossed = tree
+            .iter_chunks_on_segment([0.1, 0.15], [0.9, 0.95])
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            crossed,
+            vec![
+                QuadVec::new(0, 0, 2),
+                QuadVec::new(0, 1, 2),
+                QuadVec::new(1, 1, 2),
+                QuadVec::new(0, 1, 1),
+                QuadVec::new(1, 1, 1),
+            ]
+        );
+
+        // going the other way gives them in reverse
+        let reversed = tree
+            .iter_chunks_on_segment([0.9, 0.95], [0.1, 0.15])
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        assert_eq!(reversed, crossed.into_iter().rev().collect::<Vec<_>>());
+
+        // and a segment outside the tree crosses nothing
+        assert_eq!(
+            tree.iter_chunks_on_segment([1.5, 0.0], [2.0, 1.0]).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_bfs_limited() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(12, 3, 4)], 1, |_| C {}) {
+            tree.do_update();
+        }
+
+        for budget in [0, 1, 3, 5, 20, tree.get_num_chunks() + 10] {
+            let positions = tree
+                .iter_chunks_bfs_limited(budget)
+                .map(|(position, _)| position)
+                .collect::<Vec<_>>();
+
+            // never more than the budget
+            assert_eq!(positions.len(), budget.min(tree.get_num_chunks()));
+
+            // root first
+            if budget > 0 {
+                assert_eq!(positions[0], QuadVec::root());
+            }
+
+            // and shallowest first
+            assert!(positions.windows(2).all(|x| x[0].depth <= x[1].depth));
+        }
+    }
+
+    #[test]
+    fn test_visible_leaves() {
+        struct C;
+
+        let mut tree = Tree::<C, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 0, |_| C {}) {
+            tree.do_update();
+        }
+
+        // box in the lower left corner, slightly into the chunks next to it
+        let visible = tree
+            .iter_visible_leaves([0.0, 0.0], [0.2, 0.3])
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        // only overlapping leaves
+        assert_eq!(
+            visible,
+            vec![
+                QuadVec::new(0, 0, 3),
+                QuadVec::new(1, 0, 3),
+                QuadVec::new(0, 1, 3),
+                QuadVec::new(1, 1, 3),
+                QuadVec::new(0, 1, 2),
+            ]
+        );
+
+        // everything gives all leaves, and no internal nodes
+        assert_eq!(tree.iter_visible_leaves([0.0, 0.0], [1.0, 1.0]).count(), 10);
+
+        // and a box outside the tree has nothing
+        assert_eq!(tree.iter_visible_leaves([1.0, 0.0], [2.0, 1.0]).count(), 0);
+    }
+
+    #[test]
+    fn test_parent_child_pairs() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(12, 3, 4)], 1, |position| position) {
+            tree.do_update();
+        }
+
+        let pairs = tree.iter_parent_child_pairs().collect::<Vec<_>>();
+
+        // every internal node has a pair for each child
+        let internal = tree
+            .iter_chunks_ordered()
+            .filter(|(position, _)| {
+                tree.get_chunk_from_position(position.get_child(0))
+                    .is_some()
+            })
+            .count();
+
+        assert_eq!(pairs.len(), internal * QuadVec::num_children());
+
+        // with the right chunks, and the child inside the parent
+        assert!(pairs
+            .iter()
+            .all(|(parent_position, parent, child_position, child)| {
+                parent_position == *parent
+                    && child_position == *child
+                    && child_position.depth == parent_position.depth + 1
+                    && parent_position.contains_child_node(*child_position)
+            }));
+    }
+
+    #[test]
+    fn test_containing_point() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(12, 3, 4)], 1, |position| position) {
+            tree.do_update();
+        }
+
+        for point in [[0.8, 0.2], [0.1, 0.9], [0.75, 0.25], [0.0, 0.0]] {
+            let chunks = tree.iter_chunks_containing_point(point).collect::<Vec<_>>();
+
+            // depths go from the root down, one at a time
+            assert!(chunks
+                .iter()
+                .enumerate()
+                .all(|(i, (position, chunk))| position.depth as usize == i && position == *chunk));
+
+            // and end at a leaf
+            let (leaf, _) = chunks.last().unwrap();
+
+            assert!(tree.get_chunk_from_position(leaf.get_child(0)).is_none());
+        }
+
+        // the point in the refined area goes all the way down
+        assert_eq!(
+            tree.iter_chunks_containing_point([0.78, 0.22])
+                .last()
+                .map(|(position, _)| position),
+            Some(QuadVec::new(12, 3, 4))
+        );
+
+        // and a point outside of the tree has nothing
+        assert_eq!(tree.iter_chunks_containing_point([1.0, 0.5]).count(), 0);
+    }
+
+    #[test]
+    fn test_coarsest_leaves() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // nothing in an empty tree
+        assert_eq!(tree.coarsest_leaves().count(), 0);
+
+        // refine only the lower left corner
+        while tree.prepare_update(&[QuadVec::new(0, 0, 4)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        let mut coarsest = tree
+            .coarsest_leaves()
+            .map(|(position, chunk)| {
+                assert_eq!(position, *chunk);
+                position
+            })
+            .collect::<Vec<_>>();
+
+        coarsest.sort();
+
+        // only the quadrants away from the target
+        assert_eq!(
+            coarsest,
+            vec![
+                QuadVec::new(0, 1, 1),
+                QuadVec::new(1, 0, 1),
+                QuadVec::new(1, 1, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leaves_in_bounds_morton() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(
+            &[QuadVec::new(5, 9, 4), QuadVec::new(12, 2, 4)],
+            1,
+            |position| position,
+        ) {
+            tree.do_update();
+        }
+
+        let (min, max) = (QuadVec::new(3, 1, 4), QuadVec::new(16, 16, 4));
+
+        let leaves = tree
+            .iter_leaves_in_bounds_morton(min, max, 4)
+            .map(|(position, chunk)| {
+                assert_eq!(position, *chunk);
+                position
+            })
+            .collect::<Vec<_>>();
+
+        assert!(!leaves.is_empty());
+
+        // all are leaves in the bound
+        for position in leaves.iter() {
+            assert!(position.is_inside_bounds(min, max, 4));
+            assert!(tree
+                .get_chunk_from_position(position.get_child(0))
+                .is_none());
+        }
+
+        // and all leaves in the bound are there
+        let expected = tree
+            .iter_chunks_ordered()
+            .map(|(position, _)| position)
+            .filter(|position| {
+                position.is_inside_bounds(min, max, 4)
+                    && tree
+                        .get_chunk_from_position(position.get_child(0))
+                        .is_none()
+            })
+            .count();
+
+        assert_eq!(leaves.len(), expected);
+
+        // in Morton order
+        assert!(leaves
+            .windows(2)
+            .all(|x| x[0].containment_cmp(x[1]) == std::cmp::Ordering::Less));
+    }
+
+    #[test]
+    fn test_leaves_in_bounds_morton_cut_off() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(5, 9, 4)], 1, |position| position) {
+            tree.do_update();
+        }
+
+        // the leaves go deeper than the max depth
+        let (min, max) = (QuadVec::new(0, 0, 4), QuadVec::new(16, 16, 4));
+        let all = tree
+            .iter_leaves_in_bounds_morton(min, max, 4)
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+
+        assert!(all.iter().any(|position| position.depth > 2));
+
+        let cut = tree
+            .iter_leaves_in_bounds_morton(min, max, 2)
+            .map(|(position, chunk)| {
+                assert_eq!(position, *chunk);
+                position
+            })
+            .collect::<Vec<_>>();
+
+        // nodes at the max depth are returned in place of their leaves
+        assert!(cut.iter().all(|position| position.depth <= 2));
+        assert!(cut.iter().any(|position| position.depth == 2
+            && tree
+                .get_chunk_from_position(position.get_child(0))
+                .is_some()));
+
+        // so every leaf is still covered by exactly one of them
+        for leaf in all {
+            assert_eq!(
+                cut.iter()
+                    .filter(|position| position.contains_child_node(leaf))
+                    .count(),
+                1
+            );
+        }
+
+        // and they are still in Morton order
+        assert!(cut
+            .windows(2)
+            .all(|x| x[0].containment_cmp(x[1]) == std::cmp::Ordering::Less));
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn test_par_iter_chunks() {
+        use rayon::prelude::*;
+
+        struct Chunk {
+            value: u64,
+        }
+
+        let mut tree = Tree::<Chunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(5, 9, 4)], 2, |position| Chunk {
+            value: position.x + position.y * 3 + position.depth as u64,
+        }) {
+            tree.do_update();
+        }
+
+        // same sum as the serial iterator
+        let serial = tree.iter_chunks().map(|chunk| chunk.value).sum::<u64>();
+        let parallel = tree.par_iter_chunks().map(|chunk| chunk.value).sum::<u64>();
+
+        assert_eq!(parallel, serial);
+        assert_eq!(tree.par_iter_chunks().len(), tree.get_num_chunks());
+
+        // and in the same order
+        let values = tree
+            .par_iter_chunks()
+            .map(|chunk| chunk.value)
+            .collect::<Vec<_>>();
+        assert!(tree.iter_chunks().map(|chunk| chunk.value).eq(values));
+
+        // change them all
+        tree.par_iter_chunks_mut()
+            .for_each(|chunk| chunk.value *= 2);
+
+        assert_eq!(
+            tree.iter_chunks().map(|chunk| chunk.value).sum::<u64>(),
+            serial * 2
+        );
+    }
+
+    #[test]
+    fn test_iter_leaf_chunks() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(16);
+
+        // nothing in an empty tree
+        assert_eq!(tree.iter_leaf_chunks().count(), 0);
+
+        // move the target, so there are free nodes as well
+        for target in [QuadVec::new(5, 9, 4), QuadVec::new(12, 2, 4)] {
+            while tree.prepare_update(&[target], 1, |position| position) {
+                tree.do_update();
+                tree.complete_update();
+            }
+        }
+
+        // as many leaves as nodes without children that are in use
+        let leaves = tree
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(index, node)| {
+                node.children.is_none()
+                    && tree
+                        .chunks
+                        .get(node.chunk)
+                        .map_or(false, |c| c.index == *index)
+            })
+            .count();
+
+        assert!(leaves > 1);
+        assert_eq!(tree.iter_leaf_chunks().count(), leaves);
+        assert_eq!(tree.iter_leaf_chunks_and_positions().count(), leaves);
+
+        // and they cover the root
+        let area = tree
+            .iter_leaf_chunks()
+            .map(|chunk| chunk.get_size() * chunk.get_size())
+            .sum::<f64>();
+        assert_eq!(area, 1.0);
+
+        // with the right positions
+        assert!(tree
+            .iter_leaf_chunks_and_positions()
+            .all(|(chunk, position)| *chunk == position));
+
+        // which can be changed
+        for (chunk, position) in tree.iter_leaf_chunks_and_positions_mut() {
+            *chunk = position.get_child(0);
+        }
+
+        for chunk in tree.iter_leaf_chunks_mut() {
+            *chunk = chunk.parent().unwrap();
+        }
+
+        assert!(tree
+            .iter_leaf_chunks_and_positions()
+            .all(|(chunk, position)| *chunk == position));
+    }
+
+    #[test]
+    fn test_iter_sibling_groups() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // nothing in an empty tree
+        assert_eq!(tree.iter_sibling_groups().count(), 0);
+
+        while tree.prepare_update(&[QuadVec::new(5, 9, 4)], 1, |position| position) {
+            tree.do_update();
+            tree.complete_update();
+        }
+
+        let mut leaves = 0;
+        let mut partial = 0;
+
+        for (parent, group) in tree.iter_sibling_groups() {
+            // up to one chunk for each child
+            assert!(!group.is_empty());
+            assert!(group.len() <= QuadVec::num_children());
+
+            if group.len() < QuadVec::num_children() {
+                partial += 1;
+            }
+
+            // that are all leaves below the parent
+            for (position, chunk) in group {
+                assert_eq!(position.parent(), Some(parent));
+                assert_eq!(*chunk, position);
+                assert!(tree
+                    .children_of(tree.node_index_of(position).unwrap())
+                    .is_none());
+
+                leaves += 1;
+            }
+        }
+
+        // some parents also have children that aren't leaves
+        assert!(partial > 0);
+
+        // and together they have all leaves
+        assert!(leaves > QuadVec::num_children());
+        assert_eq!(leaves, tree.iter_leaf_chunks().count());
+    }
+
+    #[test]
+    fn test_iter_positions_at_depth() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // nothing in an empty tree
+        assert_eq!(tree.iter_positions_at_depth(0).count(), 0);
+
+        while tree.prepare_update(
+            &[QuadVec::new(37, 12, 6), QuadVec::new(2, 60, 6)],
+            1,
+            |position| position,
+        ) {
+            tree.do_update();
+        }
+
+        // count by hand
+        let expected = tree
+            .iter_chunks_ordered()
+            .filter(|(position, _)| position.depth == 4)
+            .count();
+
+        assert!(expected > 0);
+        assert_eq!(tree.iter_positions_at_depth(4).count(), expected);
+        assert_eq!(tree.depth_histogram()[4], expected);
+
+        // all at the right depth
+        assert!(tree
+            .iter_positions_at_depth(4)
+            .all(|(position, chunk)| position.depth == 4 && *chunk == position));
+
+        // and the root is the only one at depth 0
+        assert_eq!(
+            tree.iter_positions_at_depth(0).collect::<Vec<_>>(),
+            vec![(QuadVec::root(), &QuadVec::root())]
+        );
+
+        // while nothing is deeper than the tree goes
+        assert_eq!(tree.iter_positions_at_depth(7).count(), 0);
+    }
 }
@@ -21,7 +21,7 @@
 //! # use lodtree::*;
 //! # use lodtree::coords::OctVec;
 //! # struct Chunk {}
-//! let mut tree = Tree::<Chunk, OctVec>::new();
+//! let mut tree = Tree::<Chunk, OctVec>::new(64);
 //! ```
 //!
 //! If you want to update chunks due to the camera being moved, you can check if it's needed with prepare_update.
@@ -29,8 +29,8 @@
 //!
 //! Targets: where to generate the most detail around.
 //!
-//! The given LodVec implementations (OctVec and QuadVec) take in 4 and 3 arguments respectively.
-//! The first 3/2 are the position in the tree, which is dependant on the lod level.
+//! The given LodVec implementations (OctVec, QuadVec and LineVec) take in 4, 3 and 2 arguments respectively, and NVec takes an array with the position on each axis and the depth.
+//! All but the last are the position in the tree, which is dependant on the lod level (3 for OctVec, 2 for QuadVec and 1 for LineVec).
 //! and the last parameter is the lod level. No lods smaller than this will be generated for this target.
 //!
 //! Detail: The amount of detail for the targets.
@@ -38,16 +38,16 @@
 //!
 //! Chunk creator:
 //! Internally a buffer for new chunks is filled, and this function is called to create the new chunk.
-//! It takes in the LodVec of the position of the chunk.
+//! It takes in the LodVec of the position of the chunk, and can be a closure that captures things like a world generator.
 //! ```rust
 //! # use lodtree::*;
 //! # use lodtree::coords::OctVec;
 //! # struct Chunk {}
 //! # let mut tree = Tree::<Chunk, OctVec>::new(64);
 //! let needs_updating = tree.prepare_update(
-//! 	&[OctVec::new(8, 8, 8, 8)], // the target positions to generate the lod around
-//! 	4, // amount of detail
-//! 	|pos| Chunk {} // and the function to construct the chunk with
+//!     &[OctVec::new(8, 8, 8, 8)], // the target positions to generate the lod around
+//!     4, // amount of detail
+//!     |pos| Chunk {} // and the function to construct the chunk with
 //!                    // NOTE: this is only called for completely new chunks, not the ones loaded from the chunk cache!
 //! );
 //! ```
@@ -62,14 +62,14 @@
 //! # impl Chunk {
 //! #     fn expensive_init(&mut self, pos: QuadVec) {}
 //! # }
-//! # let mut tree = Tree::<Chunk, QuadVec>::new();
+//! # let mut tree = Tree::<Chunk, QuadVec>::new(64);
 //! tree.get_chunks_to_add_slice_mut()
-//! 	.iter_mut() // or par_iter_mut() if you're using rayon
-//! 	.for_each(|(position, chunk)| {
+//!     .iter_mut() // or par_iter_mut() if you're using rayon
+//!     .for_each(|ToAddContainer { position, chunk }| {
 //!
-//! 		// and run expensive init, probably does something with procedural generation
-//! 		chunk.expensive_init(*position);
-//! 	});
+//!         // and run expensive init, probably does something with procedural generation
+//!         chunk.expensive_init(*position);
+//!     });
 //! ```
 //!
 //! Next, we'll also want to change the visibility of some chunks so they don't overlap with higher detail lods.
@@ -80,14 +80,14 @@
 //! # impl Chunk {
 //! #     fn set_visible(&mut self, v: bool) {}
 //! # }
-//! # let mut tree = Tree::<Chunk, QuadVec>::new();
+//! # let mut tree = Tree::<Chunk, QuadVec>::new(64);
 //! // and make all chunks visible or not
 //! for chunk in tree.iter_chunks_to_activate_mut() {
-//! 	chunk.set_visible(true);
+//!     chunk.set_visible(true);
 //! }
 //!
 //! for chunk in tree.iter_chunks_to_deactivate_mut() {
-//! 	chunk.set_visible(false);
+//!     chunk.set_visible(false);
 //! }
 //! ```
 //! We'll probably also want to do some cleanup with chunks that are removed.
@@ -99,9 +99,9 @@
 //! # impl Chunk {
 //! #     fn cleanup(&mut self) {}
 //! # }
-//! # let mut tree = Tree::<Chunk, QuadVec>::new();
+//! # let mut tree = Tree::<Chunk, QuadVec>::new(64);
 //! for chunk in tree.iter_chunks_to_remove_mut() {
-//! 	chunk.cleanup();
+//!     chunk.cleanup();
 //! }
 //! ```
 //! And finally, actually update the tree with the new chunks.
@@ -110,7 +110,7 @@
 //! # use lodtree::*;
 //! # use lodtree::coords::QuadVec;
 //! # struct Chunk {}
-//! # let mut tree = Tree::<Chunk, QuadVec>::new();
+//! # let mut tree = Tree::<Chunk, QuadVec>::new(64);
 //! tree.do_update();
 //! ```
 //! But we're not done yet!
@@ -123,9 +123,9 @@
 //! # impl Chunk {
 //! #     fn true_cleanup(&mut self) {}
 //! # }
-//! # let mut tree = Tree::<Chunk, QuadVec>::new();
-//! for (position, chunk) in tree.get_chunks_to_delete_slice_mut().iter_mut() { // there's also an iterator for just chunks here
-//! 	chunk.true_cleanup();
+//! # let mut tree = Tree::<Chunk, QuadVec>::new(64);
+//! for ToDeleteContainer { position, chunk } in tree.get_chunks_to_delete_slice_mut().iter_mut() { // there's also an iterator for just chunks here
+//!     chunk.true_cleanup();
 //! }
 //!
 //! // and finally, complete the entire update
@@ -158,6 +158,13 @@
 //!
 //! Cached chunks are also stored seperate from the tree, inside a HashMap. These can't be accessed.
 //!
+//! # Features
+//! - `testing`: adds the `testing` module, with a conformance check for custom LodVec implementations.
+//! - `tracing`: emits `tracing` spans and events for `prepare_update` and `do_update`, with the amount of chunks that changed.
+//! - `ffi`: adds methods on quadtrees and octrees that take raw coordinates instead of QuadVec and OctVec, for wrapping the crate in a C API.
+//! - `rayon`: adds `prepare_update_par`, which makes the new chunks in parallel with rayon, and `par_iter_chunks` and `par_iter_chunks_mut` to go over all chunks in parallel.
+//! - `serde`: implements `Serialize` and `Deserialize` for the coordinate types in `coords`, and for the `SerializedTree` given by `serialize_structure`.
+//!
 //! # Iterators
 //! Iterators are provided for each chunk group, in the flavour of chunks, mutable chunks, chunk and positions and mutable chunk and positions.
 //!
@@ -166,6 +173,8 @@
 
 pub mod coords;
 pub mod iter;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod traits;
 pub mod tree;
 
@@ -0,0 +1,318 @@
+//! Contains a conformance check for LodVec implementations, to test custom coordinate types with.
+//! This is only available with the `testing` feature.
+
+use crate::traits::LodVec;
+
+/// checks if a LodVec implementation behaves consistently, and panics if it doesn't.
+/// This is meant to be called from the tests of a crate that implements LodVec for it's own type.
+///
+/// The checks go down the first few levels of the tree, and check that:
+/// - the root spans the entire tree, with a size of 1 and float coords at 0
+/// - num_children is 2 to the power of the number of axes, and all children of a node are distinct
+/// - every node contains itself and it's children, and exactly one node at the level above contains it
+/// - the parent of a child is the node it came from, and the root has no parent
+/// - get_child_index gives the index the child was made with
+/// - neighbors are at the same depth, and going back gives the same node, unless the node is on the boundary
+/// - containment_cmp puts a node before it's children, and children in the order of their index
+/// - children are half the size of their parent, and inside of it
+/// - can_subdivide is true for a node that contains a deeper target, and false for a target at the same depth
+/// - the root is on all boundaries, and every node is on exactly one side of each axis if it spans that axis
+///
+/// ```rust
+/// # use lodtree::coords::QuadVec;
+/// lodtree::testing::check_lodvec_conformance::<QuadVec>();
+/// ```
+pub fn check_lodvec_conformance<L: LodVec + std::fmt::Debug>() {
+    // how many levels to check
+    const DEPTH: usize = 3;
+
+    let root = L::root();
+    let axes = root.get_float_coords().as_ref().len();
+
+    // the root spans the entire tree
+    assert_eq!(root.get_size(), 1.0, "root size should be 1");
+    assert!(
+        root.get_float_coords().as_ref().iter().all(|x| *x == 0.0),
+        "root float coords should be 0"
+    );
+    assert_eq!(
+        L::num_children(),
+        1 << axes,
+        "num_children should be 2 to the power of the number of axes"
+    );
+
+    assert_eq!(root.parent(), None, "root should not have a parent");
+
+    for axis in 0..axes {
+        assert!(
+            root.on_boundary(axis, false) && root.on_boundary(axis, true),
+            "root should be on all boundaries"
+        );
+    }
+
+    // all nodes at the current level
+    let mut level = vec![root];
+
+    for _ in 0..DEPTH {
+        let mut next_level = Vec::with_capacity(level.len() * L::num_children());
+
+        for &parent in level.iter() {
+            assert!(
+                parent.contains_child_node(parent),
+                "{:?} should contain itself",
+                parent
+            );
+
+            let children = (0..L::num_children())
+                .map(|i| parent.get_child(i))
+                .collect::<Vec<_>>();
+
+            for (i, &child) in children.iter().enumerate() {
+                // children are unique
+                assert!(
+                    children[..i].iter().all(|other| *other != child),
+                    "children of {:?} should be distinct",
+                    parent
+                );
+
+                // and inside their parent
+                assert!(
+                    parent.contains_child_node(child),
+                    "{:?} should contain it's child {:?}",
+                    parent,
+                    child
+                );
+                assert!(
+                    !child.contains_child_node(parent),
+                    "{:?} should not contain it's parent {:?}",
+                    child,
+                    parent
+                );
+                assert_eq!(
+                    child.parent(),
+                    Some(parent),
+                    "{:?} should have {:?} as parent",
+                    child,
+                    parent
+                );
+                assert_eq!(
+                    child.get_child_index(),
+                    i,
+                    "{:?} should have child index {}",
+                    child,
+                    i
+                );
+                assert_eq!(
+                    child.get_size() * 2.0,
+                    parent.get_size(),
+                    "{:?} should be half the size of it's parent",
+                    child
+                );
+
+                let parent_coords = parent.get_float_coords();
+                let child_coords = child.get_float_coords();
+
+                assert!(
+                    parent_coords
+                        .as_ref()
+                        .iter()
+                        .zip(child_coords.as_ref())
+                        .all(|(p, c)| *c >= *p && c + child.get_size() <= p + parent.get_size()),
+                    "{:?} should be inside of it's parent {:?}",
+                    child,
+                    parent
+                );
+
+                // the parent can subdivide for a target inside it
+                assert!(
+                    child.can_subdivide(parent, 0),
+                    "{:?} should subdivide for the target {:?}",
+                    parent,
+                    child
+                );
+                assert!(
+                    !child.can_subdivide(child, 0),
+                    "{:?} should not subdivide for a target at the same depth",
+                    child
+                );
+
+                // parents go first, and children are in order
+                assert_eq!(
+                    parent.containment_cmp(child),
+                    std::cmp::Ordering::Less,
+                    "{:?} should be ordered before it's child {:?}",
+                    parent,
+                    child
+                );
+
+                if i > 0 {
+                    assert_eq!(
+                        children[i - 1].containment_cmp(child),
+                        std::cmp::Ordering::Less,
+                        "{:?} should be ordered before {:?}",
+                        children[i - 1],
+                        child
+                    );
+                }
+
+                // neighbors are only missing past the boundary
+                for direction in 0..axes * 2 {
+                    match child.get_neighbor(direction) {
+                        Some(neighbor) => {
+                            assert_eq!(
+                                neighbor.get_size(),
+                                child.get_size(),
+                                "neighbor {:?} of {:?} should be at the same depth",
+                                neighbor,
+                                child
+                            );
+                            assert_eq!(
+                                neighbor.get_neighbor(direction ^ 1),
+                                Some(child),
+                                "neighbor {:?} of {:?} should have it as neighbor",
+                                neighbor,
+                                child
+                            );
+                        }
+                        None => assert!(
+                            child.on_boundary(direction / 2, direction % 2 == 1),
+                            "{:?} should have a neighbor in direction {}",
+                            child,
+                            direction
+                        ),
+                    }
+                }
+
+                // only on one side of each axis, if the node doesn't span it
+                for axis in 0..axes {
+                    assert!(
+                        !(child.on_boundary(axis, false) && child.on_boundary(axis, true)),
+                        "{:?} can't be on both sides of axis {}",
+                        child,
+                        axis
+                    );
+                }
+            }
+
+            next_level.extend(children);
+        }
+
+        // every node has exactly one parent
+        for &child in next_level.iter() {
+            assert_eq!(
+                level
+                    .iter()
+                    .filter(|parent| parent.contains_child_node(child))
+                    .count(),
+                1,
+                "{:?} should have exactly one parent",
+                child
+            );
+        }
+
+        level = next_level;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::coords::*;
+
+    #[test]
+    fn quadvec_conformance() {
+        check_lodvec_conformance::<QuadVec>();
+    }
+
+    #[test]
+    fn octvec_conformance() {
+        check_lodvec_conformance::<OctVec>();
+    }
+
+    #[test]
+    fn linevec_conformance() {
+        check_lodvec_conformance::<LineVec>();
+    }
+
+    #[test]
+    fn nvec_conformance() {
+        check_lodvec_conformance::<NVec<1>>();
+        check_lodvec_conformance::<NVec<2>>();
+        check_lodvec_conformance::<NVec<4>>();
+    }
+
+    // an OctVec that only implements the required methods, to check the default ones
+    #[derive(Copy, Clone, PartialEq, Eq, Default, Debug, Hash)]
+    struct Minimal(OctVec);
+
+    impl LodVec for Minimal {
+        type FloatCoords = [f64; 3];
+
+        fn get_child(self, index: usize) -> Self {
+            Self(self.0.get_child(index))
+        }
+
+        fn num_children() -> usize {
+            OctVec::num_children()
+        }
+
+        fn root() -> Self {
+            Self(OctVec::root())
+        }
+
+        fn can_subdivide(self, node: Self, detail: u64) -> bool {
+            self.0.can_subdivide(node.0, detail)
+        }
+
+        fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool {
+            self.0.is_inside_bounds(min.0, max.0, max_depth)
+        }
+
+        fn contains_child_node(self, child: Self) -> bool {
+            self.0.contains_child_node(child.0)
+        }
+
+        fn get_float_coords(self) -> Self::FloatCoords {
+            LodVec::get_float_coords(self.0)
+        }
+
+        fn get_size(self) -> f64 {
+            LodVec::get_size(self.0)
+        }
+
+        fn on_boundary(self, axis: usize, positive: bool) -> bool {
+            self.0.on_boundary(axis, positive)
+        }
+
+        fn get_neighbor(self, direction: usize) -> Option<Self> {
+            self.0.get_neighbor(direction).map(Self)
+        }
+
+        fn containment_cmp(self, other: Self) -> std::cmp::Ordering {
+            self.0.containment_cmp(other.0)
+        }
+    }
+
+    #[test]
+    fn default_methods_conformance() {
+        check_lodvec_conformance::<Minimal>();
+
+        // and they give the same as the ones from OctVec
+        for position in [
+            OctVec::root(),
+            OctVec::new(1, 0, 1, 1),
+            OctVec::new(5, 2, 7, 3),
+            OctVec::new(1 << 40, 3, (1 << 41) - 1, 41),
+        ] {
+            assert_eq!(Minimal(position).parent().map(|x| x.0), position.parent());
+
+            if position != OctVec::root() {
+                assert_eq!(
+                    Minimal(position).get_child_index(),
+                    position.get_child_index()
+                );
+            }
+        }
+    }
+}
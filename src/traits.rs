@@ -10,9 +10,45 @@ pub trait LodVec: std::hash::Hash + Eq + Sized + Copy + Clone + Send + Sync + De
     /// get the number of child nodes a node can have in the tree.
     fn num_children() -> usize;
 
+    /// the deepest depth a node can be at, as deeper positions don't fit in the coordinates.
+    /// The default fits positions with u64 coordinates, where a node at a depth has coordinates below 1 << depth
+    const MAX_DEPTH: u8 = 63;
+
     /// returns the lod vector as if it's at the root of the tree.
     fn root() -> Self;
 
+    /// gets the position of the parent node, or None if this is the root.
+    /// get_child on the parent with the right index gives this node again.
+    /// The default goes down from the root until it finds this node, so implementations should replace it with something faster
+    fn parent(self) -> Option<Self> {
+        let mut current = Self::root();
+
+        while current != self {
+            // go down to the child that has this node
+            let child = (0..Self::num_children())
+                .map(|i| current.get_child(i))
+                .find(|child| child.contains_child_node(self))?;
+
+            if child == self {
+                return Some(current);
+            }
+
+            current = child;
+        }
+
+        // the root has no parent
+        None
+    }
+
+    /// gets the index of this node in it's parent, so that get_child on the parent with this index gives this node again.
+    /// The result is unspecified for the root.
+    /// The default finds the child of the parent that is this node
+    fn get_child_index(self) -> usize {
+        self.parent()
+            .and_then(|parent| (0..Self::num_children()).find(|&i| parent.get_child(i) == self))
+            .unwrap_or(0)
+    }
+
     /// wether the node can subdivide, compared to another node and the required detail.
     ///
     /// Assumes self is the target position for a lod.
@@ -21,7 +57,7 @@ pub trait LodVec: std::hash::Hash + Eq + Sized + Copy + Clone + Send + Sync + De
     ///
     /// if the detail is 0, this may only return true if self is inside the node.
     ///
-    /// The implementation used in the QuadVec implementation is as follows:
+    /// The implementation used in the QuadVec implementation is as follows, where the math is done with u128 so a big detail doesn't overflow:
     /// ```rust
     /// # struct Chunk { x: u64, y: u64, depth: u8 }
     /// # impl Chunk {
@@ -34,38 +70,48 @@ pub trait LodVec: std::hash::Hash + Eq + Sized + Copy + Clone + Send + Sync + De
     ///    // difference in lod level between the target and the node
     ///    let level_difference = self.depth - node.depth;
     ///
-    ///    // minimum corner of the bounding box
-    ///    let min = (
-    ///        (node.x << (level_difference + 1))
-    ///            .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-    ///        (node.y << (level_difference + 1))
-    ///            .saturating_sub(((detail + 1) << level_difference) - (1 << level_difference)),
-    ///    );
+    ///    // check if the target is inside of the bounding box around the node on both axes
+    ///    in_subdivide_range(self.x, node.x, level_difference, detail)
+    ///        && in_subdivide_range(self.y, node.y, level_difference, detail)
+    /// }
+    /// # }
+    ///
+    /// fn in_subdivide_range(target: u64, node: u64, level_difference: u8, detail: u64) -> bool {
+    ///    // position of the node, one lod level deeper than the target
+    ///    let node = (node as u128) << (level_difference + 1);
+    ///
+    ///    // how far the range goes around the node
+    ///    let detail = (detail as u128 + 1) << level_difference;
+    ///    let half_size = 1u128 << level_difference;
+    ///
+    ///    // minimum of the bounding range
+    ///    let min = node.saturating_sub(detail - half_size);
     ///
     ///    // max as well
-    ///    let max = (
-    ///        (node.x << (level_difference + 1))
-    ///            .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-    ///        (node.y << (level_difference + 1))
-    ///            .saturating_add(((detail + 1) << level_difference) + (1 << level_difference)),
-    ///    );
+    ///    let max = node.saturating_add(detail + half_size);
     ///
     ///    // local position of the target, which is one lod level higher to allow more detail
-    ///    let local = (self.x << 1, self.y << 1);
+    ///    let local = (target as u128) << 1;
     ///
-    ///    // check if the target is inside of the bounding box
-    ///    local.0 >= min.0 && local.0 < max.0 && local.1 >= min.1 && local.1 < max.1
+    ///    local >= min && local < max
     /// }
-    /// # }
     /// ```
     fn can_subdivide(self, node: Self, detail: u64) -> bool;
 
     /// check if this chunk is inside of a bounding box
-    /// where min is the lowest corner of the box, and max is the highest corner
+    /// where min is the lowest corner of the box, and max is the highest corner.
+    ///
+    /// min is inclusive and max is exclusive on every axis, so a box from (0, 0) to (2, 2) at depth 1 covers the nodes at x and y 0 and 1.
+    /// min and max can be at a different depth than this node, in which case all three are compared at the lowest of their depths,
+    /// by shifting the coordinates down, which rounds both min and max down, so a node that only partly overlaps the max side of the box doesn't count as inside.
+    /// Nodes deeper than max_depth are never inside, while nodes at max_depth can be.
+    ///
     /// The implementation for QuadVec is as follows:
     /// ```rust
+    /// # #[derive(Clone, Copy)]
     /// # struct Chunk { x: u64, y: u64, depth: u8 }
     /// # impl Chunk {
+    /// # fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool {
     /// // get the lowest lod level
     /// let level = self.depth.min(min.depth.min(max.depth));
     ///
@@ -74,7 +120,7 @@ pub trait LodVec: std::hash::Hash + Eq + Sized + Copy + Clone + Send + Sync + De
     /// let min_difference = min.depth - level;
     /// let max_difference = max.depth - level;
     ///
-    //// // get the coords to that level
+    /// // get the coords to that level
     /// let self_x = self.x >> self_difference;
     /// let self_y = self.y >> self_difference;
     ///
@@ -86,14 +132,42 @@ pub trait LodVec: std::hash::Hash + Eq + Sized + Copy + Clone + Send + Sync + De
     ///
     /// // then check if we are inside the AABB
     /// self.depth as u64 <= max_depth
-    /// 	&& self_x >= min_x
-    /// 	&& self_x < max_x
-    /// 	&& self_y >= min_y
-    /// 	&& self_y < max_y
+    ///     && self_x >= min_x
+    ///     && self_x < max_x
+    ///     && self_y >= min_y
+    ///     && self_y < max_y
+    /// # }
     /// # }
     /// ```
     fn is_inside_bounds(self, min: Self, max: Self, max_depth: u64) -> bool;
 
-    /// Wether this node contains a child node
+    /// Wether this node contains a child node, at any depth below it.
+    /// A node contains itself, and returns false if the child is shallower than this node.
     fn contains_child_node(self, child: Self) -> bool;
+
+    /// float coordinates of a node, with one value per axis.
+    /// QuadVec and OctVec use [f64; 2] and [f64; 3] for this, so it can be indexed in generic code.
+    type FloatCoords: Copy + PartialEq + std::fmt::Debug + AsRef<[f64]> + AsMut<[f64]>;
+
+    /// gets the float coords of the lowest corner of this node, where the root spans from 0 to 1 on all axes.
+    fn get_float_coords(self) -> Self::FloatCoords;
+
+    /// gets the size this node takes up on each axis, where the root has a size of 1.
+    fn get_size(self) -> f64;
+
+    /// wether this node is flush against a face of the entire tree.
+    /// # Args
+    /// * `axis` the axis of the face, 0 for x, 1 for y, and so on
+    /// * `positive` wether to check the face at the highest coordinate on the axis, instead of the face at 0
+    fn on_boundary(self, axis: usize, positive: bool) -> bool;
+
+    /// gets the node at the same depth next to this one, or None if that would be outside of the tree, or the direction is past the last axis.
+    /// # Args
+    /// * `direction` the direction to go in, where direction / 2 is the axis, and an odd direction goes towards the higher coordinate on that axis.
+    ///   So for QuadVec 0 is -x, 1 is +x, 2 is -y and 3 is +y
+    fn get_neighbor(self, direction: usize) -> Option<Self>;
+
+    /// compares two nodes in the order they are visited when going depth first through the tree.
+    /// A node comes before all nodes it contains, and nodes that don't contain each other are in Morton order, same as the child indices.
+    fn containment_cmp(self, other: Self) -> std::cmp::Ordering;
 }
@@ -1,9 +1,15 @@
 //! Contains the tree struct, which is used to hold all chunks
 
+use crate::coords::*;
+use crate::iter::center_distance_squared;
 use crate::traits::*;
 
-use std::collections::{HashMap, VecDeque};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
 use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
 // struct for keeping track of chunks
 // keeps track of the parent and child indices
@@ -15,6 +21,12 @@ pub(crate) struct TreeNode {
 
     // where the chunk for this node is stored
     pub(crate) chunk: usize,
+
+    // generation of this node, this changes every time the node is allocated or freed
+    pub(crate) generation: u64,
+
+    // depth of this node in the tree, so it doesn't need to be found by going down from the root
+    pub(crate) depth: u8,
 }
 
 // utility struct for holding actual chunks and the node that owns them
@@ -23,6 +35,86 @@ pub(crate) struct ChunkContainer<C: Sized, L: LodVec> {
     pub(crate) chunk: C,
     pub(crate) index: usize,
     pub(crate) position: L,
+    pub(crate) active: bool, // wether the chunk is active, leaves are active by default
+    pub(crate) last_access: AccessStamp, // when the chunk was last accessed, for the chunk limit
+}
+
+// utility struct for a point in time for access tracking
+// this is atomic so it can be changed while the tree is borrowed immutably
+#[derive(Debug, Default)]
+pub(crate) struct AccessStamp(AtomicU64);
+
+impl AccessStamp {
+    // gets the time
+    #[inline]
+    fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    // sets the time
+    #[inline]
+    fn set(&self, time: u64) {
+        self.0.store(time, Ordering::Relaxed);
+    }
+
+    // moves the time forward, and returns the new time
+    #[inline]
+    fn tick(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    // marks something as accessed now, where clock is the clock of the tree, or None if accesses aren't tracked
+    #[inline]
+    pub(crate) fn touch(&self, clock: Option<&AccessStamp>) {
+        if let Some(clock) = clock {
+            self.set(clock.tick());
+        }
+    }
+
+    // makes a stamp for something accessed now, where this is the clock
+    // stamps are only read with a chunk limit, so without one the clock isn't touched
+    #[inline]
+    fn stamp(&self, chunk_limit: Option<usize>) -> Self {
+        match chunk_limit {
+            Some(_) => Self(AtomicU64::new(self.tick())),
+            None => Self::default(),
+        }
+    }
+}
+
+impl Clone for AccessStamp {
+    fn clone(&self) -> Self {
+        Self(AtomicU64::new(self.get()))
+    }
+}
+
+// utility struct for the function that's called when a chunk leaves the tree
+// clones of the tree share it, and it's behind a mutex so the tree can still be shared between threads
+pub(crate) struct RemoveCallback<C, L>(Arc<Mutex<RemoveFn<C, L>>>);
+
+// the function itself
+type RemoveFn<C, L> = dyn FnMut(L, &mut C) + Send;
+
+impl<C, L> RemoveCallback<C, L> {
+    // calls the function
+    #[inline]
+    fn call(&self, position: L, chunk: &mut C) {
+        // if the function panicked before, it can still be called again
+        let mut function = self.0.lock().unwrap_or_else(|error| error.into_inner());
+        function(position, chunk);
+    }
+}
+
+impl<C, L> Clone for RemoveCallback<C, L> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<C, L> std::fmt::Debug for RemoveCallback<C, L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RemoveCallback")
+    }
 }
 
 /// holds a chunk to add and it's position
@@ -54,6 +146,227 @@ pub struct ToDeleteContainer<C: Sized, L: LodVec> {
     pub position: L,
 }
 
+/// positions that would change in an update, as given by predict_update
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UpdatePlan<L: LodVec> {
+    /// positions of the chunks that would be added
+    pub to_add: Vec<L>,
+
+    /// positions of the chunks that would be removed
+    pub to_remove: Vec<L>,
+}
+
+/// the update that's prepared in a tree, as given by pending_update.
+/// This borrows the tree, and shows what do_update is going to do
+#[derive(Debug)]
+pub struct UpdateDiff<'a, C: Sized, L: LodVec> {
+    tree: &'a Tree<C, L>,
+}
+
+impl<'a, C: Sized, L: LodVec> UpdateDiff<'a, C, L> {
+    /// the chunks that are going to be added, and their positions
+    #[inline]
+    pub fn to_add(&self) -> &'a [ToAddContainer<C, L>] {
+        &self.tree.chunks_to_add[..]
+    }
+
+    /// the positions and chunks that are going to be removed
+    #[inline]
+    pub fn to_remove(&self) -> impl Iterator<Item = (L, &'a C)> {
+        let tree = self.tree;
+
+        (0..tree.get_num_chunks_to_remove()).map(move |i| {
+            (
+                tree.get_position_of_chunk_to_remove(i),
+                tree.get_chunk_to_remove(i),
+            )
+        })
+    }
+
+    /// the positions and chunks that are going to be activated
+    #[inline]
+    pub fn to_activate(&self) -> impl Iterator<Item = (L, &'a C)> {
+        let tree = self.tree;
+
+        (0..tree.get_num_chunks_to_activate()).map(move |i| {
+            (
+                tree.get_position_of_chunk_to_activate(i),
+                tree.get_chunk_to_activate(i),
+            )
+        })
+    }
+
+    /// the positions and chunks that are going to be deactivated
+    #[inline]
+    pub fn to_deactivate(&self) -> impl Iterator<Item = (L, &'a C)> {
+        let tree = self.tree;
+
+        (0..tree.get_num_chunks_to_deactivate()).map(move |i| {
+            (
+                tree.get_position_of_chunk_to_deactivate(i),
+                tree.get_chunk_to_deactivate(i),
+            )
+        })
+    }
+
+    /// number of chunks that are going to be added
+    #[inline]
+    pub fn num_to_add(&self) -> usize {
+        self.tree.get_num_chunks_to_add()
+    }
+
+    /// number of chunks that are going to be removed
+    #[inline]
+    pub fn num_to_remove(&self) -> usize {
+        self.tree.get_num_chunks_to_remove()
+    }
+
+    /// number of chunks that are going to be activated
+    #[inline]
+    pub fn num_to_activate(&self) -> usize {
+        self.tree.get_num_chunks_to_activate()
+    }
+
+    /// number of chunks that are going to be deactivated
+    #[inline]
+    pub fn num_to_deactivate(&self) -> usize {
+        self.tree.get_num_chunks_to_deactivate()
+    }
+
+    /// wether the update doesn't add or remove anything
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.num_to_add() == 0 && self.num_to_remove() == 0
+    }
+}
+
+/// id of a chunk in the tree, which stays the same as long as the chunk isn't removed from the tree
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkId {
+    // the node the chunk belongs to
+    pub(crate) node: usize,
+
+    // and the generation of that node
+    pub(crate) generation: u64,
+}
+
+/// error for when a position is outside of the root of the tree, as given by try_get_chunk
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PositionOutOfRange;
+
+impl std::fmt::Display for PositionOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "position is outside of the root of the tree")
+    }
+}
+
+impl std::error::Error for PositionOutOfRange {}
+
+/// statistics of all updates done since they were enabled with enable_stats
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct UpdateStats {
+    /// number of times do_update was called
+    pub updates: u64,
+
+    /// total number of chunks added
+    pub total_added: u64,
+
+    /// total number of chunks removed
+    pub total_removed: u64,
+
+    /// most chunks added in a single update
+    pub max_added: u64,
+
+    /// most chunks removed in a single update
+    pub max_removed: u64,
+}
+
+impl UpdateStats {
+    /// average number of chunks added per update
+    #[inline]
+    pub fn average_added(&self) -> f64 {
+        self.total_added as f64 / self.updates.max(1) as f64
+    }
+
+    /// average number of chunks removed per update
+    #[inline]
+    pub fn average_removed(&self) -> f64 {
+        self.total_removed as f64 / self.updates.max(1) as f64
+    }
+}
+
+/// differences between the positions of the chunks in two trees, as given by diff
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TreeDiff<L: LodVec> {
+    /// positions that are only in the first tree
+    pub removed: Vec<L>,
+
+    /// positions that are only in the second tree
+    pub added: Vec<L>,
+
+    /// positions that are in both trees, but with a different chunk
+    pub changed: Vec<L>,
+}
+
+/// what was fixed by repair, with a count for each kind of problem that was found
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RepairReport {
+    /// nodes that had children that were out of range or didn't have a chunk, these are now leaves
+    pub detached_children: usize,
+
+    /// chunks with a wrong node index or position, or nodes with a wrong depth, that were corrected
+    pub stale_back_pointers: usize,
+
+    /// chunks that didn't belong to any node in the tree, these were moved to the chunks to delete
+    pub orphaned_chunks: usize,
+
+    /// nodes that were neither in the tree nor free, or free while still in the tree, and are now put right
+    pub free_list_fixes: usize,
+
+    /// entries in the lists for the next update that were dropped, as the update might have been half applied
+    pub pending_dropped: usize,
+}
+
+impl RepairReport {
+    /// wether nothing needed to be repaired
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// structure of a tree without any chunks, as given by serialize_structure.
+/// This keeps the exact layout of the nodes, so a tree rebuilt from it stores everything in the same place
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializedTree<L: LodVec> {
+    /// position of the root of the tree
+    pub root: L,
+
+    /// all nodes, at the index they are stored at in the tree, or None if the node is free
+    pub nodes: Vec<Option<SerializedNode<L>>>,
+
+    /// indices of the free nodes, in the order they are reused
+    pub free_list: Vec<usize>,
+}
+
+/// a node in a SerializedTree
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SerializedNode<L: LodVec> {
+    /// position of the node
+    pub position: L,
+
+    /// index of the first child, the other children come right after it
+    pub children: Option<usize>,
+
+    /// index of the chunk of this node
+    pub chunk: usize,
+
+    /// wether the chunk is active
+    pub active: bool,
+}
+
 // utility struct for holding chunks in the queue
 #[derive(Clone, Debug)]
 struct QueueContainer<L: LodVec> {
@@ -64,6 +377,8 @@ struct QueueContainer<L: LodVec> {
 // Tree holding all chunks
 // partially based on: https://stackoverflow.com/questions/41946007/efficient-and-well-explained-implementation-of-a-quadtree-for-2d-collision-det
 // assumption here is that because of the fact that we need to keep inactive chunks in memory for later use, we can keep them together with the actual nodes.
+// cloning copies everything, including the cache and any pending update, so the clone can be changed without affecting the original
+// the only exception is the function set with set_on_remove, which is shared between the clone and the original
 #[derive(Clone, Debug)]
 pub struct Tree<C: Sized, L: LodVec> {
     /// All chunks in the tree
@@ -82,6 +397,18 @@ pub struct Tree<C: Sized, L: LodVec> {
     /// actual chunk to add
     chunks_to_add: Vec<ToAddContainer<C, L>>,
 
+    /// chunks to add that still need to be filled in, for updates prepared with prepare_update_structural
+    chunks_to_fill: Vec<ToAddContainer<Option<C>, L>>,
+
+    /// positions of the chunks to add that were taken with take_chunks_to_add, in the order they need to be given back in
+    taken_chunks_to_add: Vec<L>,
+
+    /// indices of the chunks to fill that weren't given out by next_chunk_to_generate yet, with the closest at the end
+    fill_queue: Vec<usize>,
+
+    /// what modifications was when the fill queue was made, so it's made again when the tree changed
+    fill_queue_modifications: Option<u64>,
+
     /// chunk indices to be removed, tuple of index, parent index
     chunks_to_remove: Vec<ToRemoveContainer>,
 
@@ -105,6 +432,43 @@ pub struct Tree<C: Sized, L: LodVec> {
 
     /// chunks that are going to be permamently removed, due to not fitting in the cache anymore
     chunks_to_delete: Vec<ToDeleteContainer<C, L>>,
+
+    /// number of times the tree was modified, iterators check this to catch the tree changing underneath them.
+    /// Iterators borrow the tree, so safe code can't change it while one is alive, and this check is only a backstop for unsafe code or misuse inside the crate.
+    /// Those checks are debug_assert, so they're only done in debug builds,
+    /// but the counter itself is always kept, as next_chunk_to_generate also uses it to see if the tree changed, and it's only a single add per change
+    pub(crate) modifications: u64,
+
+    /// roots of the regions that can't be merged
+    frozen: HashSet<L>,
+
+    /// positions of the chunks that were marked as dirty
+    dirty: HashSet<L>,
+
+    /// targets of the last update with their weight, so the detail can be changed without giving them again
+    /// targets from the unweighted updates have a weight of 1
+    last_targets: Vec<(L, f64)>,
+
+    /// statistics of the updates, if enabled
+    stats: Option<UpdateStats>,
+
+    /// next generation to give to a node
+    generation: u64,
+
+    /// position of the root node, everything in the tree is inside of it
+    pub(crate) root: L,
+
+    /// depth of the root node
+    pub(crate) root_depth: u8,
+
+    /// max number of chunks in the tree, if any
+    pub(crate) chunk_limit: Option<usize>,
+
+    /// current time for access tracking, goes up on every access
+    pub(crate) access_clock: AccessStamp,
+
+    /// called for every chunk that leaves the tree, if set
+    on_remove: Option<RemoveCallback<C, L>>,
 }
 
 impl<C, L> Tree<C, L>
@@ -114,23 +478,22 @@ where
 {
     // helper function for later, gets a node index from a position
     fn get_node_index_from_position(&self, position: L) -> Option<usize> {
-        // the current node
-        let mut current = *self.nodes.get(0)?;
+        // the current node, the root is always at 0
+        let mut current_index = 0;
+        let mut current = *self.nodes.first()?;
 
         // and position
-        let mut current_position = L::root();
+        let mut current_position = self.root;
 
         // then loop
         loop {
             // if the current node is the one we are looking for, return
             if current_position == position {
-                return Some(current.chunk);
+                return Some(current_index);
             }
 
             // if the current node does not have children, stop
-			// this works according to clippy
-            current.children?;
-            
+            let children = current.children?;
 
             // if not, go over the node children
             if let Some((index, found_position)) = (0..L::num_children())
@@ -141,7 +504,8 @@ where
                 current_position = found_position;
 
                 // and the node is at the index of the child nodes + index
-                current = self.nodes[current.children.unwrap().get() + index];
+                current_index = children.get() + index;
+                current = self.nodes[current_index];
             } else {
                 // if no child got found that matched the item, return none
                 return None;
@@ -149,13 +513,69 @@ where
         }
     }
 
+    // helper function, gets the index of the deepest node that contains a position
+    fn get_deepest_node_index_containing(&self, position: L) -> Option<usize> {
+        // the position has to be in the tree
+        if !self.root.contains_child_node(position) {
+            return None;
+        }
+
+        let mut current_index = 0;
+        let mut current = *self.nodes.first()?;
+        let mut current_position = self.root;
+
+        // go down as long as there are children
+        while let Some(children) = current.children {
+            if current_position == position {
+                break;
+            }
+
+            let index = (0..L::num_children())
+                .find(|&i| current_position.get_child(i).contains_child_node(position))?;
+
+            current_position = current_position.get_child(index);
+            current_index = children.get() + index;
+            current = self.nodes[current_index];
+        }
+
+        Some(current_index)
+    }
+
+    // helper function, gets the indices of all nodes from the root down to the node at a position
+    fn get_node_path_from_position(&self, position: L) -> Option<Vec<usize>> {
+        // start at the root
+        let mut path = vec![0];
+        let mut current = *self.nodes.first()?;
+        let mut current_position = self.root;
+
+        while current_position != position {
+            // go to the child that has the position, if any
+            let children = current.children?;
+            let index = (0..L::num_children())
+                .find(|&i| current_position.get_child(i).contains_child_node(position))?;
+
+            current_position = current_position.get_child(index);
+            path.push(children.get() + index);
+            current = self.nodes[children.get() + index];
+        }
+
+        Some(path)
+    }
+
     /// create a new, empty tree
+    /// # Params
+    /// * `cache_size` the max amount of removed chunks kept around to be added back later.
+    ///   To allocate room for the chunks and nodes up front, use with_capacity instead
     pub fn new(cache_size: usize) -> Self {
         // make a new Tree
         // also allocate some room for nodes
         Self {
             chunks_to_add_parent: Vec::with_capacity(512),
             chunks_to_add: Vec::with_capacity(512),
+            chunks_to_fill: Vec::new(),
+            taken_chunks_to_add: Vec::new(),
+            fill_queue: Vec::new(),
+            fill_queue_modifications: None,
             chunks_to_remove: Vec::with_capacity(512),
             chunks_to_activate: Vec::with_capacity(512),
             chunks_to_deactivate: Vec::with_capacity(512),
@@ -167,7 +587,91 @@ where
             chunk_cache: HashMap::with_capacity(cache_size),
             cache_queue: VecDeque::with_capacity(cache_size),
             chunks_to_delete: Vec::with_capacity(cache_size),
+            modifications: 0,
+            frozen: HashSet::new(),
+            dirty: HashSet::new(),
+            last_targets: Vec::new(),
+            stats: None,
+            generation: 0,
+            root: L::root(),
+            root_depth: 0,
+            chunk_limit: None,
+            access_clock: AccessStamp::default(),
+            on_remove: None,
+        }
+    }
+
+    /// create a new, empty tree that never has more than chunk_limit chunks after an update.
+    /// If an update would go over the limit, prepare_update skips subdividing some nodes, and puts their new chunks in the chunks to delete.
+    /// Chunks the targets still want are kept, so updating until prepare_update returns false still stops once the tree is full.
+    /// If the tree is over the limit even without adding chunks, such as after lowering the limit with set_chunk_limit,
+    /// prepare_update also merges the least recently accessed leaf chunks into their parent until it fits.
+    /// Chunks are accessed when they are added, gotten with get_chunk, get_chunk_from_position, find_chunk and their mutable versions,
+    /// or returned by any of the iterators over the chunks in the tree, so iterating over all chunks marks all of them as accessed.
+    /// The root is always kept, so a limit of 0 still leaves the root chunk in the tree, the same as a limit of 1.
+    /// The cache size starts at 0, and can be changed with set_cache_size
+    pub fn with_chunk_limit(chunk_limit: usize) -> Self {
+        Self {
+            chunk_limit: Some(chunk_limit),
+            ..Self::new(0)
+        }
+    }
+
+    /// create a new, empty tree with room for chunk_cap chunks and node_cap nodes, so the tree doesn't reallocate until it grows past that.
+    /// Every node has a chunk, but nodes that are removed stay around to be reused later, so the tree has at least as many nodes as chunks,
+    /// and when the targets move around a lot, more nodes than chunks.
+    /// The cache size starts at 0, and can be changed with set_cache_size
+    /// # Params
+    /// * `chunk_cap` how many chunks to allocate room for
+    /// * `node_cap` how many nodes to allocate room for, including the ones that are removed but kept around
+    pub fn with_capacity(chunk_cap: usize, node_cap: usize) -> Self {
+        Self {
+            chunks: Vec::with_capacity(chunk_cap),
+            nodes: Vec::with_capacity(node_cap),
+            free_list: VecDeque::with_capacity(node_cap),
+            processing_queue: Vec::with_capacity(node_cap),
+            ..Self::new(0)
+        }
+    }
+
+    /// create a new, empty tree with the given position as root, so it only covers part of the space.
+    /// Positions are the same as in a tree with the default root, but nothing outside of the root is stored in the tree.
+    /// Targets outside of the root can still subdivide nodes near the edge of it, so trees next to each other line up.
+    /// The cache size starts at 0, and can be changed with set_cache_size
+    pub fn with_root(root: L) -> Self {
+        Self::try_with_root(root).expect("Root is not a valid position")
+    }
+
+    // makes a new, empty tree with the given root, or None if the root isn't a valid position
+    fn try_with_root(root: L) -> Option<Self> {
+        // find the depth of the root, by going down to it
+        let mut root_depth = 0u8;
+        let mut current = L::root();
+
+        while current != root {
+            current = (0..L::num_children())
+                .map(|i| current.get_child(i))
+                .find(|child| child.contains_child_node(root))?;
+            root_depth = root_depth.checked_add(1)?;
         }
+
+        Some(Self {
+            root,
+            root_depth,
+            ..Self::new(0)
+        })
+    }
+
+    /// get the position of the root of the tree
+    #[inline]
+    pub fn get_root(&self) -> L {
+        self.root
+    }
+
+    // marks the tree as modified, invalidating any iterators
+    #[inline]
+    fn mark_modified(&mut self) {
+        self.modifications = self.modifications.wrapping_add(1);
     }
 
     /// get the number of chunks in the tree
@@ -179,693 +683,6623 @@ where
     /// get a chunk
     #[inline]
     pub fn get_chunk(&self, index: usize) -> &C {
+        self.touch(index);
         &self.chunks[index].chunk
     }
 
-    /// get a chunk by position, or none if it's not in the tree
+    /// get a chunk by it's id, or none if the chunk was removed from the tree since the id was retrieved
     #[inline]
-    pub fn get_chunk_from_position(&self, position: L) -> Option<&C> {
-        // get the index of the chunk
-        let chunk_index = self.get_node_index_from_position(position)?;
+    pub fn get_chunk_by_id(&self, id: ChunkId) -> Option<&C> {
+        // the node needs to be the same as when the id was made
+        let node = self.nodes.get(id.node)?;
 
-        // and return the chunk
-        Some(&self.chunks[chunk_index].chunk)
+        if node.generation == id.generation {
+            Some(&self.chunks[node.chunk].chunk)
+        } else {
+            None
+        }
     }
 
-    /// get a mutable chunk by position, or none if it's not in the tree
+    /// gets the index of the node at a position, or None if it's not in the tree.
+    /// The index stays the same until the node is removed, and can be used with get_chunk_by_node_index
     #[inline]
-    pub fn get_chunk_from_position_mut(&mut self, position: L) -> Option<&mut C> {
-        // get the index of the chunk
-        let chunk_index = self.get_node_index_from_position(position)?;
-
-        // and return the chunk
-        Some(&mut self.chunks[chunk_index].chunk)
+    pub fn node_index_of(&self, position: L) -> Option<usize> {
+        self.get_node_index_from_position(position)
     }
 
-    /// get a chunk as mutable
+    /// gets the indices of the children of a node, or None if the node is a leaf or the index is out of range.
+    /// Children are always next to each other, so this is a range of num_children indices, in the same order as get_child.
+    /// The root is always at index 0, so together with get_chunk_by_node_index this allows going through the tree in any way
     #[inline]
-    pub fn get_chunk_mut(&mut self, index: usize) -> &mut C {
-        &mut self.chunks[index].chunk
+    pub fn children_of(&self, node_index: usize) -> Option<std::ops::Range<usize>> {
+        let first = self.nodes.get(node_index)?.children?.get();
+
+        Some(first..first + L::num_children())
     }
 
-    /// gets a mutable pointer to a chunk
-    /// This casts get_chunk_mut to a pointer underneath the hood
+    /// wether there is a node at the position in the tree, without touching the chunk.
+    /// This includes nodes that are subdivided, so it's the same as get_chunk_from_position returning a chunk
     #[inline]
-    pub fn get_chunk_pointer_mut(&mut self, index: usize) -> *mut C {
-        self.get_chunk_mut(index)
+    pub fn contains_position(&self, position: L) -> bool {
+        self.get_node_index_from_position(position).is_some()
     }
 
-    /// get the position of a chunk
+    /// get the chunk of the node at an index, as given by node_index_of, or None if that node isn't in use anymore.
+    /// Removed nodes are reused for new nodes, so the index can point to a different chunk later on, use a ChunkId to avoid that
     #[inline]
-    pub fn get_chunk_position(&self, index: usize) -> L {
-        self.chunks[index].position
+    pub fn get_chunk_by_node_index(&self, index: usize) -> Option<&C> {
+        let node = self.nodes.get(index)?;
+
+        // removed nodes still point to the chunk they had, which is now either gone, or belongs to a different node
+        let container = self.chunks.get(node.chunk)?;
+
+        if container.index == index {
+            Some(&container.chunk)
+        } else {
+            None
+        }
     }
 
-    /// get the number of chunks pending activation
+    /// get a chunk by position, or none if it's not in the tree.
+    /// Nodes that are subdivided still have a chunk, so this also works for positions that aren't leaves,
+    /// but positions deeper than the tree goes there give None
     #[inline]
-    pub fn get_num_chunks_to_activate(&self) -> usize {
-        self.chunks_to_activate.len()
+    pub fn get_chunk_from_position(&self, position: L) -> Option<&C> {
+        // get the index of the chunk
+        let chunk_index = self.nodes[self.get_node_index_from_position(position)?].chunk;
+
+        // and return the chunk
+        self.touch(chunk_index);
+        Some(&self.chunks[chunk_index].chunk)
     }
 
-    /// get a chunk pending activation
+    /// get a chunk by position, like get_chunk_from_position, but tells positions outside of the tree apart from chunks that aren't loaded.
+    /// Returns an error if the position is outside of the root, and None if it's inside but there is no chunk for it
     #[inline]
-    pub fn get_chunk_to_activate(&self, index: usize) -> &C {
-        &self.chunks[self.nodes[self.chunks_to_activate[index]].chunk].chunk
+    pub fn try_get_chunk(&self, position: L) -> Result<Option<&C>, PositionOutOfRange> {
+        if !self.root.contains_child_node(position) {
+            return Err(PositionOutOfRange);
+        }
+
+        Ok(self.get_chunk_from_position(position))
     }
 
-    /// get a mutable chunk pending activation
-    #[inline]
-    pub fn get_chunk_to_activate_mut(&mut self, index: usize) -> &mut C {
-        &mut self.chunks[self.nodes[self.chunks_to_activate[index]].chunk].chunk
+    /// sets wether each leaf chunk is active, based on it's position.
+    /// Chunks that aren't leaves are always inactive, and updates (de)activate the chunks they change, like with get_chunk_to_activate
+    /// # Params
+    /// * `f` function that returns wether the leaf at the given position should be active
+    pub fn set_active_where(&mut self, f: impl Fn(L) -> bool) {
+        let Self { chunks, nodes, .. } = self;
+
+        for container in chunks.iter_mut() {
+            container.active = nodes[container.index].children.is_none() && f(container.position);
+        }
     }
 
-    /// gets a mutable pointer to a chunk that is pending activation
-    /// This casts get_chunk_to_activate_mut to a pointer underneath the hood
+    /// wether the chunk at the given position is active, or None if it's not in the tree
     #[inline]
-    pub fn get_chunk_to_activate_pointer_mut(&mut self, index: usize) -> *mut C {
-        self.get_chunk_to_activate_mut(index)
+    pub fn is_active(&self, position: L) -> Option<bool> {
+        let chunk_index = self.nodes[self.get_node_index_from_position(position)?].chunk;
+
+        Some(self.chunks[chunk_index].active)
     }
 
-    /// get the position of a chunk pending activation
+    /// get a mutable chunk by position, or none if it's not in the tree, same as get_chunk_from_position
     #[inline]
-    pub fn get_position_of_chunk_to_activate(&self, index: usize) -> L {
-        self.chunks[self.nodes[self.chunks_to_activate[index]].chunk].position
+    pub fn get_chunk_from_position_mut(&mut self, position: L) -> Option<&mut C> {
+        // get the index of the chunk
+        let chunk_index = self.nodes[self.get_node_index_from_position(position)?].chunk;
+
+        // and return the chunk
+        self.touch(chunk_index);
+        Some(&mut self.chunks[chunk_index].chunk)
     }
 
-    /// get the number of chunks pending deactivation
-    #[inline]
-    pub fn get_num_chunks_to_deactivate(&self) -> usize {
-        self.chunks_to_deactivate.len()
+    /// finds the first chunk in the tree for which pred returns true, and stops looking after that.
+    /// Chunks are checked in the same order as iter_chunks, so if more than one matches, which one is found is unspecified
+    /// # Params
+    /// * `pred` function that gets the position and chunk, and returns wether it's the one to find
+    pub fn find_chunk(&self, mut pred: impl FnMut(L, &C) -> bool) -> Option<(L, &C)> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|container| pred(container.position, &container.chunk))?;
+
+        self.touch(index);
+        Some((self.chunks[index].position, &self.chunks[index].chunk))
     }
 
-    /// get a chunk pending deactivation
-    #[inline]
-    pub fn get_chunk_to_deactivate(&self, index: usize) -> &C {
-        &self.chunks[self.nodes[self.chunks_to_deactivate[index]].chunk].chunk
+    /// finds the first chunk in the tree for which pred returns true, same as find_chunk, but gives a mutable chunk
+    /// # Params
+    /// * `pred` function that gets the position and chunk, and returns wether it's the one to find
+    pub fn find_chunk_mut(&mut self, mut pred: impl FnMut(L, &C) -> bool) -> Option<(L, &mut C)> {
+        let index = self
+            .chunks
+            .iter()
+            .position(|container| pred(container.position, &container.chunk))?;
+
+        self.touch(index);
+        let container = &mut self.chunks[index];
+        Some((container.position, &mut container.chunk))
     }
 
-    /// get a mutable chunk pending deactivation
-    #[inline]
-    pub fn get_chunk_to_deactivate_mut(&mut self, index: usize) -> &mut C {
-        &mut self.chunks[self.nodes[self.chunks_to_deactivate[index]].chunk].chunk
+    /// gets the chunks and positions of the other children of the parent of the node at position.
+    /// Returns None if the position is the root, or not in the tree
+    pub fn sibling_chunks(&self, position: L) -> Option<Vec<(L, &C)>> {
+        // get the parent, the root doesn't have one
+        let path = self.get_node_path_from_position(position)?;
+        let node = *path.last()?;
+        let parent = path.len().checked_sub(2).map(|i| path[i])?;
+        let parent_position = self.chunks[self.nodes[parent].chunk].position;
+        let first_child = self.nodes[parent].children?.get();
+
+        // and get all other children
+        Some(
+            (0..L::num_children())
+                .filter(|&i| first_child + i != node)
+                .map(|i| {
+                    (
+                        parent_position.get_child(i),
+                        &self.chunks[self.nodes[first_child + i].chunk].chunk,
+                    )
+                })
+                .collect(),
+        )
     }
 
-    /// gets a mutable pointer to a chunk that is pending deactivation
-    /// This casts get_chunk_to_deactivate_mut to a pointer underneath the hood
+    /// get the chunk of the parent of the node at a position, or None if the position is the root or not in the tree
     #[inline]
-    pub fn get_chunk_to_deactivate_pointer_mut(&mut self, index: usize) -> *mut C {
-        self.get_chunk_to_deactivate_mut(index)
+    pub fn parent_chunk(&self, position: L) -> Option<&C> {
+        // get the parent, the root doesn't have one
+        let path = self.get_node_path_from_position(position)?;
+        let parent = path[path.len().checked_sub(2)?];
+
+        Some(&self.chunks[self.nodes[parent].chunk].chunk)
     }
 
-    /// get the position of a chunk pending deactivation
+    /// get the mutable chunk of the parent of the node at a position, or None if the position is the root or not in the tree
     #[inline]
-    pub fn get_position_of_chunk_to_deactivate(&self, index: usize) -> L {
-        self.chunks[self.nodes[self.chunks_to_deactivate[index]].chunk].position
-    }
+    pub fn parent_chunk_mut(&mut self, position: L) -> Option<&mut C> {
+        // get the parent, the root doesn't have one
+        let path = self.get_node_path_from_position(position)?;
+        let parent = path[path.len().checked_sub(2)?];
 
-    /// get the number of chunks pending removal
-    #[inline]
-    pub fn get_num_chunks_to_remove(&self) -> usize {
-        self.chunks_to_remove.len()
+        Some(&mut self.chunks[self.nodes[parent].chunk].chunk)
     }
 
-    /// get a chunk pending removal
-    #[inline]
-    pub fn get_chunk_to_remove(&self, index: usize) -> &C {
-        &self.chunks[self.nodes[self.chunks_to_remove[index].chunk].chunk].chunk
+    /// applies a function to the chunk at a position, and then to the chunks of all nodes above it, up to and including the root.
+    /// Nothing happens if the position is not in the tree
+    pub fn propagate_up(&mut self, position: L, mut f: impl FnMut(&mut C)) {
+        if let Some(path) = self.get_node_path_from_position(position) {
+            // go up from the node itself
+            for node in path.into_iter().rev() {
+                f(&mut self.chunks[self.nodes[node].chunk].chunk);
+            }
+        }
     }
 
-    /// get a mutable chunk pending removal
-    #[inline]
-    pub fn get_chunk_to_remove_mut(&mut self, index: usize) -> &mut C {
-        &mut self.chunks[self.nodes[self.chunks_to_remove[index].chunk].chunk].chunk
-    }
+    /// recomputes the chunk of every node with children from the chunks of it's children, going from the leaves up to the root.
+    /// Children are always done before their parent, so this can be used to build a pyramid of lower detail chunks from the leaves
+    /// # Params
+    /// * `combine` function that makes a new chunk from the chunks of all children of a node, in order
+    pub fn aggregate_up(&mut self, combine: impl Fn(&[&C]) -> C) {
+        // nothing to do without a root
+        if self.nodes.is_empty() {
+            return;
+        }
 
-    /// gets a mutable pointer to a chunk that is pending removal
-    /// This casts get_chunk_to_remove_mut to a pointer underneath the hood
-    #[inline]
-    pub fn get_chunk_to_remove_pointer_mut(&mut self, index: usize) -> *mut C {
-        self.get_chunk_to_remove_mut(index)
-    }
+        // all nodes with children, parents before their children
+        let mut parents = Vec::new();
+        let mut stack = vec![0];
 
-    /// get the position of a chunk pending removal
-    #[inline]
-    pub fn get_position_of_chunk_to_remove(&self, index: usize) -> L {
-        self.chunks[self.nodes[self.chunks_to_remove[index].chunk].chunk].position
-    }
+        while let Some(index) = stack.pop() {
+            if let Some(children) = self.nodes[index].children {
+                parents.push(index);
+                stack.extend(children.get()..children.get() + L::num_children());
+            }
+        }
 
-    /// get the number of chunks to be added
-    #[inline]
-    pub fn get_num_chunks_to_add(&self) -> usize {
-        self.chunks_to_add.len()
-    }
+        // so going over them backwards does the children first
+        for &index in parents.iter().rev() {
+            let first_child = match self.nodes[index].children {
+                Some(children) => children.get(),
+                None => continue,
+            };
 
-    /// get a chunk that's going to be added
-    #[inline]
-    pub fn get_chunk_to_add(&self, index: usize) -> &C {
-        &self.chunks_to_add[index].chunk
-    }
+            let chunk = combine(
+                &(first_child..first_child + L::num_children())
+                    .map(|child| &self.chunks[self.nodes[child].chunk].chunk)
+                    .collect::<Vec<_>>(),
+            );
 
-    /// get a mutable chunk that's going to be added
-    #[inline]
-    pub fn get_chunk_to_add_mut(&mut self, index: usize) -> &mut C {
-        &mut self.chunks_to_add[index].chunk
+            self.chunks[self.nodes[index].chunk].chunk = chunk;
+        }
     }
 
-    /// gets a mutable pointer to a chunk that is pending to be added
-    /// This casts get_chunk_to_add_mut to a pointer underneath the hood
-    #[inline]
-    pub fn get_chunk_to_add_pointer_mut(&mut self, index: usize) -> *mut C {
-        self.get_chunk_to_add_mut(index)
-    }
+    /// edits all leaf chunks inside the bounds, and then recomputes the chunks of all nodes above them, like aggregate_up does for the entire tree.
+    /// Only nodes that have an edited chunk below them are recomputed, children before their parent
+    /// # Params
+    /// * `bound_min` lowest corner of the bounds
+    /// * `bound_max` highest corner of the bounds, exclusive, see is_inside_bounds
+    /// * `max_depth` max depth of the leaves to edit, leaves deeper than this aren't edited
+    /// * `edit` function that changes a leaf chunk, given it's position
+    /// * `combine` function that makes a new chunk from the chunks of all children of a node, in order
+    pub fn edit_bounds(
+        &mut self,
+        bound_min: L,
+        bound_max: L,
+        max_depth: u64,
+        mut edit: impl FnMut(L, &mut C),
+        combine: impl Fn(&[&C]) -> C,
+    ) {
+        // nothing to do without a root
+        if self.nodes.is_empty() {
+            return;
+        }
 
-    /// get the position of a chunk that's going to be added
-    #[inline]
-    pub fn get_position_of_chunk_to_add(&self, index: usize) -> L {
-        self.chunks_to_add[index].position
-    }
+        // which nodes were changed, so their parents need to be recomputed
+        let mut changed = vec![false; self.nodes.len()];
 
-    /// gets the positions and chunks to be added as a slice
-    #[inline]
-    pub fn get_chunks_to_add_slice(&self) -> &[ToAddContainer<C, L>] {
-        &self.chunks_to_add[..]
-    }
+        // nodes with children in the bounds, parents before their children
+        // like the bounds iterators, this starts at the root, and only goes to children in the bounds
+        let mut parents = Vec::new();
+        let mut stack = vec![(0, self.root)];
 
-    /// gets the positions and chunks to be added as a mutable slice
-    #[inline]
-    pub fn get_chunks_to_add_slice_mut(&mut self) -> &mut [ToAddContainer<C, L>] {
-        &mut self.chunks_to_add[..]
-    }
+        while let Some((index, position)) = stack.pop() {
+            if let Some(children) = self.nodes[index].children {
+                parents.push(index);
+                stack.extend(
+                    (0..L::num_children())
+                        .map(|i| (children.get() + i, position.get_child(i)))
+                        .filter(|(_, child)| {
+                            child.is_inside_bounds(bound_min, bound_max, max_depth)
+                        }),
+                );
+            } else {
+                // edit the leaf
+                let container = &mut self.chunks[self.nodes[index].chunk];
+                edit(container.position, &mut container.chunk);
+                changed[index] = true;
+            }
+        }
 
-    /// get the number of chunks to be delete
-    #[inline]
-    pub fn get_num_chunks_to_delete(&self) -> usize {
-        self.chunks_to_delete.len()
-    }
+        // going over the parents backwards does the children first
+        for &index in parents.iter().rev() {
+            let first_child = match self.nodes[index].children {
+                Some(children) => children.get(),
+                None => continue,
+            };
 
-    /// get a chunk that's going to be delete
-    #[inline]
-    pub fn get_chunk_to_delete(&self, index: usize) -> &C {
-        &self.chunks_to_delete[index].chunk
-    }
+            let children = first_child..first_child + L::num_children();
 
-    /// get a mutable chunk that's going to be delete
-    #[inline]
-    pub fn get_chunk_to_delete_mut(&mut self, index: usize) -> &mut C {
-        &mut self.chunks_to_delete[index].chunk
-    }
+            if !children.clone().any(|child| changed[child]) {
+                continue;
+            }
 
-    /// gets a mutable pointer to a chunk that is pending deletion
-    /// This casts get_chunk_to_delete_mut to a pointer underneath the hood
-    #[inline]
-    pub fn get_chunk_to_delete_pointer_mut(&mut self, index: usize) -> *mut C {
-        self.get_chunk_to_delete_mut(index)
-    }
+            let chunk = combine(
+                &children
+                    .map(|child| &self.chunks[self.nodes[child].chunk].chunk)
+                    .collect::<Vec<_>>(),
+            );
 
-    /// get the position of a chunk that's going to be delete
-    #[inline]
-    pub fn get_position_of_chunk_to_delete(&self, index: usize) -> L {
-        self.chunks_to_delete[index].position
+            self.chunks[self.nodes[index].chunk].chunk = chunk;
+            changed[index] = true;
+        }
     }
 
-    /// gets the positions and chunks to be delete as a slice
-    #[inline]
-    pub fn get_chunks_to_delete_slice(&self) -> &[ToDeleteContainer<C, L>] {
-        &self.chunks_to_delete[..]
+    /// swaps the chunks at two positions, without changing the positions themselves.
+    /// Returns false if either position is not in the tree, in which case nothing is swapped
+    pub fn swap_chunks(&mut self, a: L, b: L) -> bool {
+        // get the chunks for both positions
+        let (a_index, b_index) = match (
+            self.get_node_index_from_position(a),
+            self.get_node_index_from_position(b),
+        ) {
+            (Some(a_node), Some(b_node)) => (self.nodes[a_node].chunk, self.nodes[b_node].chunk),
+            _ => return false,
+        };
+
+        // swapping a chunk with itself does nothing
+        if a_index != b_index {
+            // split the chunks so we can borrow both
+            let (low, high) = (a_index.min(b_index), a_index.max(b_index));
+            let (left, right) = self.chunks.split_at_mut(high);
+
+            std::mem::swap(&mut left[low].chunk, &mut right[0].chunk);
+        }
+
+        true
     }
 
-    /// gets the positions and chunks to be delete as a mutable slice
+    /// replaces the chunk at a position with a new one made by chunk_creator, without changing the tree itself.
+    /// Returns false if the position is not in the tree, in which case chunk_creator isn't called
     #[inline]
-    pub fn get_chunks_to_delete_slice_mut(&mut self) -> &mut [ToDeleteContainer<C, L>] {
-        &mut self.chunks_to_delete[..]
+    pub fn regenerate_chunk(&mut self, position: L, chunk_creator: impl FnOnce(L) -> C) -> bool {
+        match self.get_chunk_from_position_mut(position) {
+            Some(chunk) => {
+                *chunk = chunk_creator(position);
+                true
+            }
+            None => false,
+        }
     }
 
-    // how it works:
-    // each node contains a pointer to it's chunk data and first child
-    // start from the root node, which is at 0
-    // check if we can't subdivide, and if all children are leafs
-    // if so, queue children for removal, and self for activation (child indices, chunk pointer)
-    // if we can subdivide, and have no children, queue children for addition, and self for removal (child positions, chunk pointer)
-    // if none of the above and have children, queue children for processing
-    // processing queue is only the node positon and node index
+    /// counts the nodes and leaf nodes in the subtree starting at the given position, including the node at that position itself.
+    /// Returns a tuple of (nodes, leaves), or None if the position is not in the tree
+    pub fn count_subtree(&self, position: L) -> Option<(usize, usize)> {
+        // the node to start from
+        let start = self.get_node_index_from_position(position)?;
 
-    // when removing nodes, do so in groups of num children, and use the free list
-    // clear the free list once we only have one chunk (the root) active
-    // swap remove chunks, and update the node that references them (nodes won't move due to free list)
+        let mut nodes = 0;
+        let mut leaves = 0;
 
-    /// prepares the tree for an update.
-    /// this fills the internal lists of what chunks need to be added or removed.
-    /// # Params
-    /// * `targets` The target positions to generate the lod around (QuadVec and OctVec define the center position and max lod in depth for this)
-    /// * `detail` The detail for these targets (QuadVec and OctVec define this as amount of chunks around this point)
-    /// * `chunk_creator` function to create a new chunk from a given position
-    /// returns wether any update is needed.
-    pub fn prepare_update(
-        &mut self,
-        targets: &[L],
-        detail: u64,
-        chunk_creator: fn(L) -> C,
-    ) -> bool {
-        // first, clear the previous arrays
-        self.chunks_to_add.clear();
-        self.chunks_to_remove.clear();
-        self.chunks_to_activate.clear();
-        self.chunks_to_deactivate.clear();
+        // go over all nodes in the subtree
+        let mut stack = vec![start];
 
-        // if we don't have a root, make one pending for creation
+        while let Some(index) = stack.pop() {
+            nodes += 1;
+
+            // add the children, or count it as a leaf if there are none
+            if let Some(children) = self.nodes[index].children {
+                stack.extend(children.get()..children.get() + L::num_children());
+            } else {
+                leaves += 1;
+            }
+        }
+
+        Some((nodes, leaves))
+    }
+
+    /// counts the chunks in the tree that are inside the bounds, up to max_depth.
+    /// This gives the same result as iter_all_chunks_in_bounds_and_tree(...).count(), but skips the iterator
+    /// # Params
+    /// * `bound_min` lowest corner of the bounds
+    /// * `bound_max` highest corner of the bounds, exclusive
+    /// * `max_depth` the maximum depth to count chunks at
+    pub fn count_chunks_in_bounds(&self, bound_min: L, bound_max: L, max_depth: u64) -> usize {
+        // nothing to count if there's no root
         if self.nodes.is_empty() {
-            // chunk to add
-            let chunk_to_add = self.get_chunk_from_cache(L::root(), chunk_creator);
+            return 0;
+        }
 
-            // we need to add the root as pending
-            self.chunks_to_add.push(ToAddContainer {
-                position: L::root(),
-                chunk: chunk_to_add,
-            });
+        let mut count = 0;
 
-            // and the parent
-            self.chunks_to_add_parent.push(0);
+        // go over all nodes in the bounds, starting at the root
+        let mut stack = vec![(0, self.root)];
 
-            // and an update is needed
-            return true;
+        while let Some((index, position)) = stack.pop() {
+            count += 1;
+
+            // and add the children that are in the bounds
+            if let Some(children) = self.nodes[index].children {
+                for i in 0..L::num_children() {
+                    let child_position = position.get_child(i);
+
+                    if child_position.is_inside_bounds(bound_min, bound_max, max_depth) {
+                        stack.push((children.get() + i, child_position));
+                    }
+                }
+            }
         }
 
-        // clear the processing queue from any previous updates
-        self.processing_queue.clear();
+        count
+    }
 
-        // add the root node (always at 0, if there is no root we would have returned earlier) to the processing queue
-        self.processing_queue.push(QueueContainer {
-            position: L::root(),
-            node: 0,
-        });
+    /// gets the number of distinct depths that have at least one chunk in them.
+    /// Every node in the tree has a chunk, so this is the number of levels from the root down to the deepest chunk, or 0 if the tree is empty.
+    /// This is the number of nonzero entries in depth_histogram, which is the same as it's length unless the tree was made with with_root
+    pub fn num_depth_levels(&self) -> usize {
+        // the deepest node gives the number of levels, and an empty tree has none
+        self.chunks
+            .iter()
+            .map(|container| (self.nodes[container.index].depth - self.root_depth) as usize + 1)
+            .max()
+            .unwrap_or(0)
+    }
 
-        // then, traverse the tree, as long as something is inside the queue
-        while let Some(QueueContainer {
-            position: current_position,
-            node: current_node_index,
-        }) = self.processing_queue.pop()
-        {
-            // fetch the current node
-            let current_node = self.nodes[current_node_index];
+    /// gets the number of chunks at each depth, where the index is the depth.
+    /// Like everywhere else, this is the depth of the position of the chunk, so for a tree made with with_root, the entries above the depth of the root are 0.
+    /// The length is one more than the depth of the deepest chunk, or 0 if the tree is empty
+    pub fn depth_histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::new();
 
-            // wether we can subdivide
-            let can_subdivide = targets
-                .iter()
-                .any(|x| x.can_subdivide(current_position, detail));
+        for container in self.chunks.iter() {
+            let depth = self.nodes[container.index].depth as usize;
 
-            // if we can subdivide, and the current node does not have children, subdivide the current node
-            if can_subdivide && current_node.children.is_none() {
-                // add children to be added
-                for i in 0..L::num_children() {
-                    // chunk to add
-                    let chunk_to_add =
-                        self.get_chunk_from_cache(current_position.get_child(i), chunk_creator);
+            if histogram.len() <= depth {
+                histogram.resize(depth + 1, 0);
+            }
 
-                    // add the new chunk to be added
-                    self.chunks_to_add.push(ToAddContainer {
-                        position: current_position.get_child(i),
-                        chunk: chunk_to_add,
-                    });
+            histogram[depth] += 1;
+        }
 
-                    // and add the parent
-                    self.chunks_to_add_parent.push(current_node_index);
-                }
+        histogram
+    }
 
-                // and add ourselves for deactivation
-                self.chunks_to_deactivate.push(current_node_index);
-            } else if let Some(index) = current_node.children {
-                // otherwise, if we cant subdivide and have children, remove our children
-                if !can_subdivide
-                    && !(0..L::num_children())
-                        .into_iter()
-                        .any(|i| self.nodes[i + index.get()].children.is_some())
-                {
-                    // first, queue ourselves for activation
-                    self.chunks_to_activate.push(current_node_index);
-
-                    for i in 0..L::num_children() {
-                        // no need to do this in reverse, that way the last node removed will be added to the free list, which is also the first thing used by the adding logic
-                        self.chunks_to_remove.push(ToRemoveContainer {
-                            chunk: index.get() + i,
-                            parent: current_node_index,
-                        });
-                    }
-                } else {
-                    // queue child nodes for processing if we didn't subdivide or clean up our children
-                    for i in 0..L::num_children() {
-                        self.processing_queue.push(QueueContainer {
-                            position: current_position.get_child(i),
-                            node: index.get() + i,
-                        });
-                    }
+    /// checks if the leaf nodes in the tree cover the entire root, without any gaps or overlaps.
+    /// This is always the case for a tree that's only changed with updates, but can be used to check it after editing the tree.
+    /// An empty tree is not a complete partition
+    pub fn is_complete_partition(&self) -> bool {
+        // positions of all chunks without children
+        let leaves = self
+            .chunks
+            .iter()
+            .filter(|container| self.nodes[container.index].children.is_none())
+            .map(|container| container.position)
+            .collect::<HashSet<_>>();
+
+        // nothing covers nothing
+        if leaves.is_empty() {
+            return false;
+        }
+
+        // all nodes above a leaf, up to the root
+        let mut inner = HashSet::with_capacity(leaves.len());
+
+        for &leaf in leaves.iter() {
+            let mut current = leaf;
+
+            while current != self.root {
+                current = match current.parent() {
+                    Some(parent) => parent,
+                    // the leaf is outside of the root
+                    None => return false,
+                };
+
+                // the rest of the way up was already done for another leaf
+                if !inner.insert(current) {
+                    break;
                 }
             }
         }
 
-        // and return wether an update needs to be done
-        !self.chunks_to_add.is_empty() || !self.chunks_to_remove.is_empty()
+        // no leaf can be inside of another leaf
+        if leaves.iter().any(|leaf| inner.contains(leaf)) {
+            return false;
+        }
+
+        // and all children of the nodes above the leaves are covered, so there are no gaps
+        // this is counted structurally rather than by volume, as the volume of deep leaves gets lost in float rounding
+        inner.iter().all(|&node| {
+            (0..L::num_children())
+                .map(|i| node.get_child(i))
+                .all(|child| leaves.contains(&child) || inner.contains(&child))
+        })
     }
 
-    /// Runs the update that's stored in the internal lists.
-    /// This adds and removes chunks based on that, however this assumes that chunks in the to_activate and to_deactivate list were manually activated or deactivated.
-    /// This also assumes that the chunks in to_add had proper initialization, as they are added to the tree.
-    /// After this, it's needed to clean un nodes in the chunk_to_delete list and call the function complete_update(), in order to properly clear the cache
-    pub fn do_update(&mut self) {
-        // no need to do anything with chunks that needed to be (de)activated, as we assume that has been handled beforehand
+    /// makes a grid with 2^resolution cells on each axis, where each cell has the depth of the deepest leaf node in it.
+    /// Cells are ordered with x first, then y, then z. The grid is all 0 if the tree is empty.
+    /// The grid covers the root of the tree, so for a tree made with with_root it only covers that part of the space,
+    /// and depths are counted from the top of the whole space, same as for depth_histogram
+    pub fn summary_grid(&self, resolution: u8) -> Vec<u8> {
+        let axes = L::root().get_float_coords().as_ref().len();
+        let cells = 1usize << resolution;
+        let mut grid = vec![0; cells.pow(axes as u32)];
 
-        // first, get the iterator for chunks that will be added
-        // this becomes useful later
-        let mut chunks_to_add_iter = self
-            .chunks_to_add_parent
-            .drain(..)
-            .zip(self.chunks_to_add.drain(..));
+        // nothing in the tree
+        if self.nodes.is_empty() {
+            return grid;
+        }
 
-        // then, remove old chunks, or cache them
-        // we'll drain the vector, as we don't need it anymore afterward
-        for ToRemoveContainer {
-            chunk: index,
-            parent: parent_index,
-        } in self.chunks_to_remove.drain(..)
-        // but we do need to cache these
-        {
-            // remove the node from the tree
-            self.nodes[parent_index].children = None;
-            self.free_list.push_back(index);
+        // go over all nodes, and keep track of their depth
+        let mut stack = vec![(0, self.root, self.root_depth)];
 
-            // and remove the chunk
-            let chunk_index = self.nodes[index].chunk;
+        while let Some((index, position, depth)) = stack.pop() {
+            if let Some(children) = self.nodes[index].children {
+                for i in 0..L::num_children() {
+                    stack.push((children.get() + i, position.get_child(i), depth + 1));
+                }
 
-            // but not so fast, because if we can overwrite it with a new chunk, do so
-            // that way we can avoid a copy later on, which might be expensive
-            if let Some((parent_index, ToAddContainer { position, chunk })) =
-                chunks_to_add_iter.next()
-            {
-                // add the node
-                let new_node_index = match self.free_list.pop_front() {
-                    Some(x) => {
-                        // reuse a free node
-                        self.nodes[x] = TreeNode {
-                            children: None,
-                            chunk: chunk_index,
-                        };
+                continue;
+            }
 
-                        // old chunk that was previously in the array
-                        // we initialize it to the new chunk, then swap them
-                        let mut old_chunk = ChunkContainer {
-                            index: x,
-                            chunk,
-                            position,
-                        };
+            // this is a leaf
+            add_leaf_to_grid(&mut grid, cells, self.root, position, depth);
+        }
 
-                        std::mem::swap(&mut old_chunk, &mut self.chunks[chunk_index]);
+        grid
+    }
 
-                        // old chunk shouldn't be mutable anymore
-                        let old_chunk = old_chunk;
+    /// makes a grid like summary_grid, where each cell has how much deeper the deepest leaf node in it is than it would be in a tree that's fully updated for the targets.
+    /// Like summary_grid, the grid covers the root of the tree.
+    /// Negative cells are under refined, so the tree still needs to subdivide there, and positive cells are over refined and still need to be merged.
+    /// This is useful to see how far the tree lags behind the targets when they move. The differences are clamped to fit
+    /// # Params
+    /// * `targets` the targets the tree is updated for, same as for prepare_update
+    /// * `detail` the detail for the targets
+    /// * `resolution` the grid has 2^resolution cells on each axis
+    pub fn lod_error_grid(&self, targets: &[L], detail: u64, resolution: u8) -> Vec<i8> {
+        let current = self.summary_grid(resolution);
+        let cells = 1usize << resolution;
 
-                        // now, we can try to add this chunk into the cache
-                        // first, remove any extra nodes if they are in the cache
-                        while self.chunk_cache.len() > self.cache_size {
-                            if let Some(chunk_position) = self.cache_queue.pop_front() {
-                                // check if the chunk is inside the map
-                                if let Some(cached_chunk) = self.chunk_cache.remove(&chunk_position)
-                                {
-                                    // if it is, it's removed, so we need to push it to the chunks that are going to be deleted
-                                    self.chunks_to_delete.push(ToDeleteContainer {
-                                        position: chunk_position,
-                                        chunk: cached_chunk,
-                                    });
-                                }
-                            } else {
-                                // just break, otherwise we'll be stuck in an infinite loop
-                                break;
-                            }
-                        }
+        // find the leaves of the ideal tree, without building it
+        let mut ideal = vec![0; current.len()];
+        let mut stack = vec![(self.root, self.root_depth)];
 
-                        // then assign this chunk into the cache
-                        if let Some(cached_chunk) =
-                            self.chunk_cache.insert(old_chunk.position, old_chunk.chunk)
-                        {
-                            // there might have been another cached chunk
-                            self.chunks_to_delete.push(ToDeleteContainer {
-                                position: old_chunk.position,
-                                chunk: cached_chunk,
-                            });
-                        }
+        while let Some((position, depth)) = stack.pop() {
+            if targets.iter().any(|x| x.can_subdivide(position, detail)) {
+                for i in 0..L::num_children() {
+                    stack.push((position.get_child(i), depth + 1));
+                }
+            } else {
+                add_leaf_to_grid(&mut ideal, cells, self.root, position, depth);
+            }
+        }
 
-                        // and make sure it's tracked
-                        self.cache_queue.push_back(old_chunk.position);
+        // and compare
+        current
+            .into_iter()
+            .zip(ideal)
+            .map(|(current, ideal)| (current as i16 - ideal as i16).clamp(-128, 127) as i8)
+            .collect()
+    }
 
-                        x
-                    }
-                    // This can't be reached due to us *always* adding a chunk to the free list before popping it
-                    None => unsafe { std::hint::unreachable_unchecked() },
-                };
+    /// checks if all leaf nodes inside the bounds are at least at the given depth.
+    /// Returns false as soon as a shallower leaf is found, or if the tree is empty.
+    /// # Params
+    /// * `min` lowest corner of the bounds
+    /// * `max` highest corner of the bounds, exclusive
+    /// * `depth` the depth all leaf nodes in the bounds need to be at
+    pub fn is_fully_refined(&self, min: L, max: L, depth: u8) -> bool {
+        // an empty tree has nothing refined
+        if self.nodes.is_empty() {
+            return false;
+        }
 
-                // correctly set the children of the parent node.
-                // because the last node we come by in with ordered iteration is on num_children - 1, we need to set it as such].
-                // node 0 is the root, so the last child it has will be on num_children.
-                // then subtracting num_children - 1 from that gives us node 1, which is the first child of the root.
-                if new_node_index >= L::num_children() {
-                    // because we loop in order, and our nodes are contiguous, the first node of the children got added on index i - (num children - 1)
-                    // so we need to adjust for that
-                    self.nodes[parent_index].children =
-                        NonZeroUsize::new(new_node_index - (L::num_children() - 1));
+        // stack of nodes to check, with their position and depth
+        let mut stack = vec![(0, self.root, self.root_depth)];
+
+        while let Some((index, position, current_depth)) = stack.pop() {
+            // deep enough, so no need to look at the children
+            if current_depth >= depth {
+                continue;
+            }
+
+            // if this is a leaf, it's too shallow
+            let children = match self.nodes[index].children {
+                Some(children) => children,
+                None => return false,
+            };
+
+            // otherwise check all children in the bounds
+            for i in 0..L::num_children() {
+                let child_position = position.get_child(i);
+
+                if child_position.is_inside_bounds(min, max, depth as u64) {
+                    stack.push((children.get() + i, child_position, current_depth + 1));
+                }
+            }
+        }
+
+        true
+    }
+
+    /// gets the positions of all chunks in the tree, sorted in depth first order, as given by containment_cmp.
+    /// This only depends on which chunks are in the tree, and not on where they are stored internally, so it can be used to compare trees
+    pub fn sorted_positions(&self) -> Vec<L> {
+        let mut positions = self
+            .chunks
+            .iter()
+            .map(|container| container.position)
+            .collect::<Vec<_>>();
+
+        positions.sort_by(|a, b| a.containment_cmp(*b));
+
+        positions
+    }
+
+    /// finds the differences between this tree and another tree.
+    /// This tree is seen as the old tree, and other as the new tree, so added chunks are the ones that are only in other.
+    pub fn diff(&self, other: &Self) -> TreeDiff<L>
+    where
+        C: PartialEq,
+    {
+        // all chunks in the other tree, by position
+        let mut other_chunks = other
+            .chunks
+            .iter()
+            .map(|container| (container.position, &container.chunk))
+            .collect::<HashMap<_, _>>();
+
+        let mut diff = TreeDiff::default();
+
+        // go over our own chunks, and see if they are in the other tree
+        for container in self.chunks.iter() {
+            match other_chunks.remove(&container.position) {
+                Some(chunk) if *chunk != container.chunk => diff.changed.push(container.position),
+                Some(_) => (),
+                None => diff.removed.push(container.position),
+            }
+        }
+
+        // anything left is only in the other tree
+        diff.added.extend(other_chunks.into_keys());
+
+        diff
+    }
+
+    /// splits the tree at a depth into a coarse tree with all nodes up to that depth, and fine trees with the detail below it.
+    /// Nodes at the depth become leaves in the coarse tree, and each of them that has children becomes the root of a fine tree, made with with_root.
+    /// The nodes at the depth are in both the coarse and the fine tree, so together they have all chunks of this tree.
+    /// The new trees have copies of the chunks, but no cache or pending update
+    /// # Params
+    /// * `depth` the depth to split at, where the root of the entire space is at depth 0. If the root of this tree is deeper, it's split at the root
+    ///
+    /// returns the coarse tree, and the fine trees in Morton order
+    pub fn split_at_depth(&self, depth: u8) -> (Self, Vec<Self>)
+    where
+        C: Clone,
+    {
+        // copies the chunk at a position
+        let chunk_at = |position: L| match self.get_node_index_from_position(position) {
+            Some(index) => self.chunks[self.nodes[index].chunk].chunk.clone(),
+            None => unreachable!("position is not in the tree"),
+        };
+
+        // and if it has children, and at which depth
+        let children_at = |position: L| {
+            self.get_node_index_from_position(position)
+                .filter(|&index| self.nodes[index].children.is_some())
+                .map(|index| self.nodes[index].depth)
+        };
+
+        let mut coarse = Self::with_root(self.root);
+        let mut fine = Vec::new();
+
+        // nothing to split
+        if self.nodes.is_empty() {
+            return (coarse, fine);
+        }
+
+        // the coarse tree stops at the depth
+        coarse.rebuild(
+            |position| children_at(position).map_or(false, |node_depth| node_depth < depth),
+            chunk_at,
+        );
+
+        // and the fine trees start at it
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            let node = self.nodes[index];
+
+            if let Some(children) = node.children {
+                if node.depth < depth {
+                    // in reverse, so the first child is processed first
+                    stack.extend((children.get()..children.get() + L::num_children()).rev());
+                } else {
+                    let mut tree = Self::with_root(self.chunks[node.chunk].position);
+                    tree.rebuild(|position| children_at(position).is_some(), chunk_at);
+                    fine.push(tree);
+                }
+            }
+        }
+
+        (coarse, fine)
+    }
+
+    /// collects all leaf chunks and their positions in the subtree starting at the given position, including the node at that position itself.
+    /// The leaves are in Morton order, and the result is empty if the position is not in the tree
+    pub fn collect_subtree_leaves(&self, position: L) -> Vec<(L, &C)> {
+        let mut leaves = Vec::new();
+
+        // the node to start from
+        let start = match self.get_node_index_from_position(position) {
+            Some(start) => start,
+            None => return leaves,
+        };
+
+        // go over all nodes in the subtree, children in order
+        let mut stack = vec![(start, position)];
+
+        while let Some((index, current_position)) = stack.pop() {
+            let node = self.nodes[index];
+
+            if let Some(children) = node.children {
+                // in reverse, so the first child is processed first
+                for i in (0..L::num_children()).rev() {
+                    stack.push((children.get() + i, current_position.get_child(i)));
                 }
             } else {
-                // otherwise we do need to do a regular swap remove
-                let old_chunk = self.chunks.swap_remove(chunk_index);
+                leaves.push((current_position, &self.chunks[node.chunk].chunk));
+            }
+        }
+
+        leaves
+    }
+
+    /// collects all leaf chunks and their positions that touch a face of the node at the given position, across that face.
+    /// The leaves can be at any depth, so there is either one bigger or same size neighbor, or multiple smaller ones.
+    /// The result is empty if the position is not in the tree, or the face is on the edge of the tree
+    /// # Params
+    /// * `position` the position of the node
+    /// * `axis` the axis of the face, 0 for x, 1 for y, and so on
+    /// * `positive` wether to use the face at the highest coordinate on the axis, instead of the lowest
+    pub fn face_neighbors(&self, position: L, axis: usize, positive: bool) -> Vec<(L, &C)> {
+        let mut neighbors = Vec::new();
+
+        // the node has to exist
+        if self.get_node_index_from_position(position).is_none() {
+            return neighbors;
+        }
+
+        // bounds of the node, and where the face is
+        let min = position.get_float_coords();
+        let size = position.get_size();
+        let face = if positive {
+            min.as_ref()[axis] + size
+        } else {
+            min.as_ref()[axis]
+        };
+
+        // go over all nodes that can contain a neighbor
+        let mut stack = vec![(0, self.root)];
+
+        while let Some((index, current_position)) = stack.pop() {
+            let current_min = current_position.get_float_coords();
+            let current_size = current_position.get_size();
+
+            // the node has to overlap with the face on all other axes
+            let overlaps = (0..min.as_ref().len()).filter(|&i| i != axis).all(|i| {
+                current_min.as_ref()[i] < min.as_ref()[i] + size
+                    && min.as_ref()[i] < current_min.as_ref()[i] + current_size
+            });
+
+            // and be on the other side of the face, or span over it
+            let low = current_min.as_ref()[axis];
+            let high = low + current_size;
+            let across = if positive {
+                low <= face && face < high
+            } else {
+                low < face && face <= high
+            };
+
+            if !overlaps || !across {
+                continue;
+            }
+
+            let node = self.nodes[index];
+
+            if let Some(children) = node.children {
+                for i in (0..L::num_children()).rev() {
+                    stack.push((children.get() + i, current_position.get_child(i)));
+                }
+            } else if (positive && low == face) || (!positive && high == face) {
+                // leaves that touch the face from the other side are neighbors
+                neighbors.push((current_position, &self.chunks[node.chunk].chunk));
+            }
+        }
+
+        neighbors
+    }
+
+    /// iterate over the leaves on the coarse side of a step in lod caused by the target, and their chunks.
+    /// These are the leaves the target doesn't subdivide, next to a node of the same size that the target does subdivide,
+    /// so the neighbor toward the target is split into finer chunks.
+    /// # Params
+    /// * `target` the target that caused the lod, same as for prepare_update
+    /// * `detail` the detail for the target
+    pub fn iter_transition_leaves(&self, target: L, detail: u64) -> impl Iterator<Item = (L, &C)> {
+        let axes = L::root().get_float_coords().as_ref().len();
+
+        self.chunks
+            .iter()
+            // only leaves that the target doesn't subdivide
+            .filter(move |container| {
+                self.nodes[container.index].children.is_none()
+                    && !target.can_subdivide(container.position, detail)
+            })
+            // that are next to a node that the target did subdivide
+            .filter(move |container| {
+                (0..axes * 2)
+                    .filter_map(|direction| container.position.get_neighbor(direction))
+                    .any(|neighbor| {
+                        target.can_subdivide(neighbor, detail)
+                            && self
+                                .get_node_index_from_position(neighbor)
+                                .map_or(false, |index| self.nodes[index].children.is_some())
+                    })
+            })
+            .map(move |container| {
+                container.last_access.touch(self.access_clock());
+                (container.position, &container.chunk)
+            })
+    }
+
+    /// get a chunk as mutable
+    #[inline]
+    pub fn get_chunk_mut(&mut self, index: usize) -> &mut C {
+        self.touch(index);
+        &mut self.chunks[index].chunk
+    }
+
+    /// gets a mutable pointer to a chunk
+    /// This casts get_chunk_mut to a pointer underneath the hood
+    #[inline]
+    pub fn get_chunk_pointer_mut(&mut self, index: usize) -> *mut C {
+        self.get_chunk_mut(index)
+    }
+
+    /// get the position of a chunk
+    #[inline]
+    pub fn get_chunk_position(&self, index: usize) -> L {
+        self.chunks[index].position
+    }
+
+    /// get the number of chunks pending activation
+    #[inline]
+    pub fn get_num_chunks_to_activate(&self) -> usize {
+        self.chunks_to_activate.len()
+    }
+
+    /// get a chunk pending activation
+    #[inline]
+    pub fn get_chunk_to_activate(&self, index: usize) -> &C {
+        &self.chunks[self.nodes[self.chunks_to_activate[index]].chunk].chunk
+    }
+
+    /// get a mutable chunk pending activation
+    #[inline]
+    pub fn get_chunk_to_activate_mut(&mut self, index: usize) -> &mut C {
+        &mut self.chunks[self.nodes[self.chunks_to_activate[index]].chunk].chunk
+    }
+
+    /// gets a mutable pointer to a chunk that is pending activation
+    /// This casts get_chunk_to_activate_mut to a pointer underneath the hood
+    #[inline]
+    pub fn get_chunk_to_activate_pointer_mut(&mut self, index: usize) -> *mut C {
+        self.get_chunk_to_activate_mut(index)
+    }
+
+    /// get the position of a chunk pending activation
+    #[inline]
+    pub fn get_position_of_chunk_to_activate(&self, index: usize) -> L {
+        self.chunks[self.nodes[self.chunks_to_activate[index]].chunk].position
+    }
+
+    /// get the number of chunks pending deactivation
+    #[inline]
+    pub fn get_num_chunks_to_deactivate(&self) -> usize {
+        self.chunks_to_deactivate.len()
+    }
+
+    /// get a chunk pending deactivation
+    #[inline]
+    pub fn get_chunk_to_deactivate(&self, index: usize) -> &C {
+        &self.chunks[self.nodes[self.chunks_to_deactivate[index]].chunk].chunk
+    }
+
+    /// get a mutable chunk pending deactivation
+    #[inline]
+    pub fn get_chunk_to_deactivate_mut(&mut self, index: usize) -> &mut C {
+        &mut self.chunks[self.nodes[self.chunks_to_deactivate[index]].chunk].chunk
+    }
+
+    /// gets a mutable pointer to a chunk that is pending deactivation
+    /// This casts get_chunk_to_deactivate_mut to a pointer underneath the hood
+    #[inline]
+    pub fn get_chunk_to_deactivate_pointer_mut(&mut self, index: usize) -> *mut C {
+        self.get_chunk_to_deactivate_mut(index)
+    }
+
+    /// get the position of a chunk pending deactivation
+    #[inline]
+    pub fn get_position_of_chunk_to_deactivate(&self, index: usize) -> L {
+        self.chunks[self.nodes[self.chunks_to_deactivate[index]].chunk].position
+    }
+
+    /// get the number of chunks pending removal
+    #[inline]
+    pub fn get_num_chunks_to_remove(&self) -> usize {
+        self.chunks_to_remove.len()
+    }
+
+    /// get a chunk pending removal
+    #[inline]
+    pub fn get_chunk_to_remove(&self, index: usize) -> &C {
+        &self.chunks[self.nodes[self.chunks_to_remove[index].chunk].chunk].chunk
+    }
+
+    /// get a mutable chunk pending removal
+    #[inline]
+    pub fn get_chunk_to_remove_mut(&mut self, index: usize) -> &mut C {
+        &mut self.chunks[self.nodes[self.chunks_to_remove[index].chunk].chunk].chunk
+    }
+
+    /// gets a mutable pointer to a chunk that is pending removal
+    /// This casts get_chunk_to_remove_mut to a pointer underneath the hood
+    #[inline]
+    pub fn get_chunk_to_remove_pointer_mut(&mut self, index: usize) -> *mut C {
+        self.get_chunk_to_remove_mut(index)
+    }
+
+    /// get the position of a chunk pending removal
+    #[inline]
+    pub fn get_position_of_chunk_to_remove(&self, index: usize) -> L {
+        self.chunks[self.nodes[self.chunks_to_remove[index].chunk].chunk].position
+    }
+
+    /// iterate over the chunks to remove, together with the chunk of the parent they are merged into.
+    /// The parent is activated in the same update, so this can be used to hand data from the children to it
+    #[inline]
+    pub fn iter_remove_with_target(&self) -> impl Iterator<Item = (&C, &C)> {
+        self.chunks_to_remove.iter().map(move |container| {
+            (
+                &self.chunks[self.nodes[container.chunk].chunk].chunk,
+                &self.chunks[self.nodes[container.parent].chunk].chunk,
+            )
+        })
+    }
+
+    /// get the number of chunks to be added
+    #[inline]
+    pub fn get_num_chunks_to_add(&self) -> usize {
+        self.chunks_to_add.len()
+    }
+
+    /// get a chunk that's going to be added
+    #[inline]
+    pub fn get_chunk_to_add(&self, index: usize) -> &C {
+        &self.chunks_to_add[index].chunk
+    }
+
+    /// get a mutable chunk that's going to be added
+    #[inline]
+    pub fn get_chunk_to_add_mut(&mut self, index: usize) -> &mut C {
+        &mut self.chunks_to_add[index].chunk
+    }
+
+    /// gets a mutable pointer to a chunk that is pending to be added
+    /// This casts get_chunk_to_add_mut to a pointer underneath the hood
+    #[inline]
+    pub fn get_chunk_to_add_pointer_mut(&mut self, index: usize) -> *mut C {
+        self.get_chunk_to_add_mut(index)
+    }
+
+    /// get the position of a chunk that's going to be added
+    #[inline]
+    pub fn get_position_of_chunk_to_add(&self, index: usize) -> L {
+        self.chunks_to_add[index].position
+    }
+
+    /// get the chunk of the parent node of a chunk that's going to be added, so it can be used when initializing the new chunk.
+    /// An update only subdivides nodes that are already in the tree, so the parent is never a chunk to add itself.
+    /// Returns None if the chunk to add is the root, as it has no parent
+    #[inline]
+    pub fn get_parent_of_chunk_to_add(&self, index: usize) -> Option<&C> {
+        // if there are no nodes yet, we're adding the root
+        let node = self.nodes.get(self.chunks_to_add_parent[index])?;
+
+        Some(&self.chunks[node.chunk].chunk)
+    }
+
+    /// iterate over the positions of the chunks to add, with the position of their parent, and the chunk.
+    /// When the root of the tree is going to be added it's skipped, as it has no parent in the tree
+    #[inline]
+    pub fn iter_chunks_to_add_with_parent_pos(&self) -> impl Iterator<Item = (L, L, &C)> {
+        let root = self.root;
+
+        // the root of the tree can still have a parent position, if the tree only covers part of the space
+        self.chunks_to_add
+            .iter()
+            .filter(move |container| container.position != root)
+            .filter_map(|container| {
+                Some((
+                    container.position,
+                    container.position.parent()?,
+                    &container.chunk,
+                ))
+            })
+    }
+
+    /// gets the positions and chunks to be added as a slice
+    #[inline]
+    pub fn get_chunks_to_add_slice(&self) -> &[ToAddContainer<C, L>] {
+        &self.chunks_to_add[..]
+    }
+
+    /// gets the positions and chunks to be added as a mutable slice
+    #[inline]
+    pub fn get_chunks_to_add_slice_mut(&mut self) -> &mut [ToAddContainer<C, L>] {
+        &mut self.chunks_to_add[..]
+    }
+
+    /// takes all chunks that are going to be added out of the tree, together with their positions.
+    /// This allows processing them elsewhere, such as on a thread pool, without keeping the tree borrowed.
+    /// They need to be given back with return_chunks_to_add before calling do_update.
+    /// # Panics
+    /// If the chunks were already taken, and not given back yet
+    pub fn take_chunks_to_add(&mut self) -> Vec<(L, C)> {
+        assert!(
+            self.taken_chunks_to_add.is_empty(),
+            "chunks to add were already taken"
+        );
+
+        self.mark_modified();
+
+        // remember where each chunk goes
+        self.taken_chunks_to_add = self
+            .chunks_to_add
+            .iter()
+            .map(|container| container.position)
+            .collect();
+
+        self.chunks_to_add
+            .drain(..)
+            .map(|ToAddContainer { position, chunk }| (position, chunk))
+            .collect()
+    }
+
+    /// gives back the chunks that were taken with take_chunks_to_add.
+    /// They can be in any order, as each chunk is put back in the place of the taken chunk with the same position.
+    /// # Panics
+    /// If the chunks were not taken out, or the positions of the chunks differ from the positions of the taken chunks.
+    pub fn return_chunks_to_add(&mut self, chunks: Vec<(L, C)>) {
+        assert!(
+            self.chunks_to_add.is_empty() && chunks.len() == self.taken_chunks_to_add.len(),
+            "returned chunks don't match the taken chunks"
+        );
+
+        self.mark_modified();
+
+        // where each position goes
+        let slots = self
+            .taken_chunks_to_add
+            .iter()
+            .enumerate()
+            .map(|(index, &position)| (position, index))
+            .collect::<HashMap<_, _>>();
+
+        let mut returned = Vec::with_capacity(chunks.len());
+        returned.resize_with(chunks.len(), || None);
+
+        for (position, chunk) in chunks {
+            let slot = slots
+                .get(&position)
+                .and_then(|&index| returned.get_mut(index))
+                .filter(|slot| slot.is_none())
+                .expect("returned chunks don't match the taken chunks");
+
+            *slot = Some(ToAddContainer { position, chunk });
+        }
+
+        // as there are as many chunks as slots, and no slot is used twice, all are filled
+        self.chunks_to_add.extend(returned.into_iter().flatten());
+        self.taken_chunks_to_add.clear();
+    }
+
+    /// get the number of chunks to add that need to be filled in with fill_chunk_to_add, after prepare_update_structural.
+    /// Once all are filled in, they become the chunks to add during do_update
+    #[inline]
+    pub fn get_num_chunks_to_fill(&self) -> usize {
+        self.chunks_to_fill.len()
+    }
+
+    /// get the position of a chunk to add that needs to be filled in
+    #[inline]
+    pub fn get_position_of_chunk_to_fill(&self, index: usize) -> L {
+        self.chunks_to_fill[index].position
+    }
+
+    /// get the chunk of a chunk to add that needs to be filled in, or None if it isn't filled in yet.
+    /// Chunks that were still in the cache are already filled in
+    #[inline]
+    pub fn get_chunk_to_fill(&self, index: usize) -> Option<&C> {
+        self.chunks_to_fill[index].chunk.as_ref()
+    }
+
+    /// fills in a chunk to add after prepare_update_structural, where index is the same as for get_position_of_chunk_to_fill.
+    /// Chunks can be filled in any order, and filling the same index twice replaces the previous chunk
+    #[inline]
+    pub fn fill_chunk_to_add(&mut self, index: usize, chunk: C) {
+        self.chunks_to_fill[index].chunk = Some(chunk);
+    }
+
+    /// gets the next chunk to fill in after prepare_update_structural, closest to the targets first, so chunks can be made one at a time, for example on a background thread.
+    /// Every call gives a different chunk that isn't filled in yet, and the distance to the closest target never goes down between calls.
+    /// The order is decided on the first call after the update is prepared, so the targets are only used then.
+    /// If the tree changes after that, the order is decided again for all chunks that aren't filled in yet
+    /// # Params
+    /// * `targets` the targets to measure the distance to, usually the same as the ones given to prepare_update_structural
+    ///
+    /// returns the index to give to fill_chunk_to_add and the position of the chunk, or None if all chunks were given out or filled in
+    pub fn next_chunk_to_generate(&mut self, targets: &[L]) -> Option<(usize, L)> {
+        // sort the chunks to fill again if the tree changed
+        if self.fill_queue_modifications != Some(self.modifications) {
+            self.fill_queue_modifications = Some(self.modifications);
+
+            let mut queue = self
+                .chunks_to_fill
+                .iter()
+                .enumerate()
+                .filter(|(_, container)| container.chunk.is_none())
+                .map(|(index, container)| {
+                    let distance = targets
+                        .iter()
+                        .map(|target| center_distance_squared(container.position, *target))
+                        .fold(f64::INFINITY, f64::min);
+
+                    (distance, index)
+                })
+                .collect::<Vec<_>>();
+
+            // furthest first, so the closest can be taken from the end
+            queue.sort_by(|a, b| {
+                b.0.partial_cmp(&a.0)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+                    .then(b.1.cmp(&a.1))
+            });
+
+            self.fill_queue.clear();
+            self.fill_queue
+                .extend(queue.into_iter().map(|(_, index)| index));
+        }
+
+        // skip chunks that were filled in by now
+        while let Some(index) = self.fill_queue.pop() {
+            if self.chunks_to_fill[index].chunk.is_none() {
+                return Some((index, self.chunks_to_fill[index].position));
+            }
+        }
+
+        None
+    }
+
+    /// get the number of chunks to be delete
+    #[inline]
+    pub fn get_num_chunks_to_delete(&self) -> usize {
+        self.chunks_to_delete.len()
+    }
+
+    /// get a chunk that's going to be delete
+    #[inline]
+    pub fn get_chunk_to_delete(&self, index: usize) -> &C {
+        &self.chunks_to_delete[index].chunk
+    }
+
+    /// get a mutable chunk that's going to be delete
+    #[inline]
+    pub fn get_chunk_to_delete_mut(&mut self, index: usize) -> &mut C {
+        &mut self.chunks_to_delete[index].chunk
+    }
+
+    /// gets a mutable pointer to a chunk that is pending deletion
+    /// This casts get_chunk_to_delete_mut to a pointer underneath the hood
+    #[inline]
+    pub fn get_chunk_to_delete_pointer_mut(&mut self, index: usize) -> *mut C {
+        self.get_chunk_to_delete_mut(index)
+    }
+
+    /// get the position of a chunk that's going to be delete
+    #[inline]
+    pub fn get_position_of_chunk_to_delete(&self, index: usize) -> L {
+        self.chunks_to_delete[index].position
+    }
+
+    /// gets the positions and chunks to be delete as a slice
+    #[inline]
+    pub fn get_chunks_to_delete_slice(&self) -> &[ToDeleteContainer<C, L>] {
+        &self.chunks_to_delete[..]
+    }
+
+    /// gets the positions and chunks to be delete as a mutable slice
+    #[inline]
+    pub fn get_chunks_to_delete_slice_mut(&mut self) -> &mut [ToDeleteContainer<C, L>] {
+        &mut self.chunks_to_delete[..]
+    }
+
+    // how it works:
+    // each node contains a pointer to it's chunk data and first child
+    // start from the root node, which is at 0
+    // check if we can't subdivide, and if all children are leafs
+    // if so, queue children for removal, and self for activation (child indices, chunk pointer)
+    // if we can subdivide, and have no children, queue children for addition, and self for removal (child positions, chunk pointer)
+    // if none of the above and have children, queue children for processing
+    // processing queue is only the node positon and node index
+
+    // when removing nodes, do so in groups of num children, and use the free list
+    // clear the free list once we only have one chunk (the root) active
+    // swap remove chunks, and update the node that references them (nodes won't move due to free list)
+
+    /// prepares the tree for an update.
+    /// this fills the internal lists of what chunks need to be added or removed.
+    /// # Params
+    /// * `targets` The target positions to generate the lod around (QuadVec and OctVec define the center position and max lod in depth for this)
+    /// * `detail` The detail for these targets (QuadVec and OctVec define this as amount of chunks around this point)
+    /// * `chunk_creator` function to create a new chunk from a given position.
+    ///   Nodes keep their chunk when subdivided, so this is only called once for every new node, and not again for nodes that get subdivided further
+    ///
+    /// returns wether any update is needed.
+    pub fn prepare_update(
+        &mut self,
+        targets: &[L],
+        detail: u64,
+        chunk_creator: impl FnMut(L) -> C,
+    ) -> bool {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("prepare_update").entered();
+
+        // remember the targets for set_detail
+        self.last_targets.clear();
+        self.last_targets
+            .extend(targets.iter().map(|&target| (target, 1.0)));
+
+        let needs_update = self.prepare_update_with(
+            |node| targets.iter().any(|x| x.can_subdivide(node, detail)),
+            Some(chunk_creator),
+        );
+
+        #[cfg(feature = "tracing")]
+        self.trace_prepared_update(targets.len(), detail);
+
+        needs_update
+    }
+
+    /// prepares the tree for an update, same as prepare_update, but makes the new chunks in parallel with rayon.
+    /// All positions to add are found first, and chunk_creator is then called for each of them on the rayon thread pool.
+    /// Chunks that can be taken from the cache are still taken from it instead
+    /// # Params
+    /// * `targets` The target positions to generate the lod around
+    /// * `detail` The detail for these targets
+    /// * `chunk_creator` function to create a new chunk from a given position, which can be called from multiple threads at once
+    ///
+    /// returns wether any update is needed.
+    #[cfg(feature = "rayon")]
+    pub fn prepare_update_par(
+        &mut self,
+        targets: &[L],
+        detail: u64,
+        chunk_creator: impl Fn(L) -> C + Sync,
+    ) -> bool
+    where
+        C: Send,
+    {
+        use rayon::prelude::*;
+
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("prepare_update_par").entered();
+
+        // remember the targets for set_detail
+        self.last_targets.clear();
+        self.last_targets
+            .extend(targets.iter().map(|&target| (target, 1.0)));
+
+        // find what to add, without making the chunks
+        let needs_update = self.prepare_update_with(
+            |node| targets.iter().any(|x| x.can_subdivide(node, detail)),
+            None::<fn(L) -> C>,
+        );
+
+        // then make them all at once
+        self.chunks_to_fill.par_iter_mut().for_each(|container| {
+            if container.chunk.is_none() {
+                container.chunk = Some(chunk_creator(container.position));
+            }
+        });
+
+        self.add_filled_chunks();
+
+        #[cfg(feature = "tracing")]
+        self.trace_prepared_update(targets.len(), detail);
+
+        needs_update
+    }
+
+    /// prepares the tree for an update, where each target has a weight for how much detail it needs.
+    /// this fills the internal lists of what chunks need to be added or removed, same as prepare_update.
+    /// # Params
+    /// * `targets` The target positions to generate the lod around, together with their weight
+    /// * `base_detail` The detail for a target with a weight of 1. Each target uses this detail multiplied by it's weight, rounded to the nearest integer, so targets with a higher weight subdivide further away from them
+    /// * `chunk_creator` function to create a new chunk from a given position
+    ///
+    /// returns wether any update is needed.
+    pub fn prepare_update_weighted(
+        &mut self,
+        targets: &[(L, f64)],
+        base_detail: u64,
+        chunk_creator: impl FnMut(L) -> C,
+    ) -> bool {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("prepare_update_weighted").entered();
+
+        // remember the targets for set_detail
+        self.last_targets.clear();
+        self.last_targets.extend_from_slice(targets);
+
+        let needs_update = self.prepare_update_with(
+            |node| {
+                targets.iter().any(|(x, weight)| {
+                    x.can_subdivide(node, (base_detail as f64 * weight).round() as u64)
+                })
+            },
+            Some(chunk_creator),
+        );
+
+        #[cfg(feature = "tracing")]
+        self.trace_prepared_update(targets.len(), base_detail);
+
+        needs_update
+    }
+
+    /// changes the detail of the whole tree, using the targets from the last call to any of the prepare_update functions.
+    /// Targets from prepare_update_weighted keep their weight, so their detail is the new detail multiplied by it.
+    /// The budget from prepare_update_budgeted isn't kept, so this can go over it.
+    /// This updates the tree until it's done, so chunks are added and merged as needed for the new detail,
+    /// and removed chunks end up in the chunks to delete, same as when calling do_update yourself.
+    /// # Params
+    /// * `detail` The new detail for the targets
+    /// * `chunk_creator` function to create a new chunk from a given position
+    pub fn set_detail(&mut self, detail: u64, mut chunk_creator: impl FnMut(L) -> C) {
+        let targets = self.last_targets.clone();
+
+        while self.prepare_update_weighted(&targets, detail, &mut chunk_creator) {
+            self.do_update();
+        }
+    }
+
+    /// prepares the tree for an update, same as prepare_update, but without making the chunks to add.
+    /// Instead, each chunk to add is an empty slot, which has to be filled with fill_chunk_to_add before calling do_update.
+    /// This way the chunks can be made in any order, such as on other threads, while the tree decides the structure.
+    /// Chunks that are still in the cache are put in their slot right away.
+    /// # Params
+    /// * `targets` The target positions to generate the lod around
+    /// * `detail` The detail for these targets
+    ///
+    /// returns wether any update is needed.
+    pub fn prepare_update_structural(&mut self, targets: &[L], detail: u64) -> bool {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("prepare_update_structural").entered();
+
+        // remember the targets for set_detail
+        self.last_targets.clear();
+        self.last_targets
+            .extend(targets.iter().map(|&target| (target, 1.0)));
+
+        let needs_update = self.prepare_update_with(
+            |node| targets.iter().any(|x| x.can_subdivide(node, detail)),
+            None::<fn(L) -> C>,
+        );
+
+        #[cfg(feature = "tracing")]
+        self.trace_prepared_update(targets.len(), detail);
+
+        needs_update
+    }
+
+    /// prepares the tree for an update, same as prepare_update, but the tree won't have more than max_chunks chunks after the update.
+    /// If the update would go over, nodes closest to the targets are subdivided first, and the rest are left for later.
+    /// Nodes are only merged when the targets don't need them anymore, so if the tree is already over the budget it only shrinks as the targets move away
+    /// # Params
+    /// * `targets` The target positions to generate the lod around
+    /// * `detail` The detail for these targets
+    /// * `max_chunks` the max number of chunks the tree can have after the update
+    /// * `chunk_creator` function to create a new chunk from a given position, only called for the chunks that fit in the budget
+    ///
+    /// returns wether any update is needed.
+    pub fn prepare_update_budgeted(
+        &mut self,
+        targets: &[L],
+        detail: u64,
+        max_chunks: usize,
+        mut chunk_creator: impl FnMut(L) -> C,
+    ) -> bool {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("prepare_update_budgeted").entered();
+
+        // remember the targets for set_detail
+        self.last_targets.clear();
+        self.last_targets
+            .extend(targets.iter().map(|&target| (target, 1.0)));
+
+        // plan without making the chunks, as not all of them might fit
+        self.prepare_update_with(
+            |node| targets.iter().any(|x| x.can_subdivide(node, detail)),
+            None::<fn(L) -> C>,
+        );
+
+        // drop what doesn't fit
+        self.plan_budget(targets, max_chunks);
+
+        // and make the chunks for the rest
+        for container in self.chunks_to_fill.iter_mut() {
+            if container.chunk.is_none() {
+                container.chunk = Some(chunk_creator(container.position));
+            }
+        }
+
+        self.add_filled_chunks();
+
+        #[cfg(feature = "tracing")]
+        self.trace_prepared_update(targets.len(), detail);
+
+        !self.chunks_to_add.is_empty() || !self.chunks_to_remove.is_empty()
+    }
+
+    // prepares the update, where can_subdivide decides if the node at a position needs to be subdivided
+    // without a chunk_creator, chunks that aren't in the cache are left empty, to be filled in later
+    fn prepare_update_with(
+        &mut self,
+        can_subdivide: impl Fn(L) -> bool,
+        mut chunk_creator: Option<impl FnMut(L) -> C>,
+    ) -> bool {
+        self.mark_modified();
+
+        // first, clear the previous arrays
+        self.release_pending();
+        self.chunks_to_add_parent.clear();
+        self.chunks_to_remove.clear();
+        self.chunks_to_activate.clear();
+        self.chunks_to_deactivate.clear();
+
+        // if we don't have a root, make one pending for creation
+        if self.nodes.is_empty() {
+            // chunk to add
+            let root = self.root;
+            let chunk_to_add = self.chunk_cache.remove(&root).or_else(|| {
+                chunk_creator
+                    .as_mut()
+                    .map(|chunk_creator| chunk_creator(root))
+            });
+
+            // we need to add the root as pending
+            self.chunks_to_fill.push(ToAddContainer {
+                position: self.root,
+                chunk: chunk_to_add,
+            });
+
+            // and the parent
+            self.chunks_to_add_parent.push(0);
+
+            // and move it over if it's already there
+            if chunk_creator.is_some() {
+                self.add_filled_chunks();
+            }
+
+            // and an update is needed
+            return true;
+        }
+
+        // borrow all lists separately, so they can be filled while planning
+        let Self {
+            nodes,
+            frozen,
+            processing_queue,
+            chunk_cache,
+            chunks_to_fill,
+            chunks_to_add_parent,
+            chunks_to_remove,
+            chunks_to_activate,
+            chunks_to_deactivate,
+            root,
+            ..
+        } = self;
+
+        plan_update(
+            *root,
+            nodes,
+            frozen,
+            can_subdivide,
+            processing_queue,
+            |current_node_index, current_position| {
+                // add children to be added
+                for i in 0..L::num_children() {
+                    // chunk to add, from the cache if possible
+                    let position = current_position.get_child(i);
+                    let chunk_to_add = chunk_cache.remove(&position).or_else(|| {
+                        chunk_creator
+                            .as_mut()
+                            .map(|chunk_creator| chunk_creator(position))
+                    });
+
+                    // add the new chunk to be added
+                    chunks_to_fill.push(ToAddContainer {
+                        position,
+                        chunk: chunk_to_add,
+                    });
+
+                    // and add the parent
+                    chunks_to_add_parent.push(current_node_index);
+                }
+
+                // and add ourselves for deactivation
+                chunks_to_deactivate.push(current_node_index);
+            },
+            |current_node_index, index| {
+                // first, queue ourselves for activation
+                chunks_to_activate.push(current_node_index);
+
+                for i in 0..L::num_children() {
+                    // no need to do this in reverse, that way the last node removed will be added to the free list, which is also the first thing used by the adding logic
+                    chunks_to_remove.push(ToRemoveContainer {
+                        chunk: index + i,
+                        parent: current_node_index,
+                    });
+                }
+            },
+        );
+
+        // make sure the update fits in the chunk limit
+        self.plan_evictions();
+
+        // wether an update needs to be done
+        let needs_update = !self.chunks_to_fill.is_empty() || !self.chunks_to_remove.is_empty();
+
+        // all chunks are made already if there's a chunk creator
+        if chunk_creator.is_some() {
+            self.add_filled_chunks();
+        }
+
+        needs_update
+    }
+
+    // emits an event with what the prepared update is going to do
+    #[cfg(feature = "tracing")]
+    fn trace_prepared_update(&self, targets: usize, detail: u64) {
+        tracing::debug!(
+            targets,
+            detail,
+            to_add = self.chunks_to_add.len(),
+            to_remove = self.chunks_to_remove.len(),
+            to_activate = self.chunks_to_activate.len(),
+            to_deactivate = self.chunks_to_deactivate.len(),
+            "prepared update"
+        );
+    }
+
+    /// gets the update that's prepared, so what do_update is going to add, remove, activate and deactivate.
+    /// This is the same as the get_num_chunks_to_* and get_chunk_to_* methods, but in one place
+    #[inline]
+    pub fn pending_update(&self) -> UpdateDiff<'_, C, L> {
+        UpdateDiff { tree: self }
+    }
+
+    /// predicts what the next call to prepare_update would do for the given targets, without changing the tree.
+    /// This can be used to prefetch chunks before the targets actually move there.
+    /// # Params
+    /// * `future_targets` The target positions to predict the update for
+    /// * `detail` The detail for these targets
+    ///
+    /// returns the positions of the chunks that would be added and removed.
+    pub fn predict_update(&self, future_targets: &[L], detail: u64) -> UpdatePlan<L> {
+        let mut plan = UpdatePlan::default();
+
+        // without a root, the only thing that happens is adding it
+        if self.nodes.is_empty() {
+            plan.to_add.push(self.root);
+            return plan;
+        }
+
+        let UpdatePlan { to_add, to_remove } = &mut plan;
+
+        plan_update(
+            self.root,
+            &self.nodes,
+            &self.frozen,
+            |node| future_targets.iter().any(|x| x.can_subdivide(node, detail)),
+            &mut Vec::new(),
+            |_, position| to_add.extend((0..L::num_children()).map(|i| position.get_child(i))),
+            |_, index| {
+                to_remove.extend(
+                    (index..index + L::num_children())
+                        .map(|i| self.chunks[self.nodes[i].chunk].position),
+                )
+            },
+        );
+
+        plan
+    }
+
+    /// Runs the update that's stored in the internal lists.
+    /// This adds and removes chunks based on that, however this assumes that chunks in the to_activate and to_deactivate list were manually activated or deactivated.
+    /// This also assumes that the chunks in to_add had proper initialization, as they are added to the tree.
+    /// After this, it's needed to clean un nodes in the chunk_to_delete list and call the function complete_update(), in order to properly clear the cache.
+    /// Until then, the chunks to activate and deactivate can still be looked at, as they are still in the tree
+    /// # Panics
+    /// If the update was prepared with prepare_update_structural, and not all chunks to add were filled with fill_chunk_to_add,
+    /// or if the chunks to add were taken with take_chunks_to_add, and not given back with return_chunks_to_add
+    pub fn do_update(&mut self) {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("do_update").entered();
+
+        // taken chunks need to be back, otherwise their parents are subdivided without them
+        assert!(
+            self.taken_chunks_to_add.is_empty(),
+            "chunks to add were taken and not returned before do_update"
+        );
+
+        // chunks from prepare_update_structural need to be filled in by now
+        self.add_filled_chunks();
+
+        // keep track of how long the update takes, and what it did
+        #[cfg(feature = "tracing")]
+        let (start, added, removed) = (
+            std::time::Instant::now(),
+            self.chunks_to_add.len(),
+            self.chunks_to_remove.len(),
+        );
+
+        self.mark_modified();
+
+        // keep track of the update, if needed
+        if let Some(stats) = self.stats.as_mut() {
+            let added = self.chunks_to_add.len() as u64;
+            let removed = self.chunks_to_remove.len() as u64;
+
+            stats.updates += 1;
+            stats.total_added += added;
+            stats.total_removed += removed;
+            stats.max_added = stats.max_added.max(added);
+            stats.max_removed = stats.max_removed.max(removed);
+        }
+
+        // chunks that needed to be (de)activated are assumed to be handled beforehand, so only mark them as such
+        for &index in self.chunks_to_activate.iter() {
+            self.chunks[self.nodes[index].chunk].active = true;
+        }
+
+        for &index in self.chunks_to_deactivate.iter() {
+            self.chunks[self.nodes[index].chunk].active = false;
+        }
+
+        // first, get the iterator for chunks that will be added
+        // this becomes useful later
+        let mut chunks_to_add_iter = self
+            .chunks_to_add_parent
+            .drain(..)
+            .zip(self.chunks_to_add.drain(..));
+
+        // then, remove old chunks, or cache them
+        // we'll drain the vector, as we don't need it anymore afterward
+        for ToRemoveContainer {
+            chunk: index,
+            parent: parent_index,
+        } in self.chunks_to_remove.drain(..)
+        // but we do need to cache these
+        {
+            // remove the node from the tree
+            self.nodes[parent_index].children = None;
+            // give it a new generation, so ids for it become invalid
+            self.generation += 1;
+            self.nodes[index].generation = self.generation;
+            self.free_list.push_back(index);
+
+            // and remove the chunk
+            let chunk_index = self.nodes[index].chunk;
+
+            // but not so fast, because if we can overwrite it with a new chunk, do so
+            // that way we can avoid a copy later on, which might be expensive
+            if let Some((parent_index, ToAddContainer { position, chunk })) =
+                chunks_to_add_iter.next()
+            {
+                // add the node, one deeper than it's parent
+                let depth = self.nodes[parent_index].depth + 1;
+                let new_node_index = match self.free_list.pop_front() {
+                    Some(x) => {
+                        // reuse a free node
+                        self.generation += 1;
+                        self.nodes[x] = TreeNode {
+                            children: None,
+                            chunk: chunk_index,
+                            generation: self.generation,
+                            depth,
+                        };
+
+                        // old chunk that was previously in the array
+                        // we initialize it to the new chunk, then swap them
+                        let mut old_chunk = ChunkContainer {
+                            index: x,
+                            chunk,
+                            position,
+                            active: true,
+                            last_access: self.access_clock.stamp(self.chunk_limit),
+                        };
+
+                        std::mem::swap(&mut old_chunk, &mut self.chunks[chunk_index]);
+
+                        // old chunk shouldn't be mutable anymore
+                        let old_chunk = old_chunk;
+
+                        // now, we can try to add this chunk into the cache
+                        // first, remove any extra nodes if they are in the cache
+                        while self.chunk_cache.len() > self.cache_size {
+                            if let Some(chunk_position) = self.cache_queue.pop_front() {
+                                // check if the chunk is inside the map
+                                if let Some(cached_chunk) = self.chunk_cache.remove(&chunk_position)
+                                {
+                                    // if it is, it's removed, so we need to push it to the chunks that are going to be deleted
+                                    self.chunks_to_delete.push(ToDeleteContainer {
+                                        position: chunk_position,
+                                        chunk: cached_chunk,
+                                    });
+                                }
+                            } else {
+                                // just break, otherwise we'll be stuck in an infinite loop
+                                break;
+                            }
+                        }
+
+                        // then assign this chunk into the cache
+                        if let Some(cached_chunk) =
+                            self.chunk_cache.insert(old_chunk.position, old_chunk.chunk)
+                        {
+                            // there might have been another cached chunk
+                            self.chunks_to_delete.push(ToDeleteContainer {
+                                position: old_chunk.position,
+                                chunk: cached_chunk,
+                            });
+                        }
+
+                        // and make sure it's tracked
+                        self.cache_queue.push_back(old_chunk.position);
+
+                        x
+                    }
+                    // This can't be reached due to us *always* adding a chunk to the free list before popping it
+                    None => unsafe { std::hint::unreachable_unchecked() },
+                };
+
+                // correctly set the children of the parent node.
+                // because the last node we come by in with ordered iteration is on num_children - 1, we need to set it as such].
+                // node 0 is the root, so the last child it has will be on num_children.
+                // then subtracting num_children - 1 from that gives us node 1, which is the first child of the root.
+                if new_node_index >= L::num_children() {
+                    // because we loop in order, and our nodes are contiguous, the first node of the children got added on index i - (num children - 1)
+                    // so we need to adjust for that
+                    self.nodes[parent_index].children =
+                        NonZeroUsize::new(new_node_index - (L::num_children() - 1));
+                }
+            } else {
+                // otherwise we do need to do a regular swap remove
+                let old_chunk = self.chunks.swap_remove(chunk_index);
+
+                // now, we can try to add this chunk into the cache
+                // first, remove any extra nodes if they are in the cache
+                while self.chunk_cache.len() > self.cache_size {
+                    if let Some(chunk_position) = self.cache_queue.pop_front() {
+                        // check if the chunk is inside the map
+                        if let Some(cached_chunk) = self.chunk_cache.remove(&chunk_position) {
+                            // if it is, it's removed, so we need to push it to the chunks that are going to be deleted
+                            self.chunks_to_delete.push(ToDeleteContainer {
+                                position: chunk_position,
+                                chunk: cached_chunk,
+                            });
+                        }
+                    } else {
+                        // just break, otherwise we'll be stuck in an infinite loop
+                        break;
+                    }
+                }
+
+                // then assign this chunk into the cache
+                if let Some(cached_chunk) =
+                    self.chunk_cache.insert(old_chunk.position, old_chunk.chunk)
+                {
+                    // there might have been another cached chunk
+                    self.chunks_to_delete.push(ToDeleteContainer {
+                        position: old_chunk.position,
+                        chunk: cached_chunk,
+                    });
+                }
+
+                // and make sure it's tracked
+                self.cache_queue.push_back(old_chunk.position);
+            }
+
+            // and properly set the chunk pointer of the node of the chunk we just moved, if any
+            // if we removed the last chunk, no need to update anything
+            if chunk_index < self.chunks.len() {
+                self.nodes[self.chunks[chunk_index].index].chunk = chunk_index;
+            }
+        }
+
+        // add new chunks
+        // we'll drain the vector here as well, as we won't need it anymore afterward
+        for (parent_index, ToAddContainer { position, chunk }) in chunks_to_add_iter {
+            // add the node, one deeper than it's parent, or at the depth of the root if it's the root
+            let depth = self
+                .nodes
+                .get(parent_index)
+                .map_or(self.root_depth, |parent| parent.depth + 1);
+            let new_node_index = match self.free_list.pop_front() {
+                Some(x) => {
+                    // reuse a free node
+                    self.generation += 1;
+                    self.nodes[x] = TreeNode {
+                        children: None,
+                        chunk: self.chunks.len(),
+                        generation: self.generation,
+                        depth,
+                    };
+                    self.chunks.push(ChunkContainer {
+                        index: x,
+                        chunk,
+                        position,
+                        active: true,
+                        last_access: self.access_clock.stamp(self.chunk_limit),
+                    });
+                    x
+                }
+                None => {
+                    // otherwise, use a new index
+                    self.generation += 1;
+                    self.nodes.push(TreeNode {
+                        children: None,
+                        chunk: self.chunks.len(),
+                        generation: self.generation,
+                        depth,
+                    });
+                    self.chunks.push(ChunkContainer {
+                        index: self.nodes.len() - 1,
+                        chunk,
+                        position,
+                        active: true,
+                        last_access: self.access_clock.stamp(self.chunk_limit),
+                    });
+                    self.nodes.len() - 1
+                }
+            };
+
+            // correctly set the children of the parent node.
+            // because the last node we come by in with ordered iteration is on num_children - 1, we need to set it as such].
+            // node 0 is the root, so the last child it has will be on num_children.
+            // then subtracting num_children - 1 from that gives us node 1, which is the first child of the root.
+            if new_node_index >= L::num_children() {
+                // because we loop in order, and our nodes are contiguous, the first node of the children got added on index i - (num children - 1)
+                // so we need to adjust for that
+                self.nodes[parent_index].children =
+                    NonZeroUsize::new(new_node_index - (L::num_children() - 1));
+            }
+        }
+
+        // and clear the lists of chunks that are now in or out of the tree, so if this method is accidentally called twice, no weird behavior would happen
+        // the chunks to (de)activate stay in the tree, so those are kept around until complete_update
+        self.chunks_to_add.clear();
+        self.chunks_to_remove.clear();
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            added,
+            removed,
+            chunks = self.chunks.len(),
+            elapsed_us = start.elapsed().as_micros() as u64,
+            "did update"
+        );
+    }
+
+    /// Completes the update by removing all chunks that can't be stored anymore permanently, and cleaning up what's left of the update.
+    /// This is the last step of an update, after prepare_update and do_update, and is called once the chunks to delete are cleaned up.
+    /// Afterwards, the chunks to delete, activate and deactivate are all empty, and if only the root is left, the unused nodes are freed.
+    /// The next prepare_update also clears these lists, so skipping this only keeps the chunks to delete alive for longer
+    #[inline]
+    pub fn complete_update(&mut self) {
+        self.mark_modified();
+
+        // clear the chunks to be deleted
+        self.release_deleted();
+
+        // as well as the chunks that were (de)activated
+        self.chunks_to_activate.clear();
+        self.chunks_to_deactivate.clear();
+
+        // if there's only chunk left, we know it's the root, so we can get rid of all free nodes and unused nodes
+        if self.chunks.len() == 1 {
+            self.free_list.clear();
+            self.nodes.truncate(1);
+        }
+    }
+
+    // calls the remove function for the chunks of a pending update that wasn't applied, and drops them
+    fn release_pending(&mut self) {
+        if let Some(on_remove) = &self.on_remove {
+            for container in self.chunks_to_add.iter_mut() {
+                on_remove.call(container.position, &mut container.chunk);
+            }
+
+            for container in self.chunks_to_fill.iter_mut() {
+                if let Some(chunk) = &mut container.chunk {
+                    on_remove.call(container.position, chunk);
+                }
+            }
+        }
+
+        self.chunks_to_add.clear();
+        self.chunks_to_fill.clear();
+        self.taken_chunks_to_add.clear();
+    }
+
+    // calls the remove function for the chunks to delete, and drops them
+    fn release_deleted(&mut self) {
+        if let Some(on_remove) = &self.on_remove {
+            for container in self.chunks_to_delete.iter_mut() {
+                on_remove.call(container.position, &mut container.chunk);
+            }
+        }
+
+        self.chunks_to_delete.clear();
+    }
+
+    /// clears the tree, removing all chunks and internal lists and cache
+    #[inline]
+    pub fn clear(&mut self) {
+        self.mark_modified();
+
+        // all chunks are dropped, both in the tree and in the cache
+        if let Some(on_remove) = &self.on_remove {
+            for container in self.chunks.iter_mut() {
+                on_remove.call(container.position, &mut container.chunk);
+            }
+
+            for (position, chunk) in self.chunk_cache.iter_mut() {
+                on_remove.call(*position, chunk);
+            }
+        }
+
+        self.release_pending();
+        self.release_deleted();
+
+        self.chunks.clear();
+        self.nodes.clear();
+        self.free_list.clear();
+        self.fill_queue.clear();
+        self.chunks_to_add_parent.clear();
+        self.chunks_to_remove.clear();
+        self.chunks_to_activate.clear();
+        self.chunks_to_deactivate.clear();
+        self.processing_queue.clear();
+        self.cache_queue.clear();
+        self.chunk_cache.clear();
+        self.dirty.clear();
+        self.last_targets.clear();
+    }
+
+    /// Shrinks all internal buffers to fit, reducing memory usage.
+    /// Due to most of the intermediate processing buffers being cleared after an update is done, the next update might take longer due to needing to reallocate the memory.
+    #[inline]
+    pub fn shrink(&mut self) {
+        // it should be possible to also shrink the nodes as well, and remove the free space, but this would be rather dificult to do
+        // because we have groups of num_children
+        // I'm leaving it out for now
+        self.mark_modified();
+        self.chunks.shrink_to_fit();
+        self.nodes.shrink_to_fit();
+        self.free_list.shrink_to_fit();
+        self.chunks_to_add.shrink_to_fit();
+        self.chunks_to_fill.shrink_to_fit();
+        self.fill_queue.shrink_to_fit();
+        self.chunks_to_remove.shrink_to_fit();
+        self.chunks_to_activate.shrink_to_fit();
+        self.chunks_to_deactivate.shrink_to_fit();
+        self.chunks_to_delete.shrink_to_fit();
+        self.processing_queue.shrink_to_fit();
+        self.cache_queue.shrink_to_fit();
+    }
+
+    /// freezes the region starting at the given position, so prepare_update won't merge any chunks inside it.
+    /// Nodes above the region won't be merged either, so the chunks in the region stay in the tree even if the targets move away from it.
+    /// The region can still be subdivided further.
+    #[inline]
+    pub fn freeze_region(&mut self, position: L) {
+        self.frozen.insert(position);
+    }
+
+    /// unfreezes a region that was frozen with freeze_region, allowing it to be merged again.
+    /// Returns wether the region was frozen.
+    #[inline]
+    pub fn unfreeze_region(&mut self, position: L) -> bool {
+        self.frozen.remove(&position)
+    }
+
+    /// marks the chunk at the given position as dirty, so it's returned by the next call to drain_dirty.
+    /// This is only tracked for the caller, and doesn't change anything in the tree itself
+    #[inline]
+    pub fn mark_dirty(&mut self, position: L) {
+        self.dirty.insert(position);
+    }
+
+    /// iterates over all chunks and their positions that were marked as dirty with mark_dirty, in no particular order, and unmarks them.
+    /// Chunks that were marked, but aren't in the tree anymore, are skipped
+    pub fn drain_dirty(&mut self) -> impl Iterator<Item = (L, &C)> {
+        let dirty = std::mem::take(&mut self.dirty);
+        let tree = &*self;
+
+        dirty.into_iter().filter_map(move |position| {
+            tree.get_chunk_from_position(position)
+                .map(|chunk| (position, chunk))
+        })
+    }
+
+    /// gets an estimate of the memory used by the tree itself in bytes, based on the capacity of the internal buffers.
+    /// This doesn't include the chunks, either in the tree, cache or pending lists, use total_footprint for that
+    pub fn memory_usage(&self) -> usize {
+        use std::mem::size_of;
+
+        // chunk containers without the chunk itself
+        self.chunks.capacity() * (size_of::<ChunkContainer<C, L>>() - size_of::<C>())
+            + self.nodes.capacity() * size_of::<TreeNode>()
+            + self.free_list.capacity() * size_of::<usize>()
+            + self.chunks_to_add_parent.capacity() * size_of::<usize>()
+            + self.fill_queue.capacity() * size_of::<usize>()
+            + self.chunks_to_remove.capacity() * size_of::<ToRemoveContainer>()
+            + self.chunks_to_activate.capacity() * size_of::<usize>()
+            + self.chunks_to_deactivate.capacity() * size_of::<usize>()
+            + self.processing_queue.capacity() * size_of::<QueueContainer<L>>()
+            + self.cache_queue.capacity() * size_of::<L>()
+            + self.frozen.capacity() * size_of::<L>()
+    }
+
+    /// gets an estimate of the memory used by the tree and the chunks in it in bytes.
+    /// This is memory_usage, plus the size of the chunks in the tree. Any heap memory owned by the chunks isn't included, use total_footprint_with for that
+    #[inline]
+    pub fn total_footprint(&self) -> usize {
+        self.memory_usage() + self.get_num_chunks() * std::mem::size_of::<C>()
+    }
+
+    /// gets an estimate of the memory used by the tree and the chunks in it in bytes, including memory owned by the chunks.
+    /// # Params
+    /// * `per_chunk` function that gives the amount of heap memory owned by a chunk in bytes, which is added on top of the size of the chunk itself
+    #[inline]
+    pub fn total_footprint_with(&self, per_chunk: impl Fn(&C) -> usize) -> usize {
+        self.total_footprint()
+            + self
+                .chunks
+                .iter()
+                .map(|container| per_chunk(&container.chunk))
+                .sum::<usize>()
+    }
+
+    /// starts keeping track of statistics for all updates done with do_update, which can be retrieved with stats.
+    /// Calling this again resets the statistics
+    #[inline]
+    pub fn enable_stats(&mut self) {
+        self.stats = Some(UpdateStats::default());
+    }
+
+    /// gets the statistics of all updates since enable_stats was called, or the default if it wasn't
+    #[inline]
+    pub fn stats(&self) -> UpdateStats {
+        self.stats.unwrap_or_default()
+    }
+
+    /// reorders the nodes and chunks in the tree to improve locality, which makes iterating and searching the tree faster.
+    /// The chunks are sorted in Morton order, parents before their children, and the nodes are compacted so there are no free nodes left.
+    /// Any pending update is kept, but chunk ids become invalid, and chunk indices change
+    pub fn optimize(&mut self) {
+        self.mark_modified();
+
+        // nothing to do without a root
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        // where each old node ended up
+        let mut node_map = vec![usize::MAX; self.nodes.len()];
+
+        // the old chunks, so they can be taken out in any order
+        let mut old_chunks = self.chunks.drain(..).map(Some).collect::<Vec<_>>();
+        let mut nodes = Vec::with_capacity(old_chunks.len());
+
+        // start from the root
+        nodes.push(self.nodes[0]);
+        node_map[0] = 0;
+
+        let mut stack = vec![(0, 0)];
+
+        while let Some((old_index, new_index)) = stack.pop() {
+            // move the chunk for this node
+            let old_node = self.nodes[old_index];
+            let mut container = match old_chunks[old_node.chunk].take() {
+                Some(container) => container,
+                None => unreachable!("chunk is used by multiple nodes"),
+            };
+
+            container.index = new_index;
+            nodes[new_index].chunk = self.chunks.len();
+            self.chunks.push(container);
+
+            // and give the children a new spot, right after each other
+            if let Some(children) = old_node.children {
+                let first_child = nodes.len();
+
+                nodes[new_index].children = NonZeroUsize::new(first_child);
+
+                for i in 0..L::num_children() {
+                    nodes.push(self.nodes[children.get() + i]);
+                    node_map[children.get() + i] = first_child + i;
+                }
+
+                // in reverse, so the first child is processed first
+                for i in (0..L::num_children()).rev() {
+                    stack.push((children.get() + i, first_child + i));
+                }
+            }
+        }
+
+        self.nodes = nodes;
+        self.free_list.clear();
+
+        // and update any pending changes to use the new nodes
+        // these only refer to nodes in the tree, as the root is only added to an empty tree, which returned above
+        let remap = |old: usize| {
+            let new = node_map[old];
+
+            assert_ne!(
+                new,
+                usize::MAX,
+                "pending update refers to a node that isn't in the tree"
+            );
+
+            new
+        };
+
+        for parent in self.chunks_to_add_parent.iter_mut() {
+            *parent = remap(*parent);
+        }
+
+        for ToRemoveContainer { chunk, parent } in self.chunks_to_remove.iter_mut() {
+            *chunk = remap(*chunk);
+            *parent = remap(*parent);
+        }
+
+        for node in self
+            .chunks_to_activate
+            .iter_mut()
+            .chain(self.chunks_to_deactivate.iter_mut())
+        {
+            *node = remap(*node);
+        }
+
+        // the nodes changed, so ids for them aren't valid anymore
+        for node in self.nodes.iter_mut() {
+            self.generation += 1;
+            node.generation = self.generation;
+        }
+    }
+
+    /// checks if the internal structure of the tree is consistent.
+    /// Every node in the tree has to own exactly one chunk that points back at it, with the right position and depth,
+    /// every other node has to be free, and the lists for the pending update can only refer to nodes in the tree.
+    /// This is always the case for a tree that's only changed with updates, but can fail after do_update panicked halfway
+    pub fn validate(&self) -> bool {
+        // an empty tree can't have any chunks either
+        if self.nodes.is_empty() {
+            return self.chunks.is_empty();
+        }
+
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut claimed = vec![false; self.chunks.len()];
+
+        // go over all nodes in the tree
+        let mut stack = vec![(0, self.root, self.root_depth)];
+
+        while let Some((index, position, depth)) = stack.pop() {
+            let node = self.nodes[index];
+
+            // nodes can only be in the tree once
+            if reachable[index] {
+                return false;
+            }
+
+            reachable[index] = true;
+
+            // and need a chunk that no other node has, which points back at this node
+            match self.chunks.get(node.chunk) {
+                Some(container)
+                    if !claimed[node.chunk]
+                        && container.index == index
+                        && container.position == position
+                        && node.depth == depth =>
+                {
+                    claimed[node.chunk] = true
+                }
+                _ => return false,
+            }
+
+            // children have to be in the tree as well
+            if let Some(children) = node.children {
+                if children.get() + L::num_children() > self.nodes.len() {
+                    return false;
+                }
+
+                for i in 0..L::num_children() {
+                    stack.push((children.get() + i, position.get_child(i), depth + 1));
+                }
+            }
+        }
+
+        // all chunks need to belong to a node
+        if claimed.contains(&false) {
+            return false;
+        }
+
+        // every node outside of the tree needs to be free, exactly once
+        let mut free = vec![false; self.nodes.len()];
+
+        for &index in self.free_list.iter() {
+            if index >= self.nodes.len() || reachable[index] || free[index] {
+                return false;
+            }
+
+            free[index] = true;
+        }
+
+        if (0..self.nodes.len()).any(|i| !reachable[i] && !free[i]) {
+            return false;
+        }
+
+        // and the pending update can only touch nodes in the tree
+        let in_tree = |index: usize| reachable.get(index).copied().unwrap_or(false);
+
+        self.chunks_to_add.len() + self.chunks_to_fill.len() == self.chunks_to_add_parent.len()
+            && self
+                .chunks_to_add_parent
+                .iter()
+                .all(|&parent| in_tree(parent))
+            && self
+                .chunks_to_remove
+                .iter()
+                .all(|remove| in_tree(remove.chunk) && in_tree(remove.parent))
+            && self
+                .chunks_to_activate
+                .iter()
+                .chain(self.chunks_to_deactivate.iter())
+                .all(|&index| in_tree(index))
+    }
+
+    /// fixes what it can of a tree that's no longer consistent, for example when do_update panicked halfway through, and reports what was repaired.
+    /// Children that are out of range or don't have a valid chunk are cut off, chunks that don't belong to any node are moved to the chunks to delete,
+    /// back pointers from chunks to their nodes are corrected and the free nodes are recomputed.
+    /// Any pending update is dropped, as it can't be known how much of it was already applied, so it needs to be prepared again.
+    /// Afterwards, validate returns true. A tree that is already consistent is only changed by dropping the pending update
+    pub fn repair(&mut self) -> RepairReport {
+        self.mark_modified();
+
+        // the update might have been half applied, so drop it
+        let mut report = RepairReport {
+            pending_dropped: self.chunks_to_add.len()
+                + self.chunks_to_fill.len()
+                + self.chunks_to_remove.len(),
+            ..Default::default()
+        };
+
+        self.release_pending();
+        self.chunks_to_add_parent.clear();
+        self.chunks_to_remove.clear();
+
+        // without a root with a chunk, nothing can be kept
+        if self
+            .nodes
+            .first()
+            .map_or(true, |root| root.chunk >= self.chunks.len())
+        {
+            report.orphaned_chunks = self.chunks.len();
+            report.pending_dropped +=
+                self.chunks_to_activate.len() + self.chunks_to_deactivate.len();
+            report.free_list_fixes = self.free_list.len() + self.nodes.len().saturating_sub(1);
+
+            self.chunks_to_delete
+                .extend(self.chunks.drain(..).map(|container| ToDeleteContainer {
+                    position: container.position,
+                    chunk: container.chunk,
+                }));
+
+            self.nodes.clear();
+            self.free_list.clear();
+            self.chunks_to_activate.clear();
+            self.chunks_to_deactivate.clear();
+
+            return report;
+        }
+
+        // find all nodes that are still properly in the tree, in the order they are visited
+        let mut reachable = vec![false; self.nodes.len()];
+        let mut claimed = vec![false; self.chunks.len()];
+        let mut visited = Vec::with_capacity(self.chunks.len());
+
+        reachable[0] = true;
+        claimed[self.nodes[0].chunk] = true;
+
+        let mut stack = vec![(0, self.root, self.root_depth)];
+
+        while let Some((index, position, depth)) = stack.pop() {
+            visited.push((index, position, depth));
+
+            let children = match self.nodes[index].children {
+                Some(children) => children.get(),
+                None => continue,
+            };
+
+            // the children need to be in range, and each need a chunk of their own
+            let mut child_chunks = (children..children + L::num_children())
+                .filter(|&child| child < self.nodes.len() && !reachable[child])
+                .map(|child| self.nodes[child].chunk)
+                .filter(|&chunk| chunk < self.chunks.len() && !claimed[chunk])
+                .collect::<Vec<_>>();
+
+            child_chunks.sort_unstable();
+            child_chunks.dedup();
+
+            // if not, cut them off, so this node becomes a leaf again
+            if child_chunks.len() != L::num_children() {
+                report.detached_children += 1;
+                self.nodes[index].children = None;
+                self.chunks[self.nodes[index].chunk].active = true;
+                continue;
+            }
+
+            for &chunk in child_chunks.iter() {
+                claimed[chunk] = true;
+            }
+
+            // in reverse, so the first child is processed first
+            for i in (0..L::num_children()).rev() {
+                reachable[children + i] = true;
+                stack.push((children + i, position.get_child(i), depth + 1));
+            }
+        }
+
+        // chunks without a node can't be reached anymore, so they are deleted
+        if claimed.contains(&false) {
+            let mut chunk_map = vec![usize::MAX; self.chunks.len()];
+
+            for (old_index, container) in std::mem::take(&mut self.chunks).into_iter().enumerate() {
+                if claimed[old_index] {
+                    chunk_map[old_index] = self.chunks.len();
+                    self.chunks.push(container);
+                } else {
+                    report.orphaned_chunks += 1;
+                    self.chunks_to_delete.push(ToDeleteContainer {
+                        position: container.position,
+                        chunk: container.chunk,
+                    });
+                }
+            }
+
+            for &(index, _, _) in visited.iter() {
+                self.nodes[index].chunk = chunk_map[self.nodes[index].chunk];
+            }
+        }
+
+        // make sure the chunks point back at their node, and the nodes know their depth
+        for &(index, position, depth) in visited.iter() {
+            let node = &mut self.nodes[index];
+            let container = &mut self.chunks[node.chunk];
+
+            if container.index != index || container.position != position || node.depth != depth {
+                report.stale_back_pointers += 1;
+                container.index = index;
+                container.position = position;
+                node.depth = depth;
+            }
+        }
+
+        // all other nodes are free, and get a new generation so ids for them become invalid
+        let old_free = std::mem::take(&mut self.free_list);
+        let mut was_free = vec![false; self.nodes.len()];
+
+        for index in old_free {
+            match was_free.get_mut(index) {
+                Some(free) if !*free && !reachable[index] => *free = true,
+                _ => report.free_list_fixes += 1,
+            }
+        }
+
+        for index in 0..self.nodes.len() {
+            if !reachable[index] {
+                if !was_free[index] {
+                    report.free_list_fixes += 1;
+                    self.generation += 1;
+                    self.nodes[index].generation = self.generation;
+                    self.nodes[index].children = None;
+                }
+
+                self.free_list.push_back(index);
+            }
+        }
+
+        // and the chunks to (de)activate can only be in the tree
+        let (activate, deactivate) = (
+            self.chunks_to_activate.len(),
+            self.chunks_to_deactivate.len(),
+        );
+        let in_tree = |index: &usize| reachable.get(*index).copied().unwrap_or(false);
+        self.chunks_to_activate.retain(in_tree);
+        self.chunks_to_deactivate.retain(in_tree);
+        report.pending_dropped +=
+            activate + deactivate - self.chunks_to_activate.len() - self.chunks_to_deactivate.len();
+
+        report
+    }
+
+    /// encodes the structure of the tree, without any chunks, as one bit per node.
+    /// Nodes are visited depth first, with parents before their children and children in order, and a bit is 1 if the node has children, and 0 if it's a leaf.
+    /// Bits are packed into bytes starting at the lowest bit, and an empty tree gives no bytes
+    pub fn encode_topology(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        // nothing to encode without a root
+        if self.nodes.is_empty() {
+            return bytes;
+        }
+
+        // go over all nodes, starting at the root
+        let mut bit = 0;
+        let mut stack = vec![0];
+
+        while let Some(index) = stack.pop() {
+            // make room for the bit
+            if bit % 8 == 0 {
+                bytes.push(0);
+            }
+
+            if let Some(children) = self.nodes[index].children {
+                bytes[bit / 8] |= 1 << (bit % 8);
+
+                // in reverse, so the first child is processed first
+                stack.extend((children.get()..children.get() + L::num_children()).rev());
+            }
+
+            bit += 1;
+        }
+
+        bytes
+    }
+
+    /// builds a tree from the structure made by encode_topology, and makes the chunk for every node with chunk_creator.
+    /// Missing bits are seen as leaves, and bits left over are ignored.
+    /// Returns None if the structure goes deeper than L::MAX_DEPTH, chunk_creator isn't called in that case
+    /// # Params
+    /// * `bits` the encoded structure of the tree
+    /// * `chunk_creator` function to create a new chunk from a given position
+    pub fn decode_topology(bits: &[u8], chunk_creator: impl FnMut(L) -> C) -> Option<Self> {
+        let mut tree = Self::default();
+
+        // nothing to decode means no root
+        if bits.is_empty() {
+            return Some(tree);
+        }
+
+        // wether the node at the given bit has children
+        let has_children = |bit: usize| {
+            bits.get(bit / 8)
+                .map_or(false, |byte| byte & (1 << (bit % 8)) != 0)
+        };
+
+        // go over the structure first, to make sure it isn't too deep
+        // siblings have the same depth, so the depths come out in the same order as when building the tree
+        let mut stack = vec![tree.root_depth];
+        let mut bit = 0;
+
+        while let Some(depth) = stack.pop() {
+            if has_children(bit) {
+                if depth >= L::MAX_DEPTH {
+                    return None;
+                }
+
+                stack.extend(std::iter::repeat(depth + 1).take(L::num_children()));
+            }
+
+            bit += 1;
+        }
+
+        // the tree is built in the same order as it was encoded in
+        let mut bit = 0;
+
+        tree.rebuild(
+            |_| {
+                bit += 1;
+                has_children(bit - 1)
+            },
+            chunk_creator,
+        );
+
+        Some(tree)
+    }
+
+    /// gets the structure of the tree, without any chunks, to store it and rebuild the tree later with rebuild_from_structure.
+    /// Unlike encode_topology, this keeps the position of every node, where the nodes and chunks are stored, and which nodes are free.
+    /// The chunk cache and any pending update are not kept
+    pub fn serialize_structure(&self) -> SerializedTree<L> {
+        SerializedTree {
+            root: self.root,
+            nodes: self
+                .nodes
+                .iter()
+                .enumerate()
+                .map(|(index, node)| {
+                    // free nodes don't have a chunk that points back at them
+                    let container = self
+                        .chunks
+                        .get(node.chunk)
+                        .filter(|container| container.index == index)?;
+
+                    Some(SerializedNode {
+                        position: container.position,
+                        children: node.children.map(NonZeroUsize::get),
+                        chunk: node.chunk,
+                        active: container.active,
+                    })
+                })
+                .collect(),
+            free_list: self.free_list.iter().copied().collect(),
+        }
+    }
+
+    /// builds a tree from the structure made by serialize_structure, and makes the chunk for every node with chunk_creator.
+    /// The nodes, chunks and free nodes are stored in the same place as in the tree the structure came from, but chunk ids from that tree aren't valid in the new one.
+    /// The cache size starts at 0, and can be changed with set_cache_size
+    ///
+    /// Returns None if the structure isn't a valid tree, for example when the root isn't a valid position, a node is in the tree more than once,
+    /// children are out of range or at the wrong position, the chunks aren't stored once each, or the free list doesn't hold exactly the nodes outside of the tree.
+    /// chunk_creator isn't called in that case
+    /// # Params
+    /// * `structure` the structure of the tree
+    /// * `chunk_creator` function to create a new chunk from a given position, called in the order the chunks are stored in
+    pub fn rebuild_from_structure(
+        structure: SerializedTree<L>,
+        mut chunk_creator: impl FnMut(L) -> C,
+    ) -> Option<Self> {
+        let mut tree = Self::try_with_root(structure.root)?;
+
+        // go down from the root, to find which nodes are in the tree, and their depth
+        let mut depths = vec![None; structure.nodes.len()];
+        let mut stack = Vec::new();
+        let mut num_chunks = 0;
+
+        if !structure.nodes.is_empty() {
+            stack.push((0, structure.root, tree.root_depth));
+        }
+
+        while let Some((index, position, depth)) = stack.pop() {
+            // nodes can only be in the tree once, and can't be free
+            let node = structure.nodes[index]?;
+
+            if depths[index].is_some() || node.position != position {
+                return None;
+            }
+
+            depths[index] = Some(depth);
+            num_chunks += 1;
+
+            if let Some(children) = node.children {
+                // the root can't be a child, and all children have to be in range
+                if children == 0 || children + L::num_children() > structure.nodes.len() {
+                    return None;
+                }
+
+                let depth = depth.checked_add(1)?;
+
+                stack.extend(
+                    (0..L::num_children()).map(|i| (children + i, position.get_child(i), depth)),
+                );
+            }
+        }
+
+        // every chunk is stored exactly once
+        let mut order = vec![None; num_chunks];
+
+        for (index, node) in structure.nodes.iter().enumerate() {
+            match (node, depths[index]) {
+                (Some(node), Some(_)) => match order.get_mut(node.chunk) {
+                    Some(slot @ None) => *slot = Some(index),
+                    _ => return None,
+                },
+                // nodes outside of the tree have to be free
+                (None, None) => (),
+                _ => return None,
+            }
+        }
+
+        // and the free list has all nodes outside of the tree, once
+        let mut free = vec![false; structure.nodes.len()];
+
+        for &index in structure.free_list.iter() {
+            if depths.get(index).copied().flatten().is_some() || free.get(index) != Some(&false) {
+                return None;
+            }
+
+            free[index] = true;
+        }
+
+        if (0..structure.nodes.len()).any(|i| depths[i].is_none() && !free[i]) {
+            return None;
+        }
+
+        // make the nodes
+        tree.nodes = structure
+            .nodes
+            .iter()
+            .zip(depths)
+            .map(|(node, depth)| {
+                tree.generation += 1;
+
+                TreeNode {
+                    children: node
+                        .and_then(|node| node.children)
+                        .and_then(NonZeroUsize::new),
+                    chunk: 0,
+                    generation: tree.generation,
+                    depth: depth.unwrap_or(0),
+                }
+            })
+            .collect();
+
+        // and the chunks, in the order they were stored in
+        for index in order.into_iter().flatten() {
+            let node = structure.nodes[index]?;
+
+            tree.nodes[index].chunk = tree.chunks.len();
+            tree.chunks.push(ChunkContainer {
+                chunk: chunk_creator(node.position),
+                index,
+                position: node.position,
+                active: node.active,
+                last_access: tree.access_clock.stamp(tree.chunk_limit),
+            });
+        }
+
+        tree.free_list = structure.free_list.into_iter().collect();
+
+        // everything was checked beforehand, but make sure the result is consistent
+        if tree.validate() {
+            Some(tree)
+        } else {
+            None
+        }
+    }
+
+    /// sets a function that's called for every chunk right before the tree drops it, with it's position and the chunk itself.
+    /// Chunks that are removed from the tree go to the cache first, and only call it once they are dropped from there,
+    /// which happens when the chunks to delete are cleared by complete_update, and for every chunk in the tree, cache or a pending update during clear.
+    /// Chunks that are replaced with aggregate_up, edit_bounds or regenerate_chunk don't call it, and neither does dropping the tree itself, so call clear first if that's needed.
+    /// Clones of the tree share the same function.
+    /// # Params
+    /// * `on_remove` the function to call, replacing any function that was set before
+    pub fn set_on_remove(&mut self, on_remove: impl FnMut(L, &mut C) + Send + 'static) {
+        self.on_remove = Some(RemoveCallback(Arc::new(Mutex::new(on_remove))));
+    }
+
+    /// removes the function set with set_on_remove, if any
+    #[inline]
+    pub fn clear_on_remove(&mut self) {
+        self.on_remove = None;
+    }
+
+    /// resizes the current cache size
+    /// actual resizing happens on the next update
+    #[inline]
+    pub fn set_cache_size(&mut self, cache_size: usize) {
+        self.cache_size = cache_size;
+    }
+
+    /// sets the max number of chunks in the tree, or removes the limit if it's None.
+    /// The tree only changes to fit in the limit on the next call to prepare_update, see with_chunk_limit.
+    /// Accesses aren't tracked without a limit, so chunks from before it was set count as the least recently accessed
+    #[inline]
+    pub fn set_chunk_limit(&mut self, chunk_limit: Option<usize>) {
+        self.chunk_limit = chunk_limit;
+    }
+
+    // the clock to mark chunks as accessed with, which is only there with a chunk limit, as accesses aren't tracked otherwise
+    #[inline]
+    pub(crate) fn access_clock(&self) -> Option<&AccessStamp> {
+        self.chunk_limit.map(|_| &self.access_clock)
+    }
+
+    // marks a chunk as accessed, if there's a chunk limit
+    #[inline]
+    pub(crate) fn touch(&self, index: usize) {
+        self.chunks[index].last_access.touch(self.access_clock());
+    }
+
+    // makes the update fit in the chunk limit
+    // nodes that would be subdivided aren't subdivided first, as merging chunks the targets still want only makes the next update subdivide them again
+    // if that's not enough, the tree was already over the limit, so the least recently used groups of leaves are added to the chunks to remove
+    fn plan_evictions(&mut self) {
+        let limit = match self.chunk_limit {
+            Some(limit) => limit,
+            None => return,
+        };
+
+        // number of chunks after the update
+        let mut count = self.chunks.len() + self.chunks_to_fill.len() - self.chunks_to_remove.len();
+
+        // don't subdivide some nodes
+        while count > limit && !self.chunks_to_fill.is_empty() {
+            let start = self.chunks_to_fill.len() - L::num_children();
+            let parent = self.chunks_to_add_parent[start];
+
+            self.chunks_to_add_parent.truncate(start);
+            self.chunks_to_deactivate.retain(|&node| node != parent);
+
+            // the chunks for them that were already made need to be deleted
+            self.chunks_to_delete
+                .extend(self.chunks_to_fill.drain(start..).filter_map(
+                    |ToAddContainer { chunk, position }| {
+                        Some(ToDeleteContainer {
+                            chunk: chunk?,
+                            position,
+                        })
+                    },
+                ));
+
+            count -= L::num_children();
+        }
+
+        if count <= limit {
+            return;
+        }
+
+        // nodes that are already changed by the update can't be merged
+        let changed = self
+            .chunks_to_activate
+            .iter()
+            .chain(self.chunks_to_deactivate.iter())
+            .copied()
+            .collect::<HashSet<_>>();
+
+        // and nodes that are merged here become leaves
+        let mut merged = HashSet::new();
+
+        // the parent of each node, as a node can be merged once all it's children are
+        let mut parents = vec![None; self.nodes.len()];
+
+        for container in self.chunks.iter() {
+            if let Some(children) = self.nodes[container.index].children {
+                for i in 0..L::num_children() {
+                    parents[children.get() + i] = Some(container.index);
+                }
+            }
+        }
+
+        // all groups that can be merged, with the one that was used the longest ago first
+        let mut groups = self
+            .chunks
+            .iter()
+            .filter_map(|container| self.evictable_group(container.index, &changed, &merged))
+            .map(Reverse)
+            .collect::<BinaryHeap<_>>();
+
+        while count > limit {
+            // stop if there's nothing left to merge
+            let Reverse((_, node, first_child)) = match groups.pop() {
+                Some(group) => group,
+                None => break,
+            };
+
+            // and merge it
+            for i in 0..L::num_children() {
+                self.chunks_to_remove.push(ToRemoveContainer {
+                    chunk: first_child + i,
+                    parent: node,
+                });
+            }
+
+            self.chunks_to_activate.push(node);
+            merged.insert(node);
+            count -= L::num_children();
+
+            // the parent might only have leaves as children now
+            if let Some(group) =
+                parents[node].and_then(|parent| self.evictable_group(parent, &changed, &merged))
+            {
+                groups.push(Reverse(group));
+            }
+        }
+    }
+
+    // the group of leaves under a node that plan_evictions can merge, as when it was last used, the node, and it's first child
+    // all children need to be leaves that aren't changed by the update, or already merged
+    fn evictable_group(
+        &self,
+        node: usize,
+        changed: &HashSet<usize>,
+        merged: &HashSet<usize>,
+    ) -> Option<(u64, usize, usize)> {
+        let first_child = self.nodes[node].children?.get();
+        let children = first_child..first_child + L::num_children();
+
+        if changed.contains(&node)
+            || merged.contains(&node)
+            || children.clone().any(|child| {
+                (self.nodes[child].children.is_some() && !merged.contains(&child))
+                    || changed.contains(&child)
+            })
+        {
+            return None;
+        }
+
+        // and the group was last used when any child was last used
+        let last_access = children
+            .map(|child| self.chunks[self.nodes[child].chunk].last_access.get())
+            .max()?;
+
+        Some((last_access, node, first_child))
+    }
+
+    // removes the groups of chunks to fill that don't fit in max_chunks, keeping the ones where the parent is closest to a target
+    fn plan_budget(&mut self, targets: &[L], max_chunks: usize) {
+        // the root is always added
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        // number of chunks after the update, without the ones to add
+        let mut count = self.chunks.len() - self.chunks_to_remove.len();
+
+        // all groups of chunks to add, with how far their parent is from the closest target
+        let mut groups = (0..self.chunks_to_fill.len())
+            .step_by(L::num_children())
+            .map(|start| {
+                let parent =
+                    self.chunks[self.nodes[self.chunks_to_add_parent[start]].chunk].position;
+                let distance = targets
+                    .iter()
+                    .map(|target| center_distance_squared(parent, *target))
+                    .fold(f64::INFINITY, f64::min);
+
+                (distance, start)
+            })
+            .collect::<Vec<_>>();
+
+        groups.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        // keep the closest ones that fit
+        let mut keep = vec![false; self.chunks_to_fill.len()];
+
+        for (_, start) in groups {
+            if count + L::num_children() > max_chunks {
+                break;
+            }
+
+            keep[start..start + L::num_children()].fill(true);
+            count += L::num_children();
+        }
+
+        // and remove the others, in reverse so the indices stay valid
+        for start in (0..self.chunks_to_fill.len())
+            .step_by(L::num_children())
+            .rev()
+            .filter(|&start| !keep[start])
+        {
+            let parent = self.chunks_to_add_parent[start];
+
+            self.chunks_to_add_parent
+                .drain(start..start + L::num_children());
+            self.chunks_to_deactivate.retain(|&node| node != parent);
+
+            // chunks that came from the cache need to be deleted
+            self.chunks_to_delete.extend(
+                self.chunks_to_fill
+                    .drain(start..start + L::num_children())
+                    .filter_map(|ToAddContainer { chunk, position }| {
+                        Some(ToDeleteContainer {
+                            chunk: chunk?,
+                            position,
+                        })
+                    }),
+            );
+        }
+    }
+
+    // rebuilds the entire tree from the top down, replacing all nodes and chunks
+    // subdivide decides if the node at a position gets children, and chunk_creator makes the chunk for each node
+    // nodes are visited depth first, with parents before their children, and children in order
+    fn rebuild(
+        &mut self,
+        mut subdivide: impl FnMut(L) -> bool,
+        mut chunk_creator: impl FnMut(L) -> C,
+    ) {
+        self.mark_modified();
+
+        // remove the old tree, and anything that was pending for it
+        self.chunks.clear();
+        self.nodes.clear();
+        self.free_list.clear();
+        self.release_pending();
+        self.chunks_to_add_parent.clear();
+        self.chunks_to_remove.clear();
+        self.chunks_to_activate.clear();
+        self.chunks_to_deactivate.clear();
+
+        // start from the root
+        self.nodes.push(TreeNode::default());
+
+        let mut stack = vec![(self.root, 0, self.root_depth)];
+
+        while let Some((position, index, depth)) = stack.pop() {
+            // make the chunk for this node
+            self.nodes[index].chunk = self.chunks.len();
+            self.nodes[index].depth = depth;
+            self.generation += 1;
+            self.nodes[index].generation = self.generation;
+            self.chunks.push(ChunkContainer {
+                chunk: chunk_creator(position),
+                index,
+                position,
+                active: true,
+                last_access: self.access_clock.stamp(self.chunk_limit),
+            });
+
+            // and add the children, if any
+            if subdivide(position) {
+                let first_child = self.nodes.len();
+
+                // only leaves are active
+                self.chunks[self.nodes[index].chunk].active = false;
+
+                self.nodes
+                    .resize(first_child + L::num_children(), TreeNode::default());
+                self.nodes[index].children = NonZeroUsize::new(first_child);
+
+                // in reverse, so the first child is processed first
+                for i in (0..L::num_children()).rev() {
+                    stack.push((position.get_child(i), first_child + i, depth + 1));
+                }
+            }
+        }
+    }
+
+    // moves all chunks to a new position, given by translate, or deletes them if it returns None
+    // the tree is then rebuilt to fit the new positions, with chunk_creator making any missing chunks
+    fn translate_with(
+        &mut self,
+        translate: impl Fn(L) -> Option<L>,
+        mut chunk_creator: impl FnMut(L) -> C,
+    ) {
+        // an empty tree stays empty
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        // all chunks that are kept, at their new position
+        let mut moved_chunks = HashMap::with_capacity(self.chunks.len());
+        let root = self.root;
+
+        for ChunkContainer {
+            chunk, position, ..
+        } in self.chunks.drain(..)
+        {
+            // chunks that end up outside of the root are removed as well
+            if let Some(new_position) =
+                translate(position).filter(|&new_position| root.contains_child_node(new_position))
+            {
+                moved_chunks.insert(new_position, chunk);
+            } else {
+                self.chunks_to_delete
+                    .push(ToDeleteContainer { chunk, position });
+            }
+        }
+
+        // dirty chunks stay dirty at their new position, and frozen regions stay frozen
+        let translate_inside = |position| {
+            translate(position).filter(|&new_position| root.contains_child_node(new_position))
+        };
+
+        self.dirty = self.dirty.drain().filter_map(translate_inside).collect();
+        self.frozen = self.frozen.drain().filter_map(translate_inside).collect();
+
+        // cached chunks are at their old positions, so they can't be used anymore either
+        self.cache_queue.clear();
+        self.chunks_to_delete.extend(
+            self.chunk_cache
+                .drain()
+                .map(|(position, chunk)| ToDeleteContainer { chunk, position }),
+        );
+
+        // all positions that need to be subdivided to reach the moved chunks
+        let mut parents = HashSet::with_capacity(moved_chunks.len());
+
+        for &position in moved_chunks.keys() {
+            let mut current = self.root;
+
+            while current != position {
+                parents.insert(current);
+
+                // go down to the child that has the position
+                current = match (0..L::num_children())
+                    .map(|i| current.get_child(i))
+                    .find(|child| child.contains_child_node(position))
+                {
+                    Some(child) => child,
+                    None => break,
+                };
+            }
+        }
+
+        // and build the new tree
+        self.rebuild(
+            |position| parents.contains(&position),
+            |position| {
+                moved_chunks
+                    .remove(&position)
+                    .unwrap_or_else(|| chunk_creator(position))
+            },
+        );
+    }
+
+    // builds a tree from the chunks at the given depth, going bottom up
+    // children are collapsed into their parent if they are all leaves and merge returns a chunk for them
+    // parents that aren't collapsed get their chunk from combine
+    fn from_leaves(
+        depth: u8,
+        mut leaf: impl FnMut(L) -> C,
+        mut merge: impl FnMut(&[C]) -> Option<C>,
+        mut combine: impl FnMut(&[C]) -> C,
+    ) -> Self {
+        // all chunks, and which positions got subdivided
+        let mut chunks = HashMap::new();
+        let mut subdivided = HashSet::new();
+
+        let (root, _) = build_from_leaves(
+            L::root(),
+            depth,
+            &mut leaf,
+            &mut merge,
+            &mut combine,
+            &mut chunks,
+            &mut subdivided,
+        );
+        chunks.insert(L::root(), root);
+
+        // and put it in a tree
+        // every node is either the root or the child of a subdivided node, so all have a chunk
+        let mut tree = Self::default();
+        tree.rebuild(
+            |position| subdivided.contains(&position),
+            |position| chunks.remove(&position).expect("every node has a chunk"),
+        );
+
+        tree
+    }
+
+    // moves the filled in chunks over to the chunks to add
+    fn add_filled_chunks(&mut self) {
+        // check first, so the tree is left as is if one isn't filled in
+        assert!(
+            self.chunks_to_fill
+                .iter()
+                .all(|container| container.chunk.is_some()),
+            "not all chunks to add were filled in"
+        );
+
+        self.chunks_to_add.extend(self.chunks_to_fill.drain(..).map(
+            |ToAddContainer { chunk, position }| ToAddContainer {
+                chunk: chunk.expect("chunks to add are filled in"),
+                position,
+            },
+        ));
+    }
+}
+
+// sets all cells a leaf covers in a grid made by summary_grid to it's depth, if that's deeper than what's there already
+// the grid covers the root, so a leaf is placed relative to it
+fn add_leaf_to_grid<L: LodVec>(grid: &mut [u8], cells: usize, root: L, position: L, depth: u8) {
+    // how many cells a unit of float coords is
+    let scale = cells as f64 / root.get_size();
+
+    // find the range of cells it covers on each axis
+    let size = position.get_size() * scale;
+    let ranges = position
+        .get_float_coords()
+        .as_ref()
+        .iter()
+        .zip(root.get_float_coords().as_ref())
+        .map(|(coord, root_coord)| {
+            let min = ((coord - root_coord) * scale) as usize;
+            (min, (min + size as usize).max(min + 1))
+        })
+        .collect::<Vec<_>>();
+
+    // number of cells in the range
+    let count = ranges.iter().map(|(min, max)| max - min).product::<usize>();
+
+    // and update all cells in it
+    for i in 0..count {
+        let mut cell_index = 0;
+        let mut remaining = i;
+        let mut stride = 1;
+
+        for (min, max) in ranges.iter() {
+            cell_index += (min + remaining % (max - min)) * stride;
+            remaining /= max - min;
+            stride *= cells;
+        }
+
+        grid[cell_index] = grid[cell_index].max(depth);
+    }
+}
+
+/// gets the number of nodes and chunks a tree will have once it's fully updated for the given targets, starting from an empty tree.
+/// This can be used to allocate exactly enough room for the tree up front.
+/// # Params
+/// * `targets` The target positions to generate the lod around, same as for prepare_update
+/// * `detail` The detail for these targets
+///
+/// returns the number of nodes, and the number of chunks. Every node has a chunk, so these are the same
+pub fn nodes_and_chunks_for<L: LodVec>(targets: &[L], detail: u64) -> (usize, usize) {
+    let mut count = 0;
+
+    // go over all nodes the tree will end up with, starting at the root
+    let mut stack = vec![L::root()];
+
+    while let Some(position) = stack.pop() {
+        count += 1;
+
+        // and it's children, if it will be subdivided
+        if targets.iter().any(|x| x.can_subdivide(position, detail)) {
+            stack.extend((0..L::num_children()).map(|i| position.get_child(i)));
+        }
+    }
+
+    (count, count)
+}
+
+// moves a coordinate by a delta given in chunks at delta_depth, or returns None if it doesn't line up with the grid or goes outside of the tree
+// shallower coordinates move by less, and deeper ones by more
+fn translate_coord(coord: u64, delta: i64, depth: u8, delta_depth: u8) -> Option<u64> {
+    let shift = (depth as i32 - delta_depth as i32).unsigned_abs();
+
+    // the delta at this depth, which is done with i128 so it can't overflow
+    let delta = if delta == 0 {
+        0
+    } else if shift >= 64 {
+        // too far apart to line up, or to stay in the tree
+        return None;
+    } else if depth >= delta_depth {
+        (delta as i128) << shift
+    } else if delta % (1i64 << shift) == 0 {
+        (delta >> shift) as i128
+    } else {
+        return None;
+    };
+
+    // and move the coord, as long as it stays in the tree
+    let moved: u64 = (coord as i128 + delta).try_into().ok()?;
+
+    if depth >= 64 || moved < 1 << depth {
+        Some(moved)
+    } else {
+        None
+    }
+}
+
+// goes over the tree to find which nodes need to change, where can_subdivide decides if a node needs to be subdivided
+// subdivide is called with the index and position of each leaf node that needs to get children,
+// and merge is called with the index of each node that needs to lose it's children, as well as the index of it's first child
+// nodes in or above a frozen region are never merged
+fn plan_update<L: LodVec>(
+    root: L,
+    nodes: &[TreeNode],
+    frozen: &HashSet<L>,
+    can_subdivide: impl Fn(L) -> bool,
+    processing_queue: &mut Vec<QueueContainer<L>>,
+    mut subdivide: impl FnMut(usize, L),
+    mut merge: impl FnMut(usize, usize),
+) {
+    // clear the processing queue from any previous updates
+    processing_queue.clear();
+
+    // add the root node (always at 0, if there is no root we would have returned earlier) to the processing queue
+    processing_queue.push(QueueContainer {
+        position: root,
+        node: 0,
+    });
+
+    // then, traverse the tree, as long as something is inside the queue
+    while let Some(QueueContainer {
+        position: current_position,
+        node: current_node_index,
+    }) = processing_queue.pop()
+    {
+        // fetch the current node
+        let current_node = nodes[current_node_index];
+
+        // wether we can subdivide
+        let can_subdivide = can_subdivide(current_position);
+
+        // if we can subdivide, and the current node does not have children, subdivide the current node
+        if can_subdivide && current_node.children.is_none() {
+            subdivide(current_node_index, current_position);
+        } else if let Some(index) = current_node.children {
+            // otherwise, if we cant subdivide and have children, remove our children
+            if !can_subdivide
+                && !(0..L::num_children())
+                    .into_iter()
+                    .any(|i| nodes[i + index.get()].children.is_some())
+                && !frozen.iter().any(|&region| {
+                    region.contains_child_node(current_position)
+                        || current_position.contains_child_node(region)
+                })
+            {
+                merge(current_node_index, index.get());
+            } else {
+                // queue child nodes for processing if we didn't subdivide or clean up our children
+                for i in 0..L::num_children() {
+                    processing_queue.push(QueueContainer {
+                        position: current_position.get_child(i),
+                        node: index.get() + i,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// builds the node at position and everything below it, for from_leaves
+// returns the chunk for the node, and wether it ended up as a leaf
+fn build_from_leaves<C, L: LodVec>(
+    position: L,
+    remaining_depth: u8,
+    leaf: &mut impl FnMut(L) -> C,
+    merge: &mut impl FnMut(&[C]) -> Option<C>,
+    combine: &mut impl FnMut(&[C]) -> C,
+    chunks: &mut HashMap<L, C>,
+    subdivided: &mut HashSet<L>,
+) -> (C, bool) {
+    // at the bottom, so get the chunk from the grid
+    if remaining_depth == 0 {
+        return (leaf(position), true);
+    }
+
+    // build all children first
+    let children = (0..L::num_children())
+        .map(|i| {
+            build_from_leaves(
+                position.get_child(i),
+                remaining_depth - 1,
+                leaf,
+                merge,
+                combine,
+                chunks,
+                subdivided,
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let all_leaves = children.iter().all(|(_, is_leaf)| *is_leaf);
+    let children = children
+        .into_iter()
+        .map(|(chunk, _)| chunk)
+        .collect::<Vec<_>>();
+
+    // collapse the children if possible
+    if all_leaves {
+        if let Some(chunk) = merge(&children) {
+            return (chunk, true);
+        }
+    }
+
+    // otherwise keep them, and make the chunk for this node from them
+    let chunk = combine(&children);
+
+    for (i, chunk) in children.into_iter().enumerate() {
+        chunks.insert(position.get_child(i), chunk);
+    }
+
+    subdivided.insert(position);
+
+    (chunk, false)
+}
+
+impl<C> Tree<C, QuadVec>
+where
+    C: Sized,
+{
+    /// prepares the tree for an update, same as prepare_update, but with the targets given as raw float coords, from 0 to 1.
+    /// # Params
+    /// * `targets` the x and y coords of the targets
+    /// * `depth` the depth of all targets
+    /// * `detail` The detail for these targets
+    /// * `chunk_creator` function to create a new chunk from a given position
+    ///
+    /// returns wether any update is needed.
+    #[cfg(feature = "ffi")]
+    pub fn prepare_update_xy(
+        &mut self,
+        targets: &[[f64; 2]],
+        depth: u8,
+        detail: u64,
+        chunk_creator: impl FnMut(QuadVec) -> C,
+    ) -> bool {
+        let targets = targets
+            .iter()
+            .map(|&[x, y]| QuadVec::from_float_coords(x, y, depth))
+            .collect::<Vec<_>>();
+
+        self.prepare_update(&targets, detail, chunk_creator)
+    }
+
+    /// gets a chunk from it's raw position, same as get_chunk_from_position
+    #[cfg(feature = "ffi")]
+    #[inline]
+    pub fn get_chunk_xy(&self, x: u64, y: u64, depth: u8) -> Option<&C> {
+        self.get_chunk_from_position(QuadVec::new(x, y, depth))
+    }
+
+    /// gets the chunk of the deepest node that covers a cell at max_depth, which can be a leaf at a lower depth.
+    /// Returns None if the cell is outside of the tree, or the tree is empty
+    /// # Params
+    /// * `cell` the x and y position of the cell
+    /// * `max_depth` the depth the cell is at
+    #[inline]
+    pub fn sample_cell(&self, cell: [u64; 2], max_depth: u8) -> Option<&C> {
+        let node =
+            self.get_deepest_node_index_containing(QuadVec::new(cell[0], cell[1], max_depth))?;
+
+        Some(&self.chunks[self.nodes[node].chunk].chunk)
+    }
+
+    /// builds a tree from a dense grid of 2^depth by 2^depth cells, from the bottom up.
+    /// Groups of 4 leaf nodes are collapsed into a single leaf if merge returns a chunk for them, which allows uniform regions to take up a single chunk.
+    /// Nodes that aren't collapsed get their chunk from combine.
+    /// # Params
+    /// * `depth` the depth of the cells in the tree
+    /// * `width_cells` the width of the grid, this has to be 2^depth
+    /// * `cells` function to get the chunk for the cell at the given x and y
+    /// * `merge` function that gets the 4 children of a node, in child index order, and returns the chunk to replace them with, if any
+    /// * `combine` function that gets the 4 children of a node that isn't collapsed, in child index order, and returns the chunk for that node
+    pub fn from_dense_grid(
+        depth: u8,
+        width_cells: usize,
+        cells: impl Fn([u64; 2]) -> C,
+        merge: impl Fn(&[C; 4]) -> Option<C>,
+        combine: impl Fn(&[C; 4]) -> C,
+    ) -> Self {
+        assert_eq!(
+            width_cells,
+            1 << depth,
+            "grid width doesn't match the depth"
+        );
+
+        Self::from_leaves(
+            depth,
+            |position| cells([position.x, position.y]),
+            |children| merge(children.try_into().unwrap()),
+            |children| combine(children.try_into().unwrap()),
+        )
+    }
+
+    /// moves all chunks in the tree by delta, which is in chunks at delta_depth.
+    /// Shallower chunks are only moved if the delta lines up with their size, and otherwise deleted, same for chunks that end up outside of the tree.
+    /// Deleted chunks, as well as the cache, end up in the chunks to delete. Chunks marked as dirty stay dirty, and frozen regions stay frozen, at their new position.
+    /// An empty tree is left empty.
+    /// The tree is then rebuilt around the moved chunks, with chunk_creator making any missing chunks
+    /// # Params
+    /// * `delta` how far to move the chunks on the x and y axis
+    /// * `delta_depth` the depth of the chunks the delta is given in
+    /// * `chunk_creator` function to create a new chunk from a given position
+    pub fn translate(
+        &mut self,
+        delta: [i64; 2],
+        delta_depth: u8,
+        chunk_creator: impl FnMut(QuadVec) -> C,
+    ) {
+        self.translate_with(
+            |position| {
+                Some(QuadVec::new(
+                    translate_coord(position.x, delta[0], position.depth, delta_depth)?,
+                    translate_coord(position.y, delta[1], position.depth, delta_depth)?,
+                    position.depth,
+                ))
+            },
+            chunk_creator,
+        );
+    }
+}
+
+impl<C> Tree<C, OctVec>
+where
+    C: Sized,
+{
+    /// prepares the tree for an update, same as prepare_update, but with the targets given as raw float coords, from 0 to 1.
+    /// # Params
+    /// * `targets` the x, y and z coords of the targets
+    /// * `depth` the depth of all targets
+    /// * `detail` The detail for these targets
+    /// * `chunk_creator` function to create a new chunk from a given position
+    ///
+    /// returns wether any update is needed.
+    #[cfg(feature = "ffi")]
+    pub fn prepare_update_xyz(
+        &mut self,
+        targets: &[[f64; 3]],
+        depth: u8,
+        detail: u64,
+        chunk_creator: impl FnMut(OctVec) -> C,
+    ) -> bool {
+        let targets = targets
+            .iter()
+            .map(|&[x, y, z]| OctVec::from_float_coords(x, y, z, depth))
+            .collect::<Vec<_>>();
+
+        self.prepare_update(&targets, detail, chunk_creator)
+    }
+
+    /// gets a chunk from it's raw position, same as get_chunk_from_position
+    #[cfg(feature = "ffi")]
+    #[inline]
+    pub fn get_chunk_xyz(&self, x: u64, y: u64, z: u64, depth: u8) -> Option<&C> {
+        self.get_chunk_from_position(OctVec::new(x, y, z, depth))
+    }
+
+    /// gets the chunk of the deepest node that covers a cell at max_depth, which can be a leaf at a lower depth.
+    /// Returns None if the cell is outside of the tree, or the tree is empty
+    /// # Params
+    /// * `cell` the x, y and z position of the cell
+    /// * `max_depth` the depth the cell is at
+    #[inline]
+    pub fn sample_cell(&self, cell: [u64; 3], max_depth: u8) -> Option<&C> {
+        let node = self
+            .get_deepest_node_index_containing(OctVec::new(cell[0], cell[1], cell[2], max_depth))?;
+
+        Some(&self.chunks[self.nodes[node].chunk].chunk)
+    }
+
+    /// builds a tree from a dense grid of 2^depth by 2^depth by 2^depth cells, from the bottom up.
+    /// Groups of 8 leaf nodes are collapsed into a single leaf if merge returns a chunk for them, which allows uniform regions to take up a single chunk.
+    /// Nodes that aren't collapsed get their chunk from combine.
+    /// # Params
+    /// * `depth` the depth of the cells in the tree
+    /// * `width_cells` the width of the grid, this has to be 2^depth
+    /// * `cells` function to get the chunk for the cell at the given x, y and z
+    /// * `merge` function that gets the 8 children of a node, in child index order, and returns the chunk to replace them with, if any
+    /// * `combine` function that gets the 8 children of a node that isn't collapsed, in child index order, and returns the chunk for that node
+    pub fn from_dense_grid(
+        depth: u8,
+        width_cells: usize,
+        cells: impl Fn([u64; 3]) -> C,
+        merge: impl Fn(&[C; 8]) -> Option<C>,
+        combine: impl Fn(&[C; 8]) -> C,
+    ) -> Self {
+        assert_eq!(
+            width_cells,
+            1 << depth,
+            "grid width doesn't match the depth"
+        );
+
+        Self::from_leaves(
+            depth,
+            |position| cells([position.x, position.y, position.z]),
+            |children| merge(children.try_into().unwrap()),
+            |children| combine(children.try_into().unwrap()),
+        )
+    }
+
+    /// moves all chunks in the tree by delta, which is in chunks at delta_depth.
+    /// Shallower chunks are only moved if the delta lines up with their size, and otherwise deleted, same for chunks that end up outside of the tree.
+    /// Deleted chunks, as well as the cache, end up in the chunks to delete. Chunks marked as dirty stay dirty, and frozen regions stay frozen, at their new position.
+    /// An empty tree is left empty.
+    /// The tree is then rebuilt around the moved chunks, with chunk_creator making any missing chunks
+    /// # Params
+    /// * `delta` how far to move the chunks on the x, y and z axis
+    /// * `delta_depth` the depth of the chunks the delta is given in
+    /// * `chunk_creator` function to create a new chunk from a given position
+    pub fn translate(
+        &mut self,
+        delta: [i64; 3],
+        delta_depth: u8,
+        chunk_creator: impl FnMut(OctVec) -> C,
+    ) {
+        self.translate_with(
+            |position| {
+                Some(OctVec::new(
+                    translate_coord(position.x, delta[0], position.depth, delta_depth)?,
+                    translate_coord(position.y, delta[1], position.depth, delta_depth)?,
+                    translate_coord(position.z, delta[2], position.depth, delta_depth)?,
+                    position.depth,
+                ))
+            },
+            chunk_creator,
+        );
+    }
+}
+
+impl<C, L> Default for Tree<C, L>
+where
+    C: Sized,
+    L: LodVec,
+{
+    /// creates a new, empty tree, with no cache
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    struct TestChunk;
+
+    #[test]
+    fn new_tree() {
+        // make a tree
+        let mut tree = Tree::<TestChunk, QuadVec>::new(64);
+
+        // as long as we need to update, do so
+        while tree.prepare_update(&[QuadVec::new(128, 128, 32)], 8, |_| TestChunk {}) {
+            // and actually update
+            tree.do_update();
+        }
+
+        // and move the target
+        while tree.prepare_update(&[QuadVec::new(16, 8, 16)], 8, |_| TestChunk {}) {
+            // and actually update
+            tree.do_update();
+        }
+
+        // get the resulting chunk from a search
+        let found_chunk = tree.get_chunk_from_position(QuadVec::new(16, 8, 16));
+
+        // and find the resulting chunk
+        println!("{:?}", found_chunk.is_some());
+
+        // and make the tree have no items
+        while tree.prepare_update(&[], 8, |_| TestChunk {}) {
+            // and actually update
+            tree.do_update();
+        }
+
+        // and do the same for an octree
+        let mut tree = Tree::<TestChunk, OctVec>::new(64);
+
+        // as long as we need to update, do so
+        while tree.prepare_update(&[OctVec::new(128, 128, 128, 32)], 8, |_| TestChunk {}) {
+            // and actually update
+            tree.do_update();
+        }
+
+        // and move the target
+        while tree.prepare_update(&[OctVec::new(16, 8, 32, 16)], 8, |_| TestChunk {}) {
+            // and actually update
+            tree.do_update();
+        }
+
+        // and make the tree have no items
+        while tree.prepare_update(&[], 8, |_| TestChunk {}) {
+            // and actually update
+            tree.do_update();
+        }
+    }
+
+    #[test]
+    fn new_line_tree() {
+        // make a binary tree
+        let mut tree = Tree::<TestChunk, LineVec>::new(64);
+
+        // as long as we need to update, do so
+        while tree.prepare_update(&[LineVec::new(128, 32)], 8, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // the chunk at the target is there
+        assert!(tree
+            .get_chunk_from_position(LineVec::new(128, 32))
+            .is_some());
+        assert!(tree.is_complete_partition());
+
+        // and move the target
+        while tree.prepare_update(&[LineVec::new(16, 16)], 8, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert!(tree.get_chunk_from_position(LineVec::new(16, 16)).is_some());
+        assert!(tree
+            .get_chunk_from_position(LineVec::new(128, 32))
+            .is_none());
+
+        // and make the tree have no items
+        while tree.prepare_update(&[], 8, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.get_num_chunks(), 1);
+    }
+
+    #[test]
+    fn new_4d_tree() {
+        let mut tree = Tree::<TestChunk, NVec<4>>::new(64);
+
+        while tree.prepare_update(&[NVec::new([3, 1, 4, 1], 3)], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // the chunk at the target is there, with all space still covered
+        assert!(tree
+            .get_chunk_from_position(NVec::new([3, 1, 4, 1], 3))
+            .is_some());
+        assert!(tree.is_complete_partition());
+
+        // and make the tree have no items
+        while tree.prepare_update(&[], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.get_num_chunks(), 1);
+    }
+
+    #[test]
+    fn complete_update() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(4);
+
+        // move the target a few times, so chunks are added, removed, and deleted from the cache
+        for target in [
+            QuadVec::new(1, 2, 3),
+            QuadVec::new(7, 7, 3),
+            QuadVec::new(0, 5, 3),
+        ] {
+            while tree.prepare_update(&[target], 1, |_| TestChunk {}) {
+                tree.do_update();
+
+                // the (de)activated chunks are still there after do_update, except when adding the root
+                assert!(
+                    tree.get_num_chunks() == 1
+                        || tree.get_num_chunks_to_activate() + tree.get_num_chunks_to_deactivate()
+                            > 0
+                );
+
+                tree.complete_update();
+
+                // and gone after the full cycle
+                assert_eq!(tree.get_num_chunks_to_add(), 0);
+                assert_eq!(tree.get_num_chunks_to_remove(), 0);
+                assert_eq!(tree.get_num_chunks_to_activate(), 0);
+                assert_eq!(tree.get_num_chunks_to_deactivate(), 0);
+                assert_eq!(tree.get_num_chunks_to_delete(), 0);
+            }
+        }
+
+        // once only the root is left, there are no unused nodes
+        while tree.prepare_update(&[], 1, |_| TestChunk {}) {
+            tree.do_update();
+            tree.complete_update();
+        }
+
+        assert_eq!(tree.get_num_chunks(), 1);
+        assert_eq!(tree.nodes.len(), 1);
+        assert!(tree.free_list.is_empty());
+    }
+
+    #[test]
+    fn with_capacity() {
+        let mut tree = Tree::<TestChunk, QuadVec>::with_capacity(256, 512);
+
+        // nothing in it yet
+        assert_eq!(tree.get_num_chunks(), 0);
+
+        let chunk_capacity = tree.chunks.capacity();
+        let node_capacity = tree.nodes.capacity();
+
+        assert!(chunk_capacity >= 256);
+        assert!(node_capacity >= 512);
+
+        // move the target a bit, so there are removed nodes as well
+        for target in [QuadVec::new(20, 11, 5), QuadVec::new(3, 28, 5)] {
+            while tree.prepare_update(&[target], 1, |_| TestChunk {}) {
+                tree.do_update();
+                tree.complete_update();
+            }
+        }
+
+        // as it stays below the capacity, nothing had to be reallocated
+        assert!(tree.get_num_chunks() > 1 && tree.get_num_chunks() < 256);
+        assert!(tree.nodes.len() < 512);
+        assert_eq!(tree.chunks.capacity(), chunk_capacity);
+        assert_eq!(tree.nodes.capacity(), node_capacity);
+    }
+
+    #[test]
+    fn with_capacity_zero_and_large() {
+        let mut empty = Tree::<QuadVec, QuadVec>::with_capacity(0, 0);
+        let mut large = Tree::<QuadVec, QuadVec>::with_capacity(1 << 16, 1 << 17);
+
+        // both can be updated, even the one without any room yet
+        for tree in [&mut empty, &mut large] {
+            for target in [QuadVec::new(20, 11, 5), QuadVec::new(3, 28, 5)] {
+                while tree.prepare_update(&[target], 2, |position| position) {
+                    tree.do_update();
+                    tree.complete_update();
+                }
+            }
+
+            assert!(tree.get_num_chunks() > 1);
+            assert!(tree.validate());
+            assert!(tree.is_complete_partition());
+        }
+
+        // and end up the same
+        assert_eq!(empty.sorted_positions(), large.sorted_positions());
+    }
+
+    #[test]
+    fn update_independent_of_capacity() {
+        let targets = [
+            QuadVec::new(5, 9, 4),
+            QuadVec::new(12, 2, 4),
+            QuadVec::new(0, 15, 4),
+            QuadVec::new(6, 6, 4),
+        ];
+
+        // updates the tree until it's done for each target in turn
+        fn run(tree: &mut Tree<QuadVec, QuadVec>, targets: &[QuadVec]) {
+            for target in targets {
+                while tree.prepare_update(&[*target], 2, |position| position) {
+                    tree.do_update();
+                    tree.complete_update();
+                }
+            }
+        }
+
+        let mut small = Tree::new(0);
+        let mut large = Tree::new(512);
+
+        // and one with a different history, so the free list and chunk indices are all over the place
+        let mut shuffled = Tree::new(16);
+        run(
+            &mut shuffled,
+            &[QuadVec::new(15, 0, 4), QuadVec::new(8, 8, 4)],
+        );
+
+        run(&mut small, &targets);
+        run(&mut large, &targets);
+        run(&mut shuffled, &targets);
+
+        // all end up as the same tree
+        assert_eq!(small.sorted_positions(), large.sorted_positions());
+        assert_eq!(small.sorted_positions(), shuffled.sorted_positions());
+        assert_eq!(small.diff(&large), TreeDiff::default());
+        assert_eq!(small.diff(&shuffled), TreeDiff::default());
+
+        // with every chunk in the right spot
+        for tree in [&small, &large, &shuffled] {
+            for position in tree.sorted_positions() {
+                assert_eq!(tree.get_chunk_from_position(position), Some(&position));
+            }
+        }
+    }
+
+    #[test]
+    fn take_and_return_chunks_to_add() {
+        #[derive(Default)]
+        struct Chunk {
+            value: u64,
+        }
+
+        // the value each chunk should get
+        fn value(position: QuadVec) -> u64 {
+            position.x * 1000 + position.y * 10 + position.depth as u64
+        }
+
+        let mut tree = Tree::<Chunk, QuadVec>::new(64);
+
+        while tree.prepare_update(&[QuadVec::new(5, 9, 4)], 2, |_| Chunk::default()) {
+            // take out the chunks
+            let mut chunks = tree.take_chunks_to_add();
+
+            // process them on a different thread, in a different order
+            chunks = std::thread::spawn(move || {
+                for (position, chunk) in chunks.iter_mut().rev() {
+                    chunk.value = value(*position);
+                }
+                chunks
+            })
+            .join()
+            .unwrap();
+
+            // and put them back, in any order
+            chunks.reverse();
+            tree.return_chunks_to_add(chunks);
+
+            tree.do_update();
+        }
+
+        // all chunks should be processed, and end up in the right spot
+        assert!(tree.get_num_chunks() > 1);
+
+        for (chunk, position) in tree.iter_chunks_and_positions() {
+            assert_eq!(chunk.value, value(position));
+        }
+
+        for position in tree.iter_chunk_positions().collect::<Vec<_>>() {
+            assert_eq!(
+                tree.get_chunk_from_position(position).unwrap().value,
+                value(position)
+            );
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "returned chunks don't match the taken chunks")]
+    fn return_chunks_to_add_mismatch() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // go past the root, so there are multiple chunks to add
+        tree.prepare_update(&[QuadVec::new(1, 1, 2)], 0, |position| position);
+        tree.do_update();
+        tree.prepare_update(&[QuadVec::new(1, 1, 2)], 0, |position| position);
+
+        let mut chunks = tree.take_chunks_to_add();
+        assert!(chunks.len() > 1);
+
+        // a chunk at a position that wasn't taken
+        chunks[0].0 = chunks[1].0;
+        tree.return_chunks_to_add(chunks);
+    }
+
+    #[test]
+    #[should_panic(expected = "chunks to add were already taken")]
+    fn take_chunks_to_add_twice() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        tree.prepare_update(&[QuadVec::new(1, 1, 2)], 0, |position| position);
+
+        let chunks = tree.take_chunks_to_add();
+        assert!(!chunks.is_empty());
+
+        // taking them again would lose the first batch
+        tree.take_chunks_to_add();
+    }
+
+    #[test]
+    #[should_panic(expected = "chunks to add were taken and not returned before do_update")]
+    fn do_update_with_taken_chunks() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        tree.prepare_update(&[QuadVec::new(1, 1, 2)], 0, |position| position);
+        tree.do_update();
+        tree.prepare_update(&[QuadVec::new(1, 1, 2)], 0, |position| position);
+
+        let chunks = tree.take_chunks_to_add();
+        assert!(!chunks.is_empty());
+
+        // the parents would be subdivided without their children
+        tree.do_update();
+    }
+
+    #[test]
+    fn prepare_update_structural() {
+        // the value each chunk should get
+        fn value(position: QuadVec) -> u64 {
+            position.x * 1000 + position.y * 10 + position.depth as u64
+        }
+
+        let mut tree = Tree::<u64, QuadVec>::new(64);
+        let mut expected = Tree::<u64, QuadVec>::new(64);
+
+        // move the target, so some chunks come from the cache
+        for target in [
+            QuadVec::new(5, 9, 4),
+            QuadVec::new(12, 2, 4),
+            QuadVec::new(5, 9, 4),
+        ] {
+            while tree.prepare_update_structural(&[target], 2) {
+                // positions that still need a chunk
+                let to_fill = (0..tree.get_num_chunks_to_fill())
+                    .filter(|&i| tree.get_chunk_to_fill(i).is_none())
+                    .map(|i| (i, tree.get_position_of_chunk_to_fill(i)))
+                    .collect::<Vec<_>>();
+
+                // make them on a different thread, in a different order
+                let (sender, receiver) = std::sync::mpsc::channel();
+
+                let worker = std::thread::spawn(move || {
+                    for (i, position) in to_fill.into_iter().rev() {
+                        sender.send((i, value(position))).unwrap();
+                    }
+                });
+
+                // and fill them in as they come in
+                for (i, chunk) in receiver {
+                    tree.fill_chunk_to_add(i, chunk);
+                }
+
+                worker.join().unwrap();
+
+                tree.do_update();
+                tree.complete_update();
+            }
+
+            while expected.prepare_update(&[target], 2, value) {
+                expected.do_update();
+                expected.complete_update();
+            }
+        }
+
+        // same structure as a normal update
+        assert!(tree.get_num_chunks() > 1);
+        assert_eq!(tree.diff(&expected), TreeDiff::default());
+
+        // and all chunks ended up in the right spot
+        for (chunk, position) in tree.iter_chunks_and_positions() {
+            assert_eq!(*chunk, value(position));
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "rayon")]
+    fn prepare_update_par() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(16);
+        let mut expected = Tree::<QuadVec, QuadVec>::new(16);
+
+        // move the target, so some chunks come from the cache
+        for target in [
+            QuadVec::new(5, 9, 4),
+            QuadVec::new(12, 2, 4),
+            QuadVec::new(5, 9, 4),
+        ] {
+            loop {
+                let needs_update = tree.prepare_update_par(&[target], 2, |position| position);
+
+                assert_eq!(
+                    expected.prepare_update(&[target], 2, |position| position),
+                    needs_update
+                );
+
+                // same chunks to add, in the same order
+                let positions = |tree: &mut Tree<QuadVec, QuadVec>| {
+                    tree.iter_chunks_to_add_positions().collect::<Vec<_>>()
+                };
+
+                assert_eq!(positions(&mut tree), positions(&mut expected));
+                assert!(tree
+                    .get_chunks_to_add_slice()
+                    .iter()
+                    .all(|container| container.position == container.chunk));
+
+                if !needs_update {
+                    break;
+                }
+
+                tree.do_update();
+                tree.complete_update();
+                expected.do_update();
+                expected.complete_update();
+            }
+        }
+
+        assert_eq!(tree.diff(&expected), TreeDiff::default());
+    }
+
+    #[test]
+    #[should_panic(expected = "not all chunks to add were filled in")]
+    fn prepare_update_structural_unfilled() {
+        let mut tree = Tree::<u64, QuadVec>::new(0);
+
+        // the root isn't filled in
+        tree.prepare_update_structural(&[QuadVec::new(0, 0, 2)], 0);
+        tree.do_update();
+    }
+
+    #[test]
+    fn prepare_update_structural_unfilled_keeps_tree() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |position| position);
+        tree.do_update();
+        tree.prepare_update_structural(&[QuadVec::new(0, 0, 2)], 0);
+
+        // fill in all but the last chunk
+        let num_chunks = tree.get_num_chunks_to_fill();
+        assert!(num_chunks > 1);
+
+        for index in 0..num_chunks - 1 {
+            let position = tree.get_position_of_chunk_to_fill(index);
+            tree.fill_chunk_to_add(index, position);
+        }
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| tree.do_update()));
+        assert!(result.is_err());
+
+        // nothing was moved yet, so filling in the last one still works
+        assert_eq!(tree.get_num_chunks_to_fill(), num_chunks);
+
+        let position = tree.get_position_of_chunk_to_fill(num_chunks - 1);
+        tree.fill_chunk_to_add(num_chunks - 1, position);
+        tree.do_update();
+
+        assert!(tree.validate());
+        assert_eq!(tree.get_num_chunks(), 1 + num_chunks);
+        assert!(tree
+            .iter_chunks_and_positions()
+            .all(|(chunk, position)| *chunk == position));
+    }
+
+    #[test]
+    fn next_chunk_to_generate() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+        let targets = [QuadVec::new(3, 12, 4), QuadVec::new(14, 5, 4)];
+
+        // only the root to start with
+        assert!(tree.prepare_update_structural(&targets, 1));
+        assert_eq!(
+            tree.next_chunk_to_generate(&targets),
+            Some((0, QuadVec::root()))
+        );
+        assert_eq!(tree.next_chunk_to_generate(&targets), None);
+
+        let distance = |position: QuadVec| {
+            targets
+                .iter()
+                .map(|target| center_distance_squared(position, *target))
+                .fold(f64::INFINITY, f64::min)
+        };
+
+        let mut updates = 0;
+
+        loop {
+            // fill in one chunk up front, so it's skipped
+            if tree.get_num_chunks_to_fill() > 0 {
+                let position = tree.get_position_of_chunk_to_fill(0);
+                tree.fill_chunk_to_add(0, position);
+            }
+
+            // pull all chunks one at a time
+            let mut last = 0.0;
+
+            while let Some((i, position)) = tree.next_chunk_to_generate(&targets) {
+                // the closest come first
+                assert!(distance(position) >= last);
+                last = distance(position);
+
+                assert_eq!(tree.get_position_of_chunk_to_fill(i), position);
+                assert!(tree.get_chunk_to_fill(i).is_none());
+
+                tree.fill_chunk_to_add(i, position);
+            }
+
+            // so everything is filled in
+            assert!((0..tree.get_num_chunks_to_fill()).all(|i| tree.get_chunk_to_fill(i).is_some()));
+
+            tree.do_update();
+            tree.complete_update();
+            updates += 1;
+
+            if !tree.prepare_update_structural(&targets, 1) {
+                break;
+            }
+        }
+
+        assert!(updates > 2);
+        assert!(tree
+            .iter_chunks_ordered()
+            .all(|(position, chunk)| position == *chunk));
+    }
+
+    #[test]
+    fn get_parent_of_chunk_to_add() {
+        struct Chunk {
+            depth: u8,
+        }
+
+        let mut tree = Tree::<Chunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(3, 6, 4)], 1, |_| Chunk { depth: 0 }) {
+            // set the depth based on the parent
+            for i in 0..tree.get_num_chunks_to_add() {
+                let depth = tree
+                    .get_parent_of_chunk_to_add(i)
+                    .map_or(0, |parent| parent.depth + 1);
+
+                tree.get_chunk_to_add_mut(i).depth = depth;
+            }
+
+            tree.do_update();
+        }
+
+        // and all chunks got the right depth from their parent
+        assert!(tree.get_num_chunks() > 1);
+
+        for (chunk, position) in tree.iter_chunks_and_positions() {
+            assert_eq!(chunk.depth, position.depth);
+        }
+    }
+
+    #[test]
+    fn chunks_to_add_parents_in_tree() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // a target that needs many levels, so each update adds a level below the previous one
+        let mut num_updates = 0;
+
+        while tree.prepare_update(&[QuadVec::new(9, 14, 4)], 2, |position| position) {
+            let to_add = (0..tree.get_num_chunks_to_add())
+                .map(|index| tree.get_position_of_chunk_to_add(index))
+                .collect::<HashSet<_>>();
+
+            for index in 0..tree.get_num_chunks_to_add() {
+                let position = tree.get_position_of_chunk_to_add(index);
+
+                // the root has no parent
+                let parent = match position.parent() {
+                    Some(parent) => parent,
+                    None => continue,
+                };
+
+                // the parent is already in the tree, and isn't being added itself
+                assert_eq!(tree.get_parent_of_chunk_to_add(index), Some(&parent));
+                assert!(!to_add.contains(&parent));
+            }
+
+            tree.do_update();
+            num_updates += 1;
+        }
+
+        assert!(num_updates > 2);
+    }
+
+    #[test]
+    fn iter_chunks_to_add_with_parent_pos() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // the root has no parent
+        tree.prepare_update(&[QuadVec::new(5, 2, 3)], 1, |position| position);
+        assert_eq!(tree.iter_chunks_to_add_with_parent_pos().count(), 0);
+        tree.do_update();
+
+        while tree.prepare_update(&[QuadVec::new(5, 2, 3)], 1, |position| position) {
+            // every chunk to add is there, with it's parent
+            assert_eq!(
+                tree.iter_chunks_to_add_with_parent_pos().count(),
+                tree.get_num_chunks_to_add()
+            );
+
+            for (position, parent, chunk) in tree.iter_chunks_to_add_with_parent_pos() {
+                assert_eq!(position, *chunk);
+                assert_eq!(position.parent(), Some(parent));
+                assert!(tree.contains_position(parent));
+            }
+
+            tree.do_update();
+        }
+
+        // same for a tree that only covers part of the space
+        let mut tree = Tree::<QuadVec, QuadVec>::with_root(QuadVec::new(1, 0, 1));
+
+        tree.prepare_update(&[QuadVec::new(5, 2, 3)], 1, |position| position);
+        assert_eq!(tree.get_num_chunks_to_add(), 1);
+        assert_eq!(tree.iter_chunks_to_add_with_parent_pos().count(), 0);
+    }
+
+    #[test]
+    fn iter_remove_with_target() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(13, 2, 4)], 1, |position| position) {
+            tree.do_update();
+        }
+
+        // move the target away, so chunks get merged
+        let mut merged = 0;
+
+        while tree.prepare_update(&[QuadVec::new(0, 15, 4)], 1, |position| position) {
+            assert_eq!(
+                tree.iter_remove_with_target().count(),
+                tree.get_num_chunks_to_remove()
+            );
+
+            for (child, parent) in tree.iter_remove_with_target() {
+                // the parent is the one above the child in the tree
+                assert_eq!(child.parent(), Some(*parent));
+
+                // and is going to be activated
+                assert!((0..tree.get_num_chunks_to_activate())
+                    .any(|i| tree.get_position_of_chunk_to_activate(i) == *parent));
+
+                merged += 1;
+            }
+
+            tree.do_update();
+        }
+
+        assert!(merged > 0);
+    }
+
+    #[test]
+    fn pending_update() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // nothing prepared yet
+        assert!(tree.pending_update().is_empty());
+
+        for target in [QuadVec::new(13, 2, 4), QuadVec::new(0, 15, 4)] {
+            while tree.prepare_update(&[target], 1, |position| position) {
+                let diff = tree.pending_update();
+
+                // same as the getters
+                assert!(!diff.is_empty());
+                assert_eq!(diff.num_to_add(), tree.get_num_chunks_to_add());
+                assert_eq!(diff.num_to_remove(), tree.get_num_chunks_to_remove());
+                assert_eq!(diff.num_to_activate(), tree.get_num_chunks_to_activate());
+                assert_eq!(
+                    diff.num_to_deactivate(),
+                    tree.get_num_chunks_to_deactivate()
+                );
+
+                assert_eq!(diff.to_add().len(), diff.num_to_add());
+                assert_eq!(diff.to_remove().count(), diff.num_to_remove());
+                assert_eq!(diff.to_activate().count(), diff.num_to_activate());
+                assert_eq!(diff.to_deactivate().count(), diff.num_to_deactivate());
+
+                // with the right chunks
+                assert!(diff
+                    .to_add()
+                    .iter()
+                    .all(|container| container.position == container.chunk));
+                assert!(diff
+                    .to_remove()
+                    .chain(diff.to_activate())
+                    .chain(diff.to_deactivate())
+                    .all(|(position, chunk)| position == *chunk));
+
+                tree.do_update();
+            }
+        }
+    }
+
+    #[test]
+    fn predict_update() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        // the tree is empty, so only the root would be added
+        assert_eq!(
+            tree.predict_update(&[QuadVec::new(2, 5, 3)], 1).to_add,
+            vec![QuadVec::root()]
+        );
+
+        while tree.prepare_update(&[QuadVec::new(2, 5, 3)], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // move the target a few times
+        for target in [
+            QuadVec::new(7, 0, 3),
+            QuadVec::new(7, 0, 3),
+            QuadVec::new(0, 3, 3),
+        ] {
+            let plan = tree.predict_update(&[target], 1);
+
+            tree.prepare_update(&[target], 1, |_| TestChunk {});
+
+            // and the prediction should be what happened
+            assert_eq!(
+                plan.to_add,
+                (0..tree.get_num_chunks_to_add())
+                    .map(|i| tree.get_position_of_chunk_to_add(i))
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                plan.to_remove,
+                (0..tree.get_num_chunks_to_remove())
+                    .map(|i| tree.get_position_of_chunk_to_remove(i))
+                    .collect::<Vec<_>>()
+            );
+            assert!(!plan.to_add.is_empty() || !plan.to_remove.is_empty());
+
+            tree.do_update();
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn tracing_events() {
+        use std::sync::{Arc, Mutex};
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata};
+
+        // all fields of an event
+        #[derive(Default)]
+        struct Fields(HashMap<String, String>);
+
+        impl Visit for Fields {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .insert(field.name().to_string(), format!("{:?}", value));
+            }
+        }
+
+        // subscriber that records all events
+        struct Recorder(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, event: &Event<'_>) {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                self.0.lock().unwrap().push(fields.0);
+            }
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+
+        tracing::subscriber::with_default(Recorder(events.clone()), || {
+            let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+            // add the root, and subdivide it once
+            for _ in 0..2 {
+                tree.prepare_update(&[QuadVec::new(1, 1, 2)], 3, |_| TestChunk {});
+                tree.do_update();
+            }
+        });
+
+        let events = events.lock().unwrap();
+
+        // both updates had an event for prepare_update and do_update
+        assert_eq!(events.len(), 4);
+
+        // the root is added
+        assert_eq!(events[0]["message"], "prepared update");
+        assert_eq!(events[0]["targets"], "1");
+        assert_eq!(events[0]["detail"], "3");
+        assert_eq!(events[0]["to_add"], "1");
+        assert_eq!(events[1]["message"], "did update");
+        assert_eq!(events[1]["chunks"], "1");
+
+        // and subdivided
+        assert_eq!(events[2]["to_add"], "4");
+        assert_eq!(events[2]["to_remove"], "0");
+        assert_eq!(events[2]["to_activate"], "0");
+        assert_eq!(events[2]["to_deactivate"], "1");
+        assert_eq!(events[3]["added"], "4");
+        assert_eq!(events[3]["chunks"], "5");
+        assert!(events[3].contains_key("elapsed_us"));
+    }
+
+    #[test]
+    fn get_chunk_from_position() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // nothing in an empty tree
+        assert_eq!(tree.get_chunk_from_position(QuadVec::root()), None);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // the root and other nodes that are subdivided still have their chunk
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::root()),
+            Some(&QuadVec::root())
+        );
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(0, 0, 1)),
+            Some(&QuadVec::new(0, 0, 1))
+        );
+
+        // as do leaves
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(1, 1, 2)),
+            Some(&QuadVec::new(1, 1, 2))
+        );
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(1, 0, 1)),
+            Some(&QuadVec::new(1, 0, 1))
+        );
+
+        // but not positions below the leaves
+        assert_eq!(tree.get_chunk_from_position(QuadVec::new(2, 0, 2)), None);
+        assert_eq!(tree.get_chunk_from_position(QuadVec::new(0, 0, 3)), None);
+
+        // and the mutable version finds the same chunks
+        *tree
+            .get_chunk_from_position_mut(QuadVec::new(0, 0, 1))
+            .unwrap() = QuadVec::new(5, 5, 5);
+
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(0, 0, 1)),
+            Some(&QuadVec::new(5, 5, 5))
+        );
+        assert!(tree
+            .get_chunk_from_position_mut(QuadVec::new(3, 3, 3))
+            .is_none());
+    }
+
+    #[test]
+    fn cached_depth() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(16);
+
+        // move the target around, so nodes are freed and reused a lot
+        for target in [
+            QuadVec::new(40, 3, 6),
+            QuadVec::new(1, 60, 6),
+            QuadVec::new(33, 33, 6),
+            QuadVec::new(0, 0, 3),
+        ] {
+            while tree.prepare_update(&[target], 1, |position| position) {
+                tree.do_update();
+                tree.complete_update();
+            }
+
+            // the cached depth is the same as the depth found by going down from the root
+            let mut stack = vec![(0, 0)];
+
+            while let Some((index, depth)) = stack.pop() {
+                assert_eq!(tree.nodes[index].depth, depth);
+                assert_eq!(tree.chunks[tree.nodes[index].chunk].position.depth, depth);
+
+                if let Some(children) = tree.children_of(index) {
+                    stack.extend(children.map(|child| (child, depth + 1)));
+                }
+            }
+
+            // and the histogram matches the positions
+            let histogram = tree.depth_histogram();
+
+            assert_eq!(histogram.len(), tree.num_depth_levels());
+            assert_eq!(histogram.iter().sum::<usize>(), tree.get_num_chunks());
+
+            for (depth, count) in histogram.into_iter().enumerate() {
+                assert_eq!(
+                    tree.iter_chunks_ordered()
+                        .filter(|(position, _)| position.depth as usize == depth)
+                        .count(),
+                    count
+                );
+            }
+        }
+
+        // also for trees that aren't made with updates
+        let tree =
+            Tree::<QuadVec, QuadVec>::decode_topology(&tree.encode_topology(), |position| position)
+                .unwrap();
+
+        assert!(tree
+            .chunks
+            .iter()
+            .all(|container| tree.nodes[container.index].depth == container.position.depth));
+    }
+
+    #[test]
+    fn children_of() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // nothing in an empty tree
+        assert_eq!(tree.children_of(0), None);
+
+        while tree.prepare_update(&[QuadVec::new(6, 1, 3)], 1, |position| position) {
+            tree.do_update();
+        }
+
+        // walk down from the root
+        let mut stack = vec![(0, QuadVec::root())];
+        let mut visited = 0;
+
+        while let Some((index, position)) = stack.pop() {
+            // the node is at the position we expect
+            assert_eq!(tree.chunks[tree.nodes[index].chunk].position, position);
+            assert_eq!(tree.get_chunk_by_node_index(index), Some(&position));
+            visited += 1;
+
+            // and so are it's children
+            if let Some(children) = tree.children_of(index) {
+                assert_eq!(children.len(), 4);
+
+                for (i, child) in children.enumerate() {
+                    stack.push((child, position.get_child(i)));
+                }
+            } else {
+                assert!(tree
+                    .get_chunk_from_position(position.get_child(0))
+                    .is_none());
+            }
+        }
+
+        assert_eq!(visited, tree.get_num_chunks());
+        assert_eq!(tree.children_of(100000), None);
+    }
+
+    #[test]
+    fn contains_position() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        assert!(!tree.contains_position(QuadVec::root()));
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // leaves
+        assert!(tree.contains_position(QuadVec::new(1, 1, 2)));
+        assert!(tree.contains_position(QuadVec::new(1, 0, 1)));
+
+        // interior nodes
+        assert!(tree.contains_position(QuadVec::root()));
+        assert!(tree.contains_position(QuadVec::new(0, 0, 1)));
+
+        // and positions that aren't there
+        assert!(!tree.contains_position(QuadVec::new(2, 0, 2)));
+        assert!(!tree.contains_position(QuadVec::new(0, 0, 3)));
+    }
+
+    #[test]
+    fn get_chunk_by_node_index() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // cache the handles
+        let kept = tree.node_index_of(QuadVec::new(1, 0, 1)).unwrap();
+        let removed = tree.node_index_of(QuadVec::new(1, 1, 3)).unwrap();
+
+        assert_eq!(
+            tree.get_chunk_by_node_index(kept),
+            Some(&QuadVec::new(1, 0, 1))
+        );
+        assert_eq!(tree.node_index_of(QuadVec::new(0, 0, 4)), None);
+
+        // move the target, so the lower left corner gets merged
+        while tree.prepare_update(&[QuadVec::new(7, 7, 3)], 0, |position| position) {
+            tree.do_update();
+
+            // the handle outside of the merged region stays valid
+            assert_eq!(
+                tree.get_chunk_by_node_index(kept),
+                Some(&QuadVec::new(1, 0, 1))
+            );
+        }
+
+        // unless it was removed, then it's either gone, or reused for another node
+        assert_ne!(
+            tree.get_chunk_by_node_index(removed),
+            Some(&QuadVec::new(1, 1, 3))
+        );
+        assert!(tree
+            .get_chunk_by_node_index(removed)
+            .map_or(true, |chunk| tree.node_index_of(*chunk) == Some(removed)));
+
+        // and out of range indices have nothing
+        assert_eq!(tree.get_chunk_by_node_index(100000), None);
+    }
+
+    #[test]
+    fn find_chunk() {
+        let mut tree = Tree::<u64, QuadVec>::new(0);
+
+        // nothing to find
+        assert!(tree.find_chunk(|_, _| true).is_none());
+
+        // tag every chunk with it's depth
+        while tree.prepare_update(&[QuadVec::new(5, 9, 4)], 1, |position| {
+            position.depth as u64
+        }) {
+            tree.do_update();
+        }
+
+        // count how often the predicate is called
+        let mut calls = 0;
+        let (position, chunk) = tree
+            .find_chunk(|_, chunk| {
+                calls += 1;
+                *chunk == 2
+            })
+            .unwrap();
+
+        assert_eq!(position.depth, 2);
+        assert_eq!(*chunk, 2);
+
+        // it stopped at the first match, which is the first chunk with the tag
+        let first = tree
+            .chunks
+            .iter()
+            .position(|container| container.chunk == 2);
+        assert_eq!(Some(calls - 1), first);
+        assert!(calls < tree.get_num_chunks());
+
+        // nothing matches, so everything is checked
+        calls = 0;
+        assert!(tree
+            .find_chunk(|_, _| {
+                calls += 1;
+                false
+            })
+            .is_none());
+        assert_eq!(calls, tree.get_num_chunks());
+
+        // and change the chunk that's found
+        let target = QuadVec::new(5, 9, 4);
+        let (position, chunk) = tree
+            .find_chunk_mut(|position, _| position == target)
+            .unwrap();
+
+        assert_eq!(position, target);
+        *chunk = 100;
+
+        assert_eq!(tree.get_chunk_from_position(target), Some(&100));
+    }
+
+    #[test]
+    fn sibling_chunks() {
+        let mut tree = Tree::<OctVec, OctVec>::new(0);
+
+        while tree.prepare_update(&[OctVec::new(1, 2, 3, 2)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        let position = OctVec::new(1, 2, 3, 2);
+        let siblings = tree.sibling_chunks(position).unwrap();
+
+        // all other children of the parent
+        assert_eq!(siblings.len(), OctVec::num_children() - 1);
+
+        for (sibling, chunk) in siblings {
+            assert_ne!(sibling, position);
+            assert_eq!(*chunk, sibling);
+            assert!(OctVec::new(0, 1, 1, 1).contains_child_node(sibling));
+        }
+
+        // and the root and things not in the tree don't have any
+        assert!(tree.sibling_chunks(OctVec::root()).is_none());
+        assert!(tree.sibling_chunks(OctVec::new(7, 7, 7, 3)).is_none());
+    }
+
+    #[test]
+    fn parent_chunk() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(6, 1, 3)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // the parent of a leaf
+        assert_eq!(
+            tree.parent_chunk(QuadVec::new(6, 1, 3)),
+            Some(&QuadVec::new(3, 0, 2))
+        );
+
+        *tree.parent_chunk_mut(QuadVec::new(3, 0, 2)).unwrap() = QuadVec::new(9, 9, 9);
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(1, 0, 1)),
+            Some(&QuadVec::new(9, 9, 9))
+        );
+
+        // and the root doesn't have a parent
+        assert_eq!(tree.parent_chunk(QuadVec::root()), None);
+        assert_eq!(tree.parent_chunk_mut(QuadVec::root()), None);
+
+        // same for things not in the tree
+        assert_eq!(tree.parent_chunk(QuadVec::new(0, 0, 3)), None);
+    }
+
+    #[test]
+    fn propagate_up() {
+        let mut tree = Tree::<u32, OctVec>::new(0);
+
+        while tree.prepare_update(&[OctVec::new(3, 5, 6, 3)], 0, |_| 0) {
+            tree.do_update();
+        }
+
+        tree.propagate_up(OctVec::new(3, 5, 6, 3), |counter| *counter += 1);
+
+        // all nodes above it got incremented once
+        for (counter, position) in tree.iter_chunks_and_positions() {
+            let expected = position.contains_child_node(OctVec::new(3, 5, 6, 3)) as u32;
+            assert_eq!(*counter, expected);
+        }
+
+        // and the order is from the node up, so this sets each chunk to it's depth
+        let mut depth = 4;
+        tree.propagate_up(OctVec::new(3, 5, 6, 3), |counter| {
+            depth -= 1;
+            *counter = depth;
+        });
+
+        assert_eq!(depth, 0);
+        assert_eq!(
+            tree.get_chunk_from_position(OctVec::new(1, 2, 3, 2)),
+            Some(&2)
+        );
+
+        // nothing happens for positions not in the tree
+        tree.propagate_up(OctVec::new(0, 0, 0, 3), |_| panic!("not in the tree"));
+    }
+
+    #[test]
+    fn swap_chunks() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // chunks that know where they were made
+        while tree.prepare_update(&[QuadVec::new(2, 1, 2)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        let a = QuadVec::new(1, 1, 1);
+        let b = QuadVec::new(3, 0, 2);
+
+        assert!(tree.swap_chunks(a, b));
+
+        // the data is swapped
+        assert_eq!(tree.get_chunk_from_position(a), Some(&b));
+        assert_eq!(tree.get_chunk_from_position(b), Some(&a));
+
+        // but the positions didn't change
+        assert_eq!(
+            tree.iter_chunk_positions()
+                .filter(|position| *position == a || *position == b)
+                .count(),
+            2
+        );
+
+        // and things that aren't in the tree can't be swapped
+        assert!(!tree.swap_chunks(a, QuadVec::new(3, 3, 2)));
+        assert_eq!(tree.get_chunk_from_position(a), Some(&b));
+    }
+
+    #[test]
+    fn set_detail() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+        let target = QuadVec::new(21, 40, 6);
+
+        while tree.prepare_update(&[target], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // counts the leaves at the depth of the target
+        fn deepest_leaves(tree: &Tree<TestChunk, QuadVec>) -> usize {
+            tree.iter_chunks_ordered()
+                .filter(|(position, _)| position.depth == 6)
+                .count()
+        }
+
+        let chunks = tree.get_num_chunks();
+        let leaves = deepest_leaves(&tree);
+
+        // more detail gives more leaves near the target
+        tree.set_detail(3, |_| TestChunk {});
+
+        assert!(deepest_leaves(&tree) > leaves);
+        assert!(tree.get_num_chunks() > chunks);
+        assert!(!tree.prepare_update(&[target], 3, |_| TestChunk {}));
+
+        // and going back merges them again
+        tree.set_detail(1, |_| TestChunk {});
+
+        assert_eq!(deepest_leaves(&tree), leaves);
+        assert_eq!(tree.get_num_chunks(), chunks);
+        assert!(tree.get_num_chunks_to_delete() > 0);
+    }
+
+    #[test]
+    fn set_detail_after_weighted_and_budgeted() {
+        let targets = [(QuadVec::new(3, 3, 5), 2.0), (QuadVec::new(28, 28, 5), 0.5)];
+
+        // set_detail keeps the weights of the targets
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        while tree.prepare_update_weighted(&targets, 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        tree.set_detail(3, |_| TestChunk {});
+
+        let mut expected = Tree::<TestChunk, QuadVec>::new(0);
+
+        while expected.prepare_update_weighted(&targets, 3, |_| TestChunk {}) {
+            expected.do_update();
+        }
+
+        assert_eq!(tree.sorted_positions(), expected.sorted_positions());
+
+        // and uses the targets from a budgeted update, instead of the ones before it
+        let target = QuadVec::new(45, 17, 6);
+
+        tree.prepare_update_budgeted(&[target], 1, 100000, |_| TestChunk {});
+        tree.do_update();
+        tree.set_detail(2, |_| TestChunk {});
+
+        let mut expected = Tree::<TestChunk, QuadVec>::new(0);
+
+        while expected.prepare_update(&[target], 2, |_| TestChunk {}) {
+            expected.do_update();
+        }
+
+        assert_eq!(tree.sorted_positions(), expected.sorted_positions());
+    }
+
+    #[test]
+    fn prepare_update_budgeted() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+        let target = QuadVec::new(45, 17, 6);
+
+        // a budget that's way less than the target needs
+        for _ in 0..20 {
+            tree.prepare_update_budgeted(&[target], 3, 25, |position| position);
+            tree.do_update();
+            tree.complete_update();
+
+            assert!(tree.get_num_chunks() <= 25);
+            assert!(tree.is_complete_partition());
+        }
+
+        // the chunks went to the target first, so it's as deep as the budget allows
+        assert!(tree.get_num_chunks() > 20);
+        assert!(tree
+            .iter_chunks_ordered()
+            .all(|(position, chunk)| position == *chunk));
+        let deepest = tree
+            .iter_chunks_ordered()
+            .filter(|(position, _)| position.contains_child_node(target))
+            .map(|(position, _)| position.depth)
+            .max();
+
+        assert!(deepest >= Some(3));
+
+        // moving the target still stays in the budget
+        for _ in 0..20 {
+            tree.prepare_update_budgeted(&[QuadVec::new(3, 60, 6)], 3, 25, |position| position);
+            tree.do_update();
+            tree.complete_update();
+
+            assert!(tree.get_num_chunks() <= 25);
+        }
+
+        // and with a large budget, it's the same as a normal update
+        let mut expected = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update_budgeted(&[target], 1, 100000, |position| position) {
+            tree.do_update();
+        }
+
+        while expected.prepare_update(&[target], 1, |position| position) {
+            expected.do_update();
+        }
+
+        assert_eq!(tree.sorted_positions(), expected.sorted_positions());
+    }
+
+    #[test]
+    fn prepare_update_weighted() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        // two targets at opposite corners, with a different weight
+        let targets = [(QuadVec::new(3, 3, 5), 2.0), (QuadVec::new(28, 28, 5), 0.5)];
+
+        while tree.prepare_update_weighted(&targets, 2, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // count the chunks at the deepest level around both targets
+        let high = tree
+            .iter_chunk_positions()
+            .filter(|position| position.depth == 5 && position.x < 16 && position.y < 16)
+            .count();
+        let low = tree
+            .iter_chunk_positions()
+            .filter(|position| position.depth == 5 && position.x >= 16 && position.y >= 16)
+            .count();
+
+        // and the high weight target should have more of them
+        assert!(low > 0);
+        assert!(high > low);
+    }
+
+    #[test]
+    fn regenerate_chunk() {
+        let mut tree = Tree::<(QuadVec, u32), QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(1, 1, 2)], 0, |position| (position, 0)) {
+            tree.do_update();
+        }
+
+        let num_chunks = tree.get_num_chunks();
+
+        // regenerate a chunk with a new version
+        assert!(tree.regenerate_chunk(QuadVec::new(1, 1, 2), |position| (position, 1)));
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(1, 1, 2)),
+            Some(&(QuadVec::new(1, 1, 2), 1))
+        );
+
+        // the rest of the tree stays the same
+        assert_eq!(tree.get_num_chunks(), num_chunks);
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(0, 1, 2)),
+            Some(&(QuadVec::new(0, 1, 2), 0))
+        );
+
+        // and it can't regenerate something that's not there
+        assert!(!tree.regenerate_chunk(QuadVec::new(3, 3, 2), |_| panic!("not in the tree")));
+    }
+
+    #[test]
+    fn chunk_id() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(1, 1, 3)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // get the id of a leaf
+        let (id, _, _) = tree
+            .iter_chunks_with_id()
+            .find(|(_, position, _)| *position == QuadVec::new(1, 1, 3))
+            .unwrap();
+
+        assert_eq!(tree.get_chunk_by_id(id), Some(&QuadVec::new(1, 1, 3)));
+
+        // all ids point to their own chunk
+        for (id, position, chunk) in tree.iter_chunks_with_id() {
+            assert_eq!(*chunk, position);
+            assert_eq!(tree.get_chunk_by_id(id), Some(chunk));
+        }
+
+        // an update somewhere else doesn't change it
+        tree.prepare_update(
+            &[QuadVec::new(1, 1, 3), QuadVec::new(7, 7, 3)],
+            0,
+            |position| position,
+        );
+        tree.do_update();
+
+        assert_eq!(tree.get_chunk_by_id(id), Some(&QuadVec::new(1, 1, 3)));
+
+        // but removing the chunk does
+        while tree.prepare_update(&[QuadVec::new(7, 7, 3)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        assert!(tree
+            .get_chunk_from_position(QuadVec::new(1, 1, 3))
+            .is_none());
+        assert_eq!(tree.get_chunk_by_id(id), None);
+    }
+
+    #[test]
+    fn count_subtree() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // the tree is subdivided towards the corner, so it has 3 levels below the root of 4 chunks
+        assert_eq!(tree.count_subtree(QuadVec::root()), Some((13, 10)));
+
+        // a subtree halfway
+        assert_eq!(tree.count_subtree(QuadVec::new(0, 0, 2)), Some((5, 4)));
+
+        // a single leaf
+        assert_eq!(tree.count_subtree(QuadVec::new(1, 1, 1)), Some((1, 1)));
+
+        // and something that's not in the tree
+        assert_eq!(tree.count_subtree(QuadVec::new(2, 2, 2)), None);
+    }
+
+    #[test]
+    fn is_complete_partition() {
+        let mut tree = Tree::<TestChunk, OctVec>::new(0);
+
+        // nothing covers nothing
+        assert!(!tree.is_complete_partition());
+
+        while tree.prepare_update(&[OctVec::new(5, 2, 7, 3)], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // a normal tree is complete
+        assert!(tree.is_complete_partition());
+
+        // remove a leaf by hand, leaving a hole
+        let node = tree
+            .get_node_index_from_position(OctVec::new(5, 2, 7, 3))
+            .unwrap();
+        let chunk_index = tree.nodes[node].chunk;
+        tree.chunks.swap_remove(chunk_index);
+
+        if chunk_index < tree.chunks.len() {
+            tree.nodes[tree.chunks[chunk_index].index].chunk = chunk_index;
+        }
+
+        assert!(!tree.is_complete_partition());
+
+        // deep trees are complete as well, where leaves are far smaller than the root
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(5, 3, 30)], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert!(tree.is_complete_partition());
+    }
+
+    #[test]
+    fn validate_and_repair() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(4);
+
+        // an empty tree is fine
+        assert!(tree.validate());
+
+        // and so is every step of an update
+        for target in [QuadVec::new(3, 5, 3), QuadVec::new(6, 1, 3)] {
+            while tree.prepare_update(&[target], 1, |_| TestChunk {}) {
+                assert!(tree.validate());
+                tree.do_update();
+                assert!(tree.validate());
+                tree.complete_update();
+                assert!(tree.validate());
+            }
+        }
+
+        // repairing a consistent tree does nothing
+        assert!(tree.repair().is_empty());
+
+        // prepare another update, which is going to be interrupted
+        assert!(tree.prepare_update(&[QuadVec::new(0, 0, 3)], 1, |_| TestChunk {}));
+
+        // cut off the children of a node like do_update does, without freeing them
+        let parent = tree.node_index_of(QuadVec::new(3, 0, 2)).unwrap();
+        assert!(tree.nodes[parent].children.is_some());
+        tree.nodes[parent].children = None;
+
+        // a chunk that points at the wrong node
+        let stale = tree.node_index_of(QuadVec::new(0, 1, 1)).unwrap();
+        let stale_chunk = tree.nodes[stale].chunk;
+        tree.chunks[stale_chunk].index = 0;
+
+        // and a leaf with children that don't exist
+        let leaf = tree.node_index_of(QuadVec::new(1, 1, 1)).unwrap();
+        assert!(tree.nodes[leaf].children.is_none());
+        tree.nodes[leaf].children = NonZeroUsize::new(tree.nodes.len());
+
+        assert!(!tree.validate());
+
+        let report = tree.repair();
+
+        assert_eq!(report.detached_children, 1);
+        assert_eq!(report.stale_back_pointers, 1);
+        assert_eq!(report.orphaned_chunks, 4);
+        assert_eq!(report.free_list_fixes, 4);
+        assert!(report.pending_dropped > 0);
+
+        // the tree is usable again
+        assert!(tree.validate());
+        assert!(tree.is_complete_partition());
+        assert_eq!(tree.get_num_chunks_to_delete(), 4);
+        assert_eq!(tree.get_chunk_position(stale_chunk), QuadVec::new(0, 1, 1));
+        assert!(tree.repair().is_empty());
+
+        // and updates to the same tree as one that was never broken
+        let mut fresh = Tree::<TestChunk, QuadVec>::new(4);
+
+        for tree in [&mut tree, &mut fresh] {
+            while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 1, |_| TestChunk {}) {
+                tree.do_update();
+                tree.complete_update();
+                assert!(tree.validate());
+            }
+        }
+
+        assert_eq!(tree.sorted_positions(), fresh.sorted_positions());
+    }
+
+    #[test]
+    fn count_chunks_in_bounds() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(20, 9, 5)], 2, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // simple random number generator, so the bounds are the same every time
+        let mut seed = 12345_u64;
+        let mut random = |max: u64| {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (seed >> 33) % max
+        };
+
+        for _ in 0..32 {
+            // random bounds
+            let (x, y) = (random(32), random(32));
+            let min = QuadVec::new(x, y, 5);
+            let max = QuadVec::new(x + 1 + random(32 - x), y + 1 + random(32 - y), 5);
+            let max_depth = random(6);
+
+            assert_eq!(
+                tree.count_chunks_in_bounds(min, max, max_depth),
+                tree.iter_all_chunks_in_bounds_and_tree(min, max, max_depth)
+                    .count()
+            );
+        }
+    }
+
+    #[test]
+    fn num_depth_levels() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        // nothing yet
+        assert_eq!(tree.num_depth_levels(), 0);
+
+        // only the root
+        tree.prepare_update(&[QuadVec::new(1, 0, 3)], 0, |_| TestChunk {});
+        tree.do_update();
+        assert_eq!(tree.num_depth_levels(), 1);
+
+        // and depth 0 to 3
+        while tree.prepare_update(&[QuadVec::new(1, 0, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.num_depth_levels(), 4);
+
+        // levels are counted from the root, while the histogram uses the depth of the positions
+        let mut tree = Tree::<TestChunk, QuadVec>::with_root(QuadVec::new(0, 0, 1));
+
+        while tree.prepare_update(&[QuadVec::new(1, 0, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.num_depth_levels(), 3);
+        assert_eq!(tree.depth_histogram(), vec![0, 1, 4, 4]);
+    }
+
+    #[test]
+    fn collect_subtree_leaves() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        let leaves = tree.collect_subtree_leaves(QuadVec::new(0, 0, 1));
+
+        // all leaves in the quadrant, in order
+        assert_eq!(
+            leaves
+                .iter()
+                .map(|(position, _)| *position)
+                .collect::<Vec<_>>(),
+            vec![
+                QuadVec::new(0, 0, 3),
+                QuadVec::new(1, 0, 3),
+                QuadVec::new(0, 1, 3),
+                QuadVec::new(1, 1, 3),
+                QuadVec::new(1, 0, 2),
+                QuadVec::new(0, 1, 2),
+                QuadVec::new(1, 1, 2),
+            ]
+        );
+
+        // with the right chunks
+        assert!(leaves.iter().all(|(position, chunk)| position == *chunk));
+
+        // a leaf only has itself, and things not in the tree have nothing
+        assert_eq!(tree.collect_subtree_leaves(QuadVec::new(1, 1, 1)).len(), 1);
+        assert!(tree
+            .collect_subtree_leaves(QuadVec::new(3, 3, 2))
+            .is_empty());
+    }
+
+    #[test]
+    fn iter_transition_leaves() {
+        let target = QuadVec::new(9, 6, 4);
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[target], 1, |position| position) {
+            tree.do_update();
+        }
+
+        let mut transition = tree
+            .iter_transition_leaves(target, 1)
+            .map(|(position, chunk)| {
+                assert_eq!(position, *chunk);
+                position
+            })
+            .collect::<Vec<_>>();
+
+        transition.sort();
+
+        // with a single target, the ring is all leaves that have finer leaves next to them
+        let mut expected = tree
+            .iter_chunks_ordered()
+            .map(|(position, _)| position)
+            .filter(|&position| {
+                tree.get_chunk_from_position(position.get_child(0))
+                    .is_none()
+            })
+            .filter(|&position| {
+                (0..2).any(|axis| {
+                    [false, true].iter().any(|&positive| {
+                        tree.face_neighbors(position, axis, positive)
+                            .iter()
+                            .any(|(neighbor, _)| neighbor.depth > position.depth)
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        expected.sort();
+
+        assert!(!transition.is_empty());
+        assert_eq!(transition, expected);
+
+        // and the finest leaves around the target aren't in it
+        assert!(!transition.contains(&target));
+    }
+
+    #[test]
+    #[cfg(feature = "ffi")]
+    fn ffi_helpers() {
+        let mut quad = Tree::<QuadVec, QuadVec>::new(0);
+        let mut quad_expected = Tree::<QuadVec, QuadVec>::new(0);
+
+        while quad.prepare_update_xy(&[[0.3, 0.6], [0.8, 0.1]], 5, 1, |position| position) {
+            quad.do_update();
+        }
+
+        let targets = [
+            QuadVec::from_float_coords(0.3, 0.6, 5),
+            QuadVec::from_float_coords(0.8, 0.1, 5),
+        ];
+
+        while quad_expected.prepare_update(&targets, 1, |position| position) {
+            quad_expected.do_update();
+        }
+
+        assert_eq!(quad.diff(&quad_expected), TreeDiff::default());
+        assert_eq!(quad.get_chunk_xy(9, 19, 5), Some(&QuadVec::new(9, 19, 5)));
+
+        let mut oct = Tree::<OctVec, OctVec>::new(0);
+        let mut oct_expected = Tree::<OctVec, OctVec>::new(0);
+
+        while oct.prepare_update_xyz(&[[0.3, 0.6, 0.9]], 3, 1, |position| position) {
+            oct.do_update();
+        }
+
+        while oct_expected.prepare_update(
+            &[OctVec::from_float_coords(0.3, 0.6, 0.9, 3)],
+            1,
+            |position| position,
+        ) {
+            oct_expected.do_update();
+        }
+
+        assert_eq!(oct.diff(&oct_expected), TreeDiff::default());
+        assert_eq!(
+            oct.get_chunk_xyz(2, 4, 7, 3),
+            Some(&OctVec::new(2, 4, 7, 3))
+        );
+    }
+
+    #[test]
+    fn face_neighbors() {
+        let mut tree = Tree::<OctVec, OctVec>::new(0);
+
+        while tree.prepare_update(&[OctVec::new(1, 1, 1, 2)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // a coarse leaf borders four finer leaves
+        let neighbors = tree.face_neighbors(OctVec::new(1, 0, 0, 1), 0, false);
+
+        assert_eq!(
+            neighbors
+                .iter()
+                .map(|(position, _)| *position)
+                .collect::<Vec<_>>(),
+            vec![
+                OctVec::new(1, 0, 0, 2),
+                OctVec::new(1, 1, 0, 2),
+                OctVec::new(1, 0, 1, 2),
+                OctVec::new(1, 1, 1, 2),
+            ]
+        );
+        assert!(neighbors.iter().all(|(position, chunk)| position == *chunk));
+
+        // and the other way around there's only the coarse leaf
+        assert_eq!(
+            tree.face_neighbors(OctVec::new(1, 1, 0, 2), 0, true),
+            vec![(OctVec::new(1, 0, 0, 1), &OctVec::new(1, 0, 0, 1))]
+        );
+
+        // same depth neighbors
+        assert_eq!(
+            tree.face_neighbors(OctVec::new(1, 0, 0, 1), 1, true),
+            vec![(OctVec::new(1, 1, 0, 1), &OctVec::new(1, 1, 0, 1))]
+        );
+
+        // nothing past the edge of the tree, or for nodes not in the tree
+        assert!(tree
+            .face_neighbors(OctVec::new(1, 0, 0, 1), 0, true)
+            .is_empty());
+        assert!(tree
+            .face_neighbors(OctVec::new(3, 0, 0, 2), 0, false)
+            .is_empty());
+    }
+
+    #[test]
+    fn lod_error_grid() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+        let old_target = QuadVec::new(1, 1, 4);
+        let new_target = QuadVec::new(14, 13, 4);
+
+        // an empty tree is behind everywhere
+        assert!(tree
+            .lod_error_grid(&[old_target], 1, 2)
+            .iter()
+            .all(|error| *error < 0));
+
+        while tree.prepare_update(&[old_target], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // fully updated
+        assert_eq!(tree.lod_error_grid(&[old_target], 1, 2), vec![0; 16]);
+
+        // the target moved, but the tree didn't follow yet
+        let grid = tree.lod_error_grid(&[new_target], 1, 2);
+
+        // so it's under refined around the new target, and over refined around the old one
+        assert!(grid[3 * 4 + 3] < 0);
+        assert!(grid[0] > 0);
+
+        // one update later, it's less behind
+        tree.prepare_update(&[new_target], 1, |_| TestChunk {});
+        tree.do_update();
+
+        let after = tree.lod_error_grid(&[new_target], 1, 2);
+
+        assert!(after[3 * 4 + 3] > grid[3 * 4 + 3]);
+
+        // and it catches up eventually
+        while tree.prepare_update(&[new_target], 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.lod_error_grid(&[new_target], 1, 2), vec![0; 16]);
+    }
+
+    #[test]
+    fn summary_grid() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        // empty
+        assert_eq!(tree.summary_grid(1), vec![0; 4]);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 4)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // the corner is refined to depth 4, the rest is coarser
+        assert_eq!(tree.summary_grid(1), vec![4, 1, 1, 1]);
+        assert_eq!(
+            tree.summary_grid(2),
+            vec![
+                4, 2, 1, 1, //
+                2, 2, 1, 1, //
+                1, 1, 1, 1, //
+                1, 1, 1, 1, //
+            ]
+        );
+
+        // and in 3d
+        let mut tree = Tree::<TestChunk, OctVec>::new(0);
+
+        while tree.prepare_update(&[OctVec::new(7, 7, 7, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        let grid = tree.summary_grid(1);
+
+        assert_eq!(grid.len(), 8);
+        assert_eq!(grid[7], 3);
+        assert!(grid[..7].iter().all(|depth| *depth == 1));
+
+        // a tree with a different root only covers the root
+        let mut tree = Tree::<TestChunk, QuadVec>::with_root(QuadVec::new(1, 0, 1));
+
+        while tree.prepare_update(&[QuadVec::new(5, 1, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(
+            tree.summary_grid(2),
+            vec![
+                3, 3, 2, 2, //
+                3, 3, 2, 2, //
+                2, 2, 2, 2, //
+                2, 2, 2, 2, //
+            ]
+        );
+        assert_eq!(
+            tree.lod_error_grid(&[QuadVec::new(5, 1, 3)], 0, 2),
+            vec![0; 16]
+        );
+    }
+
+    #[test]
+    fn is_fully_refined() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        // not refined if there's nothing
+        assert!(!tree.is_fully_refined(QuadVec::new(0, 0, 3), QuadVec::new(8, 8, 3), 3));
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // the corner is refined all the way down
+        assert!(tree.is_fully_refined(QuadVec::new(0, 0, 3), QuadVec::new(2, 2, 3), 3));
+
+        // but the quadrant it's in also has shallower leaves
+        assert!(!tree.is_fully_refined(QuadVec::new(0, 0, 3), QuadVec::new(4, 4, 3), 3));
+
+        // which are refined enough at a lower depth
+        assert!(tree.is_fully_refined(QuadVec::new(0, 0, 3), QuadVec::new(4, 4, 3), 2));
+    }
+
+    #[test]
+    fn from_dense_grid() {
+        // a grid where the lower left quadrant is uniform
+        let cells = |[x, y]: [u64; 2]| if x < 4 && y < 4 { 1 } else { x + y * 8 + 100 };
+
+        let tree = Tree::<u64, QuadVec>::from_dense_grid(
+            3,
+            8,
+            cells,
+            |children| {
+                if children.iter().all(|x| *x == children[0]) {
+                    Some(children[0])
+                } else {
+                    None
+                }
+            },
+            |children| children.iter().max().copied().unwrap(),
+        );
+
+        // the uniform quadrant collapsed into a single leaf
+        assert_eq!(tree.count_subtree(QuadVec::new(0, 0, 1)), Some((1, 1)));
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(0, 0, 1)),
+            Some(&1)
+        );
+
+        // and the other ones have all cells
+        for (x, y) in [(1, 0), (0, 1), (1, 1)] {
+            assert_eq!(tree.count_subtree(QuadVec::new(x, y, 1)), Some((21, 16)));
+        }
+
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(7, 5, 3)),
+            Some(&cells([7, 5]))
+        );
+
+        // nodes that weren't collapsed are made from their children
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(3, 2, 2)),
+            Some(&cells([7, 5]))
+        );
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::root()),
+            Some(&cells([7, 7]))
+        );
+
+        // and the chunks are reachable
+        assert_eq!(tree.get_num_chunks(), 1 + 4 + 3 * 20);
+    }
+
+    #[test]
+    fn on_remove() {
+        // the tree can still be sent and shared between threads
+        fn is_send_sync<T: Send + Sync>() {}
+        is_send_sync::<Tree<TestChunk, QuadVec>>();
+
+        // chunks are numbered, so it's known which one is released
+        let mut tree = Tree::<usize, QuadVec>::new(4);
+        let released = Arc::new(Mutex::new(Vec::new()));
+        let mut created = 0;
+
+        {
+            let released = Arc::clone(&released);
+            tree.set_on_remove(move |_, chunk| released.lock().unwrap().push(*chunk));
+        }
+
+        // move the target away and back, so chunks go through the cache, and some get dropped from it
+        let mut deleted = Vec::new();
+
+        for target in [
+            QuadVec::new(3, 5, 3),
+            QuadVec::new(6, 1, 3),
+            QuadVec::new(3, 5, 3),
+        ] {
+            while tree.prepare_update(&[target], 1, |_| {
+                created += 1;
+                created
+            }) {
+                tree.do_update();
+
+                deleted.extend(tree.get_chunks_to_delete_slice().iter().map(|c| c.chunk));
+
+                tree.complete_update();
+            }
+        }
+
+        // called once for every dropped chunk, and not for chunks that are only cached
+        assert!(!deleted.is_empty());
+        assert_eq!(*released.lock().unwrap(), deleted);
+
+        // so no chunk in the tree was released
+        for chunk in tree.iter_chunks() {
+            assert!(!released.lock().unwrap().contains(chunk));
+        }
+
+        // and every chunk, in the tree or cache, is released once when clearing
+        tree.clear();
+
+        let mut all = released.lock().unwrap().clone();
+        all.sort_unstable();
+        assert_eq!(all, (1..=created).collect::<Vec<_>>());
+
+        // but not anymore once it's removed
+        tree.clear_on_remove();
+
+        while tree.prepare_update(&[QuadVec::new(3, 5, 3)], 1, |_| 0) {
+            tree.do_update();
+        }
+
+        tree.clear();
+
+        assert_eq!(released.lock().unwrap().len(), created);
+    }
+
+    #[test]
+    fn clone() {
+        let mut tree = Tree::<u64, QuadVec>::new(16);
+
+        while tree.prepare_update(&[QuadVec::new(3, 5, 3)], 1, |position| position.x) {
+            tree.do_update();
+            tree.complete_update();
+        }
+
+        // with an update pending as well
+        assert!(tree.prepare_update(&[QuadVec::new(6, 1, 3)], 1, |position| position.x));
+
+        let mut copy = tree.clone();
+        let positions = tree.sorted_positions();
+
+        assert_eq!(copy.sorted_positions(), positions);
+        assert_eq!(copy.get_num_chunks_to_add(), tree.get_num_chunks_to_add());
+
+        // edit the copy
+        *copy
+            .get_chunk_from_position_mut(QuadVec::new(3, 5, 3))
+            .unwrap() = 100;
+        copy.do_update();
+        copy.complete_update();
+
+        // and the original didn't change
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(3, 5, 3)),
+            Some(&3)
+        );
+        assert_eq!(tree.sorted_positions(), positions);
+        assert_ne!(copy.sorted_positions(), positions);
+        assert!(tree.get_num_chunks_to_add() > 0);
+
+        // so it can still do it's own update
+        tree.do_update();
+        tree.complete_update();
+
+        assert_eq!(tree.sorted_positions(), copy.sorted_positions());
+    }
+
+    #[test]
+    fn split_at_depth() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(
+            &[QuadVec::new(37, 12, 6), QuadVec::new(2, 60, 6)],
+            1,
+            |position| position,
+        ) {
+            tree.do_update();
+        }
+
+        let (coarse, fine) = tree.split_at_depth(2);
+
+        // the coarse tree stops at the depth
+        assert_eq!(coarse.depth_histogram().len(), 3);
+        assert!(coarse.validate());
+        assert!(coarse.is_complete_partition());
+
+        // and the fine trees have everything below it
+        assert!(!fine.is_empty());
+
+        for fine in fine.iter() {
+            assert_eq!(fine.get_root().depth, 2);
+            assert!(fine.get_num_chunks() > 1);
+            assert!(fine.validate());
+            assert!(fine.is_complete_partition());
+            assert_eq!(
+                coarse.get_chunk_from_position(fine.get_root()),
+                Some(&fine.get_root())
+            );
+        }
+
+        // merging them back gives the original tree
+        let mut merged = coarse
+            .iter_chunks_ordered()
+            .map(|(position, chunk)| (position, *chunk))
+            .collect::<HashMap<_, _>>();
+
+        for fine in fine.iter() {
+            merged.extend(
+                fine.iter_chunks_ordered()
+                    .map(|(position, chunk)| (position, *chunk)),
+            );
+        }
+
+        let original = tree
+            .iter_chunks_ordered()
+            .map(|(position, chunk)| (position, *chunk))
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(merged, original);
+
+        // splitting deeper than the tree goes gives the same tree
+        let (coarse, fine) = tree.split_at_depth(10);
+
+        assert!(fine.is_empty());
+        assert_eq!(coarse.diff(&tree), TreeDiff::default());
+
+        // and splitting at the root has all detail in one fine tree
+        let (coarse, fine) = tree.split_at_depth(0);
+
+        assert_eq!(coarse.get_num_chunks(), 1);
+        assert_eq!(fine.len(), 1);
+        assert_eq!(fine[0].diff(&tree), TreeDiff::default());
+    }
+
+    #[test]
+    fn diff() {
+        let mut tree = Tree::<u64, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(3, 3, 3)], 0, |_| 0) {
+            tree.do_update();
+        }
+
+        // subdivide a copy of the tree further
+        let mut subdivided = tree.clone();
+        subdivided.prepare_update(&[QuadVec::new(6, 7, 4)], 0, |_| 1);
+
+        let mut new_leaves = (0..subdivided.get_num_chunks_to_add())
+            .map(|i| subdivided.get_position_of_chunk_to_add(i))
+            .collect::<Vec<_>>();
+
+        subdivided.do_update();
+
+        // the new leaves are added
+        let mut diff = tree.diff(&subdivided);
+        diff.added.sort();
+        new_leaves.sort();
+
+        assert_eq!(diff.added, new_leaves);
+        assert!(diff.removed.is_empty());
+        assert!(diff.changed.is_empty());
+
+        // and the other way around they are removed
+        assert_eq!(subdivided.diff(&tree).removed.len(), 4);
+
+        // and changing a chunk shows up as well
+        *subdivided
+            .get_chunk_from_position_mut(QuadVec::new(0, 0, 1))
+            .unwrap() = 5;
+
+        assert_eq!(tree.diff(&subdivided).changed, vec![QuadVec::new(0, 0, 1)]);
+    }
+
+    #[test]
+    fn freeze_region() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(1, 2, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // pin the area around the target
+        let region = QuadVec::new(0, 1, 2);
+        tree.freeze_region(region);
+
+        let frozen = tree
+            .iter_chunk_positions()
+            .filter(|position| region.contains_child_node(*position))
+            .collect::<Vec<_>>();
+
+        // and move the target away
+        while tree.prepare_update(&[QuadVec::new(7, 7, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        // the frozen chunks are still there, as well as the new ones
+        assert_eq!(frozen.len(), 5);
+
+        for position in frozen.iter() {
+            assert!(tree.get_chunk_from_position(*position).is_some());
+        }
+
+        assert!(tree
+            .get_chunk_from_position(QuadVec::new(7, 7, 3))
+            .is_some());
+
+        // and once it's unfrozen they can be merged again
+        assert!(tree.unfreeze_region(region));
+
+        while tree.prepare_update(&[QuadVec::new(7, 7, 3)], 0, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert!(tree
+            .get_chunk_from_position(QuadVec::new(1, 2, 3))
+            .is_none());
+    }
+
+    #[test]
+    fn total_footprint() {
+        // chunk that owns some memory
+        struct Chunk {
+            data: Vec<u8>,
+        }
+
+        let mut tree = Tree::<Chunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(5, 6, 3)], 1, |_| Chunk {
+            data: vec![0; 1000],
+        }) {
+            tree.do_update();
+        }
+
+        // the chunks themselves are included
+        assert_eq!(
+            tree.total_footprint(),
+            tree.memory_usage() + tree.get_num_chunks() * std::mem::size_of::<Chunk>()
+        );
+
+        // and the memory they own as well
+        assert_eq!(
+            tree.total_footprint_with(|chunk| chunk.data.capacity()),
+            tree.total_footprint() + tree.get_num_chunks() * 1000
+        );
+    }
+
+    #[test]
+    fn stats() {
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+        tree.enable_stats();
+
+        let mut expected = UpdateStats::default();
+
+        // move the target around, and keep track of what the updates did
+        for target in [
+            QuadVec::new(3, 3, 4),
+            QuadVec::new(12, 14, 4),
+            QuadVec::new(0, 15, 4),
+        ] {
+            while tree.prepare_update(&[target], 1, |_| TestChunk {}) {
+                let added = tree.get_num_chunks_to_add() as u64;
+                let removed = tree.get_num_chunks_to_remove() as u64;
+
+                expected.updates += 1;
+                expected.total_added += added;
+                expected.total_removed += removed;
+                expected.max_added = expected.max_added.max(added);
+                expected.max_removed = expected.max_removed.max(removed);
+
+                tree.do_update();
+            }
+        }
+
+        assert_eq!(tree.stats(), expected);
+        assert!(expected.total_removed > 0);
+
+        // and all chunks in the tree were added at some point
+        assert_eq!(
+            expected.total_added - expected.total_removed,
+            tree.get_num_chunks() as u64
+        );
+    }
+
+    #[test]
+    fn optimize() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // churn the tree, so there's free nodes and chunks out of order
+        for target in [
+            QuadVec::new(10, 200, 8),
+            QuadVec::new(250, 3, 8),
+            QuadVec::new(128, 128, 8),
+            QuadVec::new(30, 40, 8),
+        ] {
+            while tree.prepare_update(&[target], 2, |position| position) {
+                tree.do_update();
+            }
+        }
+
+        let ordered = tree
+            .iter_chunks_ordered()
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        assert!(tree.nodes.len() > tree.chunks.len());
+
+        // leave an update pending, which should still work afterwards
+        tree.prepare_update(&[QuadVec::new(200, 100, 8)], 2, |position| position);
+
+        tree.optimize();
+
+        // no free nodes
+        assert_eq!(tree.nodes.len(), tree.chunks.len());
+
+        // the chunks are in morton order now
+        assert_eq!(tree.iter_chunk_positions().collect::<Vec<_>>(), ordered);
+        assert_eq!(
+            tree.iter_chunks_ordered()
+                .map(|(position, _)| position)
+                .collect::<Vec<_>>(),
+            ordered
+        );
+
+        // and the tree is still correct
+        assert!(tree.is_complete_partition());
+
+        for position in ordered {
+            assert_eq!(tree.get_chunk_from_position(position), Some(&position));
+        }
+
+        // as well as the pending update
+        tree.do_update();
+
+        while tree.prepare_update(&[QuadVec::new(200, 100, 8)], 2, |position| position) {
+            tree.do_update();
+        }
+
+        assert!(tree.is_complete_partition());
+
+        for (chunk, position) in tree.iter_chunks_and_positions() {
+            assert_eq!(*chunk, position);
+        }
+
+        assert_eq!(tree.iter_chunks_ordered().count(), tree.get_num_chunks());
+    }
+
+    #[test]
+    fn chunk_creator_calls() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+        fn chunk_creator(position: QuadVec) -> QuadVec {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            position
+        }
+
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // build directly to a deep target
+        while tree.prepare_update(&[QuadVec::new(37, 90, 7)], 2, chunk_creator) {
+            tree.do_update();
+        }
+
+        // every node only had it's chunk made once, including the internal ones
+        assert_eq!(CALLS.load(Ordering::Relaxed), tree.get_num_chunks());
+    }
+
+    #[test]
+    fn chunk_creator_closure() {
+        let mut created = 0;
+        let mut tree = Tree::<usize, QuadVec>::new(0);
+
+        // the closure can keep track of how many chunks it made
+        while tree.prepare_update(&[QuadVec::new(37, 90, 7)], 2, |_| {
+            created += 1;
+            created
+        }) {
+            tree.do_update();
+        }
+
+        // which is one per chunk
+        assert_eq!(created, tree.get_num_chunks());
+
+        // and all chunks got a different number
+        let mut numbers = tree.iter_chunks().copied().collect::<Vec<_>>();
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        assert_eq!(numbers.len(), created);
+    }
+
+    #[test]
+    fn with_root() {
+        let root = QuadVec::new(1, 2, 2);
+        let mut tree = Tree::<QuadVec, QuadVec>::with_root(root);
+
+        assert_eq!(tree.get_root(), root);
+
+        while tree.prepare_update(&[QuadVec::new(5, 9, 4)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // only the root and things inside of it are stored
+        assert_eq!(tree.get_num_chunks(), 9);
+        assert!(tree
+            .iter_chunk_positions()
+            .all(|position| root.contains_child_node(position)));
+        assert!(tree.is_complete_partition());
+        assert_eq!(tree.get_chunk_from_position(root), Some(&root));
+        assert_eq!(tree.get_chunk_from_position(QuadVec::root()), None);
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(5, 9, 4)),
+            Some(&QuadVec::new(5, 9, 4))
+        );
+
+        // depths are the same as with the default root
+        assert!(tree.is_fully_refined(QuadVec::new(5, 9, 4), QuadVec::new(6, 10, 4), 4));
+        assert_eq!(tree.iter_chunks_in_depth_range(2, 2).count(), 1);
+
+        // targets outside of the root don't add anything
+        while tree.prepare_update(&[QuadVec::new(12, 12, 4)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.get_num_chunks(), 1);
+        assert_eq!(tree.get_chunk_from_position(QuadVec::new(12, 12, 4)), None);
+    }
+
+    #[test]
+    fn try_get_chunk() {
+        let mut tree = Tree::<QuadVec, QuadVec>::with_root(QuadVec::new(0, 0, 1));
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // out of range, both outside the coordinates and outside the root
+        assert_eq!(
+            tree.try_get_chunk(QuadVec::new(4, 0, 2)),
+            Err(PositionOutOfRange)
+        );
+        assert_eq!(
+            tree.try_get_chunk(QuadVec::new(1, 1, 1)),
+            Err(PositionOutOfRange)
+        );
+        assert_eq!(tree.try_get_chunk(QuadVec::root()), Err(PositionOutOfRange));
+
+        // in range, but not loaded
+        assert_eq!(tree.try_get_chunk(QuadVec::new(0, 0, 3)), Ok(None));
+
+        // and in range and loaded
+        assert_eq!(
+            tree.try_get_chunk(QuadVec::new(1, 0, 2)),
+            Ok(Some(&QuadVec::new(1, 0, 2)))
+        );
+    }
+
+    #[test]
+    fn set_active_where() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 4)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // after updating, only the leaves are active
+        assert_eq!(tree.is_active(QuadVec::root()), Some(false));
+        assert_eq!(tree.is_active(QuadVec::new(0, 0, 4)), Some(true));
+        assert_eq!(tree.is_active(QuadVec::new(1, 1, 1)), Some(true));
+        assert_eq!(tree.is_active(QuadVec::new(3, 3, 2)), None);
+        assert_eq!(tree.iter_active_chunks().count(), 13);
+
+        // hide everything deeper than depth 3
+        tree.set_active_where(|position| position.depth <= 3);
+
+        assert!(tree
+            .iter_active_chunks()
+            .all(|(position, chunk)| position == *chunk && position.depth <= 3));
+        assert_eq!(tree.iter_active_chunks().count(), 9);
+
+        // and the next update activates what it changes again
+        while tree.prepare_update(&[QuadVec::new(0, 0, 3)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.is_active(QuadVec::new(0, 0, 3)), Some(true));
+        assert_eq!(tree.iter_active_chunks().count(), 10);
+    }
+
+    #[test]
+    fn nodes_and_chunks_for() {
+        let targets = [QuadVec::new(5, 60, 6), QuadVec::new(33, 12, 6)];
+
+        let (nodes, chunks) = super::nodes_and_chunks_for(&targets, 2);
+
+        let mut tree = Tree::<TestChunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&targets, 2, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(nodes, tree.nodes.len());
+        assert_eq!(chunks, tree.get_num_chunks());
+
+        // and the same for octrees
+        let targets = [OctVec::new(2, 3, 1, 3)];
+
+        let (nodes, chunks) = super::nodes_and_chunks_for(&targets, 1);
+
+        let mut tree = Tree::<TestChunk, OctVec>::new(0);
+
+        while tree.prepare_update(&targets, 1, |_| TestChunk {}) {
+            tree.do_update();
+        }
+
+        assert_eq!(nodes, tree.nodes.len());
+        assert_eq!(chunks, tree.get_num_chunks());
+    }
+
+    #[test]
+    fn aggregate_up() {
+        let mut tree = Tree::<f64, QuadVec>::new(0);
+
+        // fill the tree up to depth 2 everywhere, with a different value for each leaf
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 4, |position| {
+            (position.x + position.y * 4) as f64
+        }) {
+            tree.do_update();
+        }
+
+        assert_eq!(tree.get_num_chunks(), 21);
+
+        tree.aggregate_up(|children| children.iter().copied().sum::<f64>() / children.len() as f64);
+
+        // the root has the mean of all leaves
+        assert_eq!(tree.get_chunk_from_position(QuadVec::root()), Some(&7.5));
+
+        // and the other nodes the mean of the leaves below them
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(1, 1, 1)),
+            Some(&12.5)
+        );
+
+        // while the leaves stay the same
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(3, 2, 2)),
+            Some(&11.0)
+        );
+    }
+
+    #[test]
+    fn edit_bounds() {
+        let mut tree = Tree::<f64, QuadVec>::new(0);
+        let mean =
+            |children: &[&f64]| children.iter().copied().sum::<f64>() / children.len() as f64;
+
+        // nothing to edit yet
+        tree.edit_bounds(
+            QuadVec::new(0, 0, 1),
+            QuadVec::new(1, 1, 1),
+            2,
+            |_, _| panic!("tree is empty"),
+            mean,
+        );
+
+        // same tree as for aggregate_up
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 4, |position| {
+            (position.x + position.y * 4) as f64
+        }) {
+            tree.do_update();
+        }
+
+        tree.aggregate_up(mean);
+
+        // paint the lowest corner
+        let mut edited = Vec::new();
+        let combined = std::cell::Cell::new(0);
+
+        tree.edit_bounds(
+            QuadVec::new(0, 0, 2),
+            QuadVec::new(2, 2, 2),
+            2,
+            |position, chunk| {
+                edited.push(position);
+                *chunk = 100.0;
+            },
+            |children| {
+                combined.set(combined.get() + 1);
+                mean(children)
+            },
+        );
+
+        // only the leaves in the bounds changed
+        edited.sort();
+        assert_eq!(
+            edited,
+            vec![
+                QuadVec::new(0, 0, 2),
+                QuadVec::new(0, 1, 2),
+                QuadVec::new(1, 0, 2),
+                QuadVec::new(1, 1, 2),
+            ]
+        );
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(2, 2, 2)),
+            Some(&10.0)
+        );
+
+        // and their ancestors were recomputed, but not the other nodes
+        assert_eq!(combined.get(), 2);
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(0, 0, 1)),
+            Some(&100.0)
+        );
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::new(1, 1, 1)),
+            Some(&12.5)
+        );
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::root()),
+            Some(&((100.0 + 4.5 + 10.5 + 12.5) / 4.0))
+        );
+
+        // leaves deeper than the max depth aren't edited
+        tree.edit_bounds(
+            QuadVec::new(0, 0, 2),
+            QuadVec::new(4, 4, 2),
+            1,
+            |_, _| panic!("leaves are too deep"),
+            mean,
+        );
+    }
+
+    #[test]
+    fn encode_topology() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        // an empty tree has no bits, and a tree with only a root has one
+        assert!(tree.encode_topology().is_empty());
+        assert_eq!(
+            Tree::<QuadVec, QuadVec>::decode_topology(&[], |position| position)
+                .unwrap()
+                .get_num_chunks(),
+            0
+        );
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 1)], 0, |position| position) {
+            tree.do_update();
+        }
+
+        // the root has children, and they don't
+        assert_eq!(tree.encode_topology(), vec![0b00001]);
+
+        while tree.prepare_update(
+            &[QuadVec::new(37, 12, 6), QuadVec::new(2, 60, 6)],
+            1,
+            |position| position,
+        ) {
+            tree.do_update();
+        }
 
-                // now, we can try to add this chunk into the cache
-                // first, remove any extra nodes if they are in the cache
-                while self.chunk_cache.len() > self.cache_size {
-                    if let Some(chunk_position) = self.cache_queue.pop_front() {
-                        // check if the chunk is inside the map
-                        if let Some(cached_chunk) = self.chunk_cache.remove(&chunk_position) {
-                            // if it is, it's removed, so we need to push it to the chunks that are going to be deleted
-                            self.chunks_to_delete.push(ToDeleteContainer {
-                                position: chunk_position,
-                                chunk: cached_chunk,
-                            });
-                        }
-                    } else {
-                        // just break, otherwise we'll be stuck in an infinite loop
-                        break;
-                    }
-                }
+        // one bit per node
+        let bits = tree.encode_topology();
 
-                // then assign this chunk into the cache
-                if let Some(cached_chunk) =
-                    self.chunk_cache.insert(old_chunk.position, old_chunk.chunk)
-                {
-                    // there might have been another cached chunk
-                    self.chunks_to_delete.push(ToDeleteContainer {
-                        position: old_chunk.position,
-                        chunk: cached_chunk,
-                    });
-                }
+        assert_eq!(bits.len(), (tree.get_num_chunks() + 7) / 8);
 
-                // and make sure it's tracked
-                self.cache_queue.push_back(old_chunk.position);
-            }
+        // and the decoded tree has the same leaves
+        let decoded =
+            Tree::<QuadVec, QuadVec>::decode_topology(&bits, |position| position).unwrap();
 
-            // and properly set the chunk pointer of the node of the chunk we just moved, if any
-            // if we removed the last chunk, no need to update anything
-            if chunk_index < self.chunks.len() {
-                self.nodes[self.chunks[chunk_index].index].chunk = chunk_index;
+        let leaves = |tree: &Tree<QuadVec, QuadVec>| {
+            tree.collect_subtree_leaves(QuadVec::root())
+                .into_iter()
+                .map(|(position, _)| position)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(leaves(&decoded), leaves(&tree));
+        assert_eq!(decoded.get_num_chunks(), tree.get_num_chunks());
+        assert_eq!(decoded.encode_topology(), bits);
+        assert!(decoded
+            .iter_chunks_ordered()
+            .all(|(position, chunk)| position == *chunk));
+
+        // a structure that goes deeper than the positions can is rejected, without making any chunks
+        assert!(Tree::<QuadVec, QuadVec>::decode_topology(&[0xff; 32], |_| {
+            panic!("no chunks should be made")
+        })
+        .is_none());
+
+        // and one that goes right up to it is fine
+        let mut deepest = vec![0xff; 7];
+        deepest.push(0x7f);
+
+        let decoded =
+            Tree::<QuadVec, QuadVec>::decode_topology(&deepest, |position| position).unwrap();
+
+        assert_eq!(
+            decoded.depth_histogram().len(),
+            QuadVec::MAX_DEPTH as usize + 1
+        );
+        assert!(decoded.validate());
+    }
+
+    #[test]
+    fn serialize_structure() {
+        // empty trees stay empty
+        let structure = Tree::<QuadVec, QuadVec>::new(0).serialize_structure();
+        assert!(structure.nodes.is_empty());
+        assert_eq!(
+            Tree::<QuadVec, QuadVec>::rebuild_from_structure(structure, |position| position)
+                .unwrap()
+                .get_num_chunks(),
+            0
+        );
+
+        // make a tree with some free nodes, in a part of the space
+        let mut tree = Tree::<QuadVec, QuadVec>::with_root(QuadVec::new(1, 0, 1));
+
+        for target in [QuadVec::new(37, 12, 6), QuadVec::new(60, 2, 6)] {
+            while tree.prepare_update(&[target], 1, |position| position) {
+                tree.do_update();
             }
         }
 
-        // add new chunks
-        // we'll drain the vector here as well, as we won't need it anymore afterward
-        for (parent_index, ToAddContainer { position, chunk }) in chunks_to_add_iter {
-            // add the node
-            let new_node_index = match self.free_list.pop_front() {
-                Some(x) => {
-                    // reuse a free node
-                    self.nodes[x] = TreeNode {
-                        children: None,
-                        chunk: self.chunks.len(),
-                    };
-                    self.chunks.push(ChunkContainer {
-                        index: x,
-                        chunk,
-                        position,
-                    });
-                    x
-                }
-                None => {
-                    // otherwise, use a new index
-                    self.nodes.push(TreeNode {
-                        children: None,
-                        chunk: self.chunks.len(),
-                    });
-                    self.chunks.push(ChunkContainer {
-                        index: self.nodes.len() - 1,
-                        chunk,
-                        position,
-                    });
-                    self.nodes.len() - 1
-                }
-            };
+        assert!(!tree.free_list.is_empty());
 
-            // correctly set the children of the parent node.
-            // because the last node we come by in with ordered iteration is on num_children - 1, we need to set it as such].
-            // node 0 is the root, so the last child it has will be on num_children.
-            // then subtracting num_children - 1 from that gives us node 1, which is the first child of the root.
-            if new_node_index >= L::num_children() {
-                // because we loop in order, and our nodes are contiguous, the first node of the children got added on index i - (num children - 1)
-                // so we need to adjust for that
-                self.nodes[parent_index].children =
-                    NonZeroUsize::new(new_node_index - (L::num_children() - 1));
+        // and a chunk that's not active
+        tree.set_active_where(|position| position == QuadVec::new(15, 0, 4));
+
+        let structure = tree.serialize_structure();
+        let mut rebuilt =
+            Tree::<QuadVec, QuadVec>::rebuild_from_structure(structure.clone(), |position| {
+                position
+            })
+            .unwrap();
+
+        // same positions
+        let positions =
+            |tree: &mut Tree<QuadVec, QuadVec>| tree.iter_chunk_positions().collect::<HashSet<_>>();
+
+        assert_eq!(positions(&mut rebuilt), positions(&mut tree));
+
+        // in the same place
+        assert!(rebuilt.validate());
+        assert_eq!(rebuilt.get_root(), tree.get_root());
+        assert_eq!(rebuilt.free_list, tree.free_list);
+        assert_eq!(rebuilt.serialize_structure(), structure);
+
+        for index in 0..tree.nodes.len() {
+            let (a, b) = (rebuilt.nodes[index], tree.nodes[index]);
+
+            assert_eq!(a.children, b.children);
+
+            // free nodes don't have a depth
+            if !tree.free_list.contains(&index) {
+                assert_eq!(a.depth, b.depth);
+                assert_eq!(a.chunk, b.chunk);
             }
         }
 
-        // if there's only chunk left, we know it's the root, so we can get rid of all free nodes and unused nodes
-        if self.chunks.len() == 1 {
-            self.free_list.clear();
-            self.nodes.resize(
-                1,
-                TreeNode {
-                    children: None,
-                    chunk: 0,
-                },
+        for i in 0..tree.get_num_chunks() {
+            assert_eq!(*rebuilt.get_chunk(i), rebuilt.get_chunk_position(i));
+            assert_eq!(rebuilt.get_chunk_position(i), tree.get_chunk_position(i));
+            assert_eq!(rebuilt.chunks[i].active, tree.chunks[i].active);
+        }
+
+        // and it can be stored with serde as well
+        #[cfg(feature = "serde")]
+        {
+            let json = serde_json::to_string(&structure).unwrap();
+            assert_eq!(
+                serde_json::from_str::<SerializedTree<QuadVec>>(&json).unwrap(),
+                structure
             );
         }
 
-        // and clear all internal arrays, so if this method is accidentally called twice, no weird behavior would happen
-        self.chunks_to_add.clear();
-        self.chunks_to_remove.clear();
-        self.chunks_to_activate.clear();
-        self.chunks_to_deactivate.clear();
-    }
+        // broken structures are refused, without making any chunks
+        let rebuild = |structure: SerializedTree<QuadVec>| {
+            Tree::<QuadVec, QuadVec>::rebuild_from_structure(structure, |_| panic!("made a chunk"))
+        };
 
-    /// Completes the update by removing all chunks that can't be stored anymore permanently
-    #[inline]
-    pub fn complete_update(&mut self) {
-        // just clear the chunks to be deleted
-        self.chunks_to_delete.clear();
-    }
+        let parent = (0..structure.nodes.len())
+            .find(|&i| matches!(structure.nodes[i], Some(node) if node.children.is_some()))
+            .unwrap();
 
-    /// clears the tree, removing all chunks and internal lists and cache
-    #[inline]
-    pub fn clear(&mut self) {
-        self.chunks.clear();
-        self.nodes.clear();
-        self.free_list.clear();
-        self.chunks_to_add.clear();
-        self.chunks_to_remove.clear();
-        self.chunks_to_activate.clear();
-        self.chunks_to_deactivate.clear();
-        self.chunks_to_delete.clear();
-        self.processing_queue.clear();
-        self.cache_queue.clear();
-        self.chunk_cache.clear();
-    }
+        // children pointing back at the root
+        let mut broken = structure.clone();
+        broken.nodes[parent].as_mut().unwrap().children = Some(0);
+        assert!(rebuild(broken).is_none());
 
-    /// Shrinks all internal buffers to fit, reducing memory usage.
-    /// Due to most of the intermediate processing buffers being cleared after an update is done, the next update might take longer due to needing to reallocate the memory.
-    #[inline]
-    pub fn shrink(&mut self) {
-        // it should be possible to also shrink the nodes as well, and remove the free space, but this would be rather dificult to do
-        // because we have groups of num_children
-        // I'm leaving it out for now
-        self.chunks.shrink_to_fit();
-        self.nodes.shrink_to_fit();
-        self.free_list.shrink_to_fit();
-        self.chunks_to_add.shrink_to_fit();
-        self.chunks_to_remove.shrink_to_fit();
-        self.chunks_to_activate.shrink_to_fit();
-        self.chunks_to_deactivate.shrink_to_fit();
-        self.chunks_to_delete.shrink_to_fit();
-        self.processing_queue.shrink_to_fit();
-        self.cache_queue.shrink_to_fit();
-    }
+        // or at a node higher up
+        let mut broken = structure.clone();
+        let child = structure.nodes[0].unwrap().children.unwrap();
+        broken.nodes[child].as_mut().unwrap().children = Some(child);
+        assert!(rebuild(broken).is_none());
 
-    /// resizes the current cache size
-    /// actual resizing happens on the next update
-    #[inline]
-    pub fn set_cache_size(&mut self, cache_size: usize) {
-        self.cache_size = cache_size;
+        // children out of range
+        let mut broken = structure.clone();
+        broken.nodes[parent].as_mut().unwrap().children = Some(structure.nodes.len() - 1);
+        assert!(rebuild(broken).is_none());
+
+        // two nodes with the same chunk
+        let mut broken = structure.clone();
+        broken.nodes[child].as_mut().unwrap().chunk = structure.nodes[0].unwrap().chunk;
+        assert!(rebuild(broken).is_none());
+
+        // a node in the tree that's also free
+        let mut broken = structure.clone();
+        broken.free_list.push(child);
+        assert!(rebuild(broken).is_none());
+
+        // a free node that's not in the free list
+        let mut broken = structure.clone();
+        broken.free_list.pop();
+        assert!(rebuild(broken).is_none());
+
+        // and a root that isn't valid
+        let mut broken = structure;
+        broken.root = QuadVec::new(5, 0, 1);
+        assert!(rebuild(broken).is_none());
     }
 
-    // gets a chunk from the cache, otehrwise generates one from the given function
-    #[inline]
-    fn get_chunk_from_cache(&mut self, position: L, chunk_creator: fn(L) -> C) -> C {
-        if let Some(chunk) = self.chunk_cache.remove(&position) {
-            chunk
-        } else {
-            chunk_creator(position)
+    #[test]
+    fn chunk_limit() {
+        let mut tree = Tree::<QuadVec, QuadVec>::with_chunk_limit(17);
+
+        // region that's used often, and a target that moves around
+        let used = QuadVec::new(0, 0, 3);
+
+        for i in 0..40 {
+            let target = QuadVec::new(7 - i % 4, 7 - i / 4 % 4, 3);
+
+            // updating until it's done still stops, as nodes are left unsubdivided instead of merging chunks the targets want
+            let mut num_updates = 0;
+
+            while tree.prepare_update(&[used, target], 0, |position| position) {
+                tree.do_update();
+                num_updates += 1;
+
+                // and the limit is always kept
+                assert!(tree.get_num_chunks() <= 17);
+                assert!(tree.is_complete_partition());
+            }
+
+            assert!(num_updates < 10);
         }
-    }
-}
 
-impl<C, L> Default for Tree<C, L>
-where
-    C: Sized,
-    L: LodVec,
-{
-    /// creates a new, empty tree, with no cache
-    fn default() -> Self {
-        Self::new(0)
-    }
-}
+        // the two targets need more chunks than the limit, so it's close to full
+        assert!(tree.get_num_chunks() > 17 - 4);
+        assert!(tree
+            .iter_chunks_ordered()
+            .all(|(position, chunk)| position == *chunk));
 
-#[cfg(test)]
-mod tests {
+        // the root is always kept, even without room for it
+        let mut tree = Tree::<QuadVec, QuadVec>::with_chunk_limit(0);
 
-    use super::*;
-    use crate::coords::*;
+        while tree.prepare_update(&[used], 0, |position| position) {
+            tree.do_update();
+        }
 
-    struct TestChunk;
+        assert_eq!(tree.get_num_chunks(), 1);
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::root()),
+            Some(&QuadVec::root())
+        );
+    }
 
     #[test]
-    fn new_tree() {
-        // make a tree
-        let mut tree = Tree::<TestChunk, QuadVec>::new(64);
+    fn chunk_limit_evicts_least_recently_used() {
+        let mut tree = Tree::<QuadVec, QuadVec>::with_chunk_limit(1000);
 
-        // as long as we need to update, do so
-        while tree.prepare_update(&[QuadVec::new(128, 128, 32)], 8, |_| TestChunk {}) {
-            // and actually update
+        // two regions, that both fit in the limit
+        let used = QuadVec::new(0, 0, 3);
+        let unused = QuadVec::new(7, 7, 3);
+
+        while tree.prepare_update(&[used, unused], 0, |position| position) {
             tree.do_update();
         }
 
-        // and move the target
-        while tree.prepare_update(&[QuadVec::new(16, 8, 16)], 8, |_| TestChunk {}) {
-            // and actually update
+        let num_chunks = tree.get_num_chunks();
+
+        // iterating counts as using a chunk, the same as getting it, so the used region is used last
+        tree.get_chunk_from_position(unused);
+        tree.iter_leaves_in_bounds_morton(QuadVec::new(0, 0, 1), QuadVec::new(1, 1, 1), 3)
+            .for_each(drop);
+
+        // lowering the limit merges the least recently used chunks
+        tree.set_chunk_limit(Some(num_chunks - 8));
+
+        while tree.prepare_update(&[used, unused], 0, |position| position) {
             tree.do_update();
         }
 
-        // get the resulting chunk from a search
-        let found_chunk = tree.get_chunk_from_position(QuadVec::new(16, 8, 16));
+        assert!(tree.get_num_chunks() <= num_chunks - 8);
+        assert!(tree.is_complete_partition());
 
-        // and find the resulting chunk
-        println!("{:?}", found_chunk.is_some());
+        // the used region is still there, and the other one lost it's deepest chunks
+        assert_eq!(tree.get_chunk_from_position(used), Some(&used));
+        assert!(tree.get_chunk_from_position(unused).is_none());
+    }
 
-        // and make the tree have no items
-        while tree.prepare_update(&[], 8, |_| TestChunk {}) {
-            // and actually update
+    #[test]
+    fn drain_dirty() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |position| position) {
             tree.do_update();
         }
 
-        // and do the same for an octree
-        let mut tree = Tree::<TestChunk, OctVec>::new(64);
+        // nothing is dirty at first
+        assert_eq!(tree.drain_dirty().count(), 0);
 
-        // as long as we need to update, do so
-        while tree.prepare_update(&[OctVec::new(128, 128, 128, 32)], 8, |_| TestChunk {}) {
-            // and actually update
+        tree.mark_dirty(QuadVec::new(1, 0, 2));
+        tree.mark_dirty(QuadVec::new(1, 1, 1));
+        tree.mark_dirty(QuadVec::new(1, 0, 2));
+
+        // and positions not in the tree are skipped
+        tree.mark_dirty(QuadVec::new(3, 3, 2));
+
+        let mut dirty = tree.drain_dirty().collect::<Vec<_>>();
+        dirty.sort_by_key(|(position, _)| *position);
+
+        assert_eq!(
+            dirty,
+            vec![
+                (QuadVec::new(1, 0, 2), &QuadVec::new(1, 0, 2)),
+                (QuadVec::new(1, 1, 1), &QuadVec::new(1, 1, 1)),
+            ]
+        );
+
+        // after draining, nothing is dirty anymore
+        assert_eq!(tree.drain_dirty().count(), 0);
+    }
+
+    #[test]
+    fn sample_cell() {
+        let mut tree = Tree::<QuadVec, QuadVec>::new(0);
+
+        assert_eq!(tree.sample_cell([0, 0], 4), None);
+
+        while tree.prepare_update(&[QuadVec::new(0, 0, 2)], 0, |position| position) {
             tree.do_update();
         }
 
-        // and move the target
-        while tree.prepare_update(&[OctVec::new(16, 8, 32, 16)], 8, |_| TestChunk {}) {
-            // and actually update
+        // a cell inside a coarse leaf gives that leaf
+        assert_eq!(tree.sample_cell([13, 2], 4), Some(&QuadVec::new(1, 0, 1)));
+
+        // and one in the refined corner gives the deeper leaf
+        assert_eq!(tree.sample_cell([5, 6], 4), Some(&QuadVec::new(1, 1, 2)));
+
+        // as well as cells that are bigger than the leaves
+        assert_eq!(tree.sample_cell([0, 0], 1), Some(&QuadVec::new(0, 0, 1)));
+
+        // and nothing outside the tree
+        assert_eq!(tree.sample_cell([16, 0], 4), None);
+
+        // same for octrees
+        let mut tree = Tree::<OctVec, OctVec>::new(0);
+
+        while tree.prepare_update(&[OctVec::new(0, 0, 0, 2)], 0, |position| position) {
             tree.do_update();
         }
 
-        // and make the tree have no items
-        while tree.prepare_update(&[], 8, |_| TestChunk {}) {
-            // and actually update
+        assert_eq!(
+            tree.sample_cell([7, 2, 1], 3),
+            Some(&OctVec::new(1, 0, 0, 1))
+        );
+    }
+
+    #[test]
+    fn translate() {
+        #[derive(Debug)]
+        struct Chunk {
+            original: Option<QuadVec>,
+        }
+
+        // chunks that remember where they were made
+        let mut tree = Tree::<Chunk, QuadVec>::new(0);
+
+        while tree.prepare_update(&[QuadVec::new(4, 4, 3)], 0, |position| Chunk {
+            original: Some(position),
+        }) {
             tree.do_update();
         }
+
+        let before = tree.iter_chunk_positions().collect::<Vec<_>>();
+
+        // one chunk is marked as dirty, and one that will be deleted
+        tree.mark_dirty(QuadVec::new(4, 5, 3));
+        tree.mark_dirty(QuadVec::new(3, 3, 2));
+
+        // same for frozen regions
+        tree.freeze_region(QuadVec::new(1, 2, 2));
+        tree.freeze_region(QuadVec::new(7, 0, 3));
+
+        // move it by 2 and -2 chunks at depth 3
+        tree.translate([2, -2], 3, |_| Chunk { original: None });
+
+        let mut num_moved = 0;
+
+        // the chunks at depth 3 moved by the full delta, and at depth 2 by half of it
+        for position in before {
+            let expected = match position.depth {
+                3 => QuadVec::new(position.x + 2, position.y - 2, 3),
+                2 => QuadVec::new(position.x + 1, position.y - 1, 2),
+                _ => continue,
+            };
+
+            // chunks that are moved outside the tree don't exist anymore
+            if expected.x >= 1 << expected.depth {
+                assert!(tree.get_chunk_from_position(expected).is_none());
+                continue;
+            }
+
+            assert_eq!(
+                tree.get_chunk_from_position(expected).unwrap().original,
+                Some(position)
+            );
+
+            num_moved += 1;
+        }
+
+        // all chunks at depth 3, and half of the ones at depth 2
+        assert_eq!(num_moved, 6);
+
+        // the root can't be moved, so it is made again
+        assert_eq!(
+            tree.get_chunk_from_position(QuadVec::root())
+                .unwrap()
+                .original,
+            None
+        );
+
+        // and all chunks are reachable from the root
+        assert_eq!(tree.iter_chunks_ordered().count(), tree.get_num_chunks());
+        assert!(tree.get_num_chunks_to_delete() > 0);
+
+        // the dirty chunk moved along
+        assert_eq!(
+            tree.drain_dirty()
+                .map(|(position, _)| position)
+                .collect::<Vec<_>>(),
+            vec![QuadVec::new(6, 3, 3)]
+        );
+
+        // and so did the frozen region
+        assert_eq!(
+            tree.frozen.iter().copied().collect::<Vec<_>>(),
+            vec![QuadVec::new(2, 1, 2)]
+        );
+
+        // an empty tree stays empty
+        let mut tree = Tree::<Chunk, QuadVec>::new(0);
+        tree.translate([1, 1], 3, |_| panic!("no chunks should be made"));
+
+        assert_eq!(tree.get_num_chunks(), 0);
+        assert!(tree.validate());
+
+        // and coords can be moved at any depth, without overflowing
+        assert_eq!(translate_coord(5, 1, 63, 63), Some(6));
+        assert_eq!(translate_coord(1, 1, 2, 63), None);
+        assert_eq!(translate_coord(1, 1 << 61, 2, 63), Some(2));
+        assert_eq!(translate_coord(5, 1, 63, 2), Some(5 + (1 << 61)));
+        assert_eq!(translate_coord(5, 1, 64, 0), None);
+        assert_eq!(translate_coord(5, 0, 64, 0), Some(5));
+        assert_eq!(translate_coord(u64::MAX, 1, 64, 64), None);
+        assert_eq!(translate_coord(0, -1, 64, 64), None);
     }
 }